@@ -1,7 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
 use rand::{seq::SliceRandom, thread_rng};
 
-use thunderdome::Arena;
+use thunderdome::{Arena, ReusePolicy};
 
 pub fn iter(c: &mut Criterion) {
     let mut arena = Arena::new();
@@ -101,12 +101,60 @@ pub fn reinsert_random(c: &mut Criterion) {
     });
 }
 
+pub fn reuse_policies(c: &mut Criterion) {
+    let policies = [
+        ("lifo", ReusePolicy::Lifo),
+        ("fifo", ReusePolicy::Fifo),
+        ("lowest_slot", ReusePolicy::LowestSlot),
+    ];
+
+    for (name, policy) in policies {
+        let mut arena: Arena<u64> = Arena::with_reuse_policy(policy);
+
+        let mut keys = Vec::new();
+        for i in 0..10_000 {
+            keys.push(arena.insert(i));
+        }
+        keys.shuffle(&mut thread_rng());
+
+        c.bench_function(&format!("remove_random 10k ({})", name), |b| {
+            b.iter_batched_ref(
+                || arena.clone(),
+                |arena| {
+                    for &k in &keys {
+                        black_box(arena.remove(k));
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+
+        let mut emptied = arena.clone();
+        for &k in &keys {
+            emptied.remove(k);
+        }
+
+        c.bench_function(&format!("reinsert_random 10k ({})", name), |b| {
+            b.iter_batched_ref(
+                || emptied.clone(),
+                |arena| {
+                    for i in 0..10_000 {
+                        black_box(arena.insert(i));
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
 criterion_group!(
     benches,
     iter,
     insert,
     get_random,
     remove_random,
-    reinsert_random
+    reinsert_random,
+    reuse_policies
 );
 criterion_main!(benches);
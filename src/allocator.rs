@@ -0,0 +1,162 @@
+use crate::arena::{Arena, Index};
+use crate::iter::IterLive;
+use crate::policy::ReusePolicy;
+
+/// Allocates generational indices without storing any values.
+///
+/// `IndexAllocator` hands out indices exactly like [`Arena`] does, reusing
+/// freed slots and bumping their generations, but leaves storing values up to
+/// the caller. This is useful for keeping values in several separate storages
+/// keyed by the same index, like component storages in an ECS.
+///
+/// Indices from an `IndexAllocator` are interchangeable with indices from an
+/// `Arena`: an allocator and an arena that go through the same sequence of
+/// allocations and frees produce identical indices.
+#[derive(Debug, Clone, Default)]
+pub struct IndexAllocator {
+    arena: Arena<()>,
+}
+
+impl IndexAllocator {
+    /// Construct an empty allocator.
+    pub const fn new() -> Self {
+        Self {
+            arena: Arena::new(),
+        }
+    }
+
+    /// Construct an empty allocator with space to hold exactly `capacity`
+    /// live indices without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            arena: Arena::with_capacity(capacity),
+        }
+    }
+
+    /// Construct an empty allocator that reuses free slots according to the
+    /// given [`ReusePolicy`].
+    pub const fn with_reuse_policy(policy: ReusePolicy) -> Self {
+        Self {
+            arena: Arena::with_reuse_policy(policy),
+        }
+    }
+
+    /// Return the number of live indices in the allocator.
+    pub const fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns whether the allocator has no live indices.
+    pub const fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    /// Return the number of indices the allocator can hold without
+    /// allocating, including the indices that are currently live.
+    pub fn capacity(&self) -> usize {
+        self.arena.capacity()
+    }
+
+    /// Reserve capacity for at least `additional` more indices to be
+    /// allocated.
+    pub fn reserve(&mut self, additional: usize) {
+        self.arena.reserve(additional);
+    }
+
+    /// Allocate a new live index.
+    pub fn allocate(&mut self) -> Index {
+        self.arena.insert(())
+    }
+
+    /// Compute the `Index` that the next call to [`IndexAllocator::allocate`]
+    /// would produce, without mutating the allocator.
+    pub fn next_index(&self) -> Index {
+        self.arena.next_index()
+    }
+
+    /// Free the given index so that its slot can be reused. Returns `false` if
+    /// the index was not live.
+    pub fn free(&mut self, index: Index) -> bool {
+        self.arena.remove(index).is_some()
+    }
+
+    /// Returns true if the given index is live in the allocator.
+    pub fn is_live(&self, index: Index) -> bool {
+        self.arena.contains(index)
+    }
+
+    /// Free every live index.
+    pub fn clear(&mut self) {
+        self.arena.clear();
+    }
+
+    /// Iterate over all of the live indices in the allocator.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_live(&self) -> IterLive<'_> {
+        IterLive {
+            inner: self.arena.iter(),
+        }
+    }
+
+    /// Quarantine slots freed from now on for the given number of epochs. See
+    /// [`Arena::set_quarantine`].
+    pub fn set_quarantine(&mut self, epochs: u32) {
+        self.arena.set_quarantine(epochs);
+    }
+
+    /// Advance the allocator to its next epoch. See
+    /// [`Arena::advance_epoch`].
+    pub fn advance_epoch(&mut self) {
+        self.arena.advance_epoch();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IndexAllocator;
+    use crate::Arena;
+
+    #[test]
+    fn allocate_free() {
+        let mut allocator = IndexAllocator::new();
+        let a = allocator.allocate();
+        let b = allocator.allocate();
+        assert_eq!(allocator.len(), 2);
+        assert!(allocator.is_live(a));
+
+        assert!(allocator.free(a));
+        assert!(!allocator.free(a));
+        assert!(!allocator.is_live(a));
+        assert!(allocator.is_live(b));
+
+        let predicted = allocator.next_index();
+        let c = allocator.allocate();
+        assert_eq!(predicted, c);
+        assert_eq!(c.slot(), a.slot());
+        assert_ne!(c, a);
+    }
+
+    #[test]
+    fn matches_arena() {
+        let mut allocator = IndexAllocator::new();
+        let mut arena = Arena::new();
+
+        for i in 0..8 {
+            assert_eq!(allocator.allocate(), arena.insert(i));
+        }
+
+        for (index, _) in arena.clone().iter().filter(|(_, &i)| i % 3 == 0) {
+            assert!(allocator.free(index));
+            arena.remove(index);
+        }
+
+        for i in 0..4 {
+            assert_eq!(allocator.allocate(), arena.insert(i));
+        }
+
+        assert!(allocator
+            .iter_live()
+            .eq(arena.iter().map(|(index, _)| index)));
+    }
+}
@@ -1,6 +1,7 @@
-use core::convert::TryInto;
-use core::mem::replace;
-use core::ops;
+use core::convert::{Infallible, TryInto};
+use core::marker::PhantomData;
+use core::mem::{replace, swap};
+use core::ops::{self, RangeBounds};
 
 // Vec is part of the prelude when std is enabled.
 #[cfg(not(feature = "std"))]
@@ -8,16 +9,35 @@ use alloc::vec::Vec;
 
 use crate::free_pointer::FreePointer;
 use crate::generation::Generation;
-use crate::iter::{Drain, IntoIter, IntoValues, Iter, IterMut, Values, ValuesMut};
+use crate::iter::{
+    Drain, DrainRange, ExtractIf, FreeSlots, IntoIter, IntoKeys, IntoValues, Iter, IterMut,
+    IterSlots, Keys, Values, ValuesMut,
+};
+use crate::policy::ReusePolicy;
+use crate::quarantine::Quarantine;
+use crate::storage::sealed::ArenaLike;
+use crate::storage::{SlotStorage, SlotStorageMut};
 
 /// Container that can have elements inserted into it and removed from it.
 ///
 /// Indices use the [`Index`] type, created by inserting values with [`Arena::insert`].
+///
+/// Slots freed by removing values are reused by later insertions. Which slot is
+/// reused first is decided by the arena's [`ReusePolicy`]. Freed slots can also
+/// be held back from reuse for a while with [`Arena::set_quarantine`].
 #[derive(Debug, Clone)]
 pub struct Arena<T> {
-    storage: Vec<Slot<T>>,
-    len: u32,
-    first_free: Option<FreePointer>,
+    pub(crate) storage: Vec<Slot<T>>,
+    pub(crate) len: u32,
+    pub(crate) first_free: Option<FreePointer>,
+    pub(crate) last_free: Option<FreePointer>,
+    pub(crate) policy: ReusePolicy,
+    pub(crate) quarantine: Quarantine,
+
+    /// Changed by every insertion, removal, and invalidation, so that
+    /// resumable iteration can tell whether the arena was changed between
+    /// calls.
+    pub(crate) stamp: u64,
 }
 
 /// Index type for [`Arena`] that has a generation attached to it.
@@ -90,14 +110,14 @@ pub(crate) enum Slot<T> {
 
 impl<T> Slot<T> {
     /// Consume the entry, and if it's occupied, return the value.
-    fn into_value(self) -> Option<T> {
+    pub(crate) fn into_value(self) -> Option<T> {
         match self {
             Slot::Occupied(occupied) => Some(occupied.value),
             Slot::Empty(_) => None,
         }
     }
 
-    fn get_value_mut(&mut self, generation: Generation) -> Option<&mut T> {
+    pub(crate) fn get_value_mut(&mut self, generation: Generation) -> Option<&mut T> {
         match self {
             Slot::Occupied(occupied) if occupied.generation == generation => {
                 Some(&mut occupied.value)
@@ -107,7 +127,7 @@ impl<T> Slot<T> {
     }
 
     /// If the entry is empty, a reference to it.
-    fn as_empty(&self) -> Option<&EmptySlot> {
+    pub(crate) fn as_empty(&self) -> Option<&EmptySlot> {
         match self {
             Slot::Empty(empty) => Some(empty),
             Slot::Occupied(_) => None,
@@ -115,7 +135,7 @@ impl<T> Slot<T> {
     }
 
     /// If the entry is empty, return a mutable reference to it.
-    fn as_empty_mut(&mut self) -> Option<&mut EmptySlot> {
+    pub(crate) fn as_empty_mut(&mut self) -> Option<&mut EmptySlot> {
         match self {
             Slot::Empty(empty) => Some(empty),
             Slot::Occupied(_) => None,
@@ -138,11 +158,7 @@ pub(crate) struct EmptySlot {
 impl<T> Arena<T> {
     /// Construct an empty arena.
     pub const fn new() -> Self {
-        Self {
-            storage: Vec::new(),
-            len: 0,
-            first_free: None,
-        }
+        Self::with_reuse_policy(ReusePolicy::Lifo)
     }
 
     /// Construct an empty arena with space to hold exactly `capacity` elements
@@ -150,11 +166,60 @@ impl<T> Arena<T> {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             storage: Vec::with_capacity(capacity),
+            ..Self::new()
+        }
+    }
+
+    /// Construct an empty arena that reuses free slots according to the given
+    /// [`ReusePolicy`].
+    pub const fn with_reuse_policy(policy: ReusePolicy) -> Self {
+        Self {
+            storage: Vec::new(),
             len: 0,
             first_free: None,
+            last_free: None,
+            policy,
+            quarantine: Quarantine::new(),
+            stamp: 0,
         }
     }
 
+    /// Return the [`ReusePolicy`] this arena was created with.
+    pub const fn reuse_policy(&self) -> ReusePolicy {
+        self.policy
+    }
+
+    /// Quarantine slots freed from now on for the given number of epochs.
+    ///
+    /// A quarantined slot stays empty, so indices that pointed to it keep
+    /// reporting that their value was removed, until [`Arena::advance_epoch`]
+    /// has been called `epochs` times. Only then is the slot handed to the
+    /// free list and reused by insertions, following the arena's
+    /// [`ReusePolicy`].
+    ///
+    /// Passing zero disables quarantine for future removals. Slots that are
+    /// already quarantined are still released on their original schedule.
+    pub fn set_quarantine(&mut self, epochs: u32) {
+        self.quarantine.epochs = epochs;
+    }
+
+    /// Return the number of epochs freed slots are quarantined for. See
+    /// [`Arena::set_quarantine`].
+    pub const fn quarantine(&self) -> u32 {
+        self.quarantine.epochs
+    }
+
+    /// Return the number of freed slots that are currently in quarantine.
+    pub fn quarantined_len(&self) -> usize {
+        self.quarantine.len() as usize
+    }
+
+    /// Advance the arena to its next epoch, releasing every quarantined slot
+    /// whose quarantine has expired back to the free list.
+    pub fn advance_epoch(&mut self) {
+        self.release_expired();
+    }
+
     /// Return the number of elements contained in the arena.
     pub const fn len(&self) -> usize {
         self.len as usize
@@ -168,7 +233,11 @@ impl<T> Arena<T> {
 
     /// Reserve capacity for at least `additional` more elements to be inserted
     pub fn reserve(&mut self, additional: usize) {
-        let currently_free = self.storage.len().saturating_sub(self.len as usize);
+        let currently_free = self
+            .storage
+            .len()
+            .saturating_sub(self.len as usize)
+            .saturating_sub(self.quarantined_len());
         let to_reserve = additional.saturating_sub(currently_free);
         self.storage.reserve(to_reserve);
     }
@@ -181,190 +250,136 @@ impl<T> Arena<T> {
     /// Insert a new value into the arena, returning an index that can be used
     /// to later retrieve the value.
     pub fn insert(&mut self, value: T) -> Index {
-        // This value will definitely be inserted, so we can update length now.
-        self.len = self
-            .len
-            .checked_add(1)
-            .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
-
-        // If there was a previously free entry, we can re-use its slot as long
-        // as we increment its generation.
-        if let Some(free_pointer) = self.first_free {
-            let slot = free_pointer.slot();
-            let entry = self.storage.get_mut(slot as usize).unwrap_or_else(|| {
-                unreachable!("first_free pointed past the end of the arena's storage")
-            });
-
-            let empty = entry
-                .as_empty()
-                .unwrap_or_else(|| unreachable!("first_free pointed to an occupied entry"));
-
-            // If there is another empty entry after this one, we'll update the
-            // arena to point to it to use it on the next insertion.
-            self.first_free = empty.next_free;
-
-            // Overwrite the entry directly using our mutable reference instead
-            // of indexing into our storage again. This should avoid an
-            // additional bounds check.
-            let generation = empty.generation.next();
-            *entry = Slot::Occupied(OccupiedSlot { generation, value });
-
-            Index { slot, generation }
-        } else {
-            // There were no more empty entries left in our free list, so we'll
-            // create a new first-generation entry and push it into storage.
-
-            let generation = Generation::first();
-            let slot: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
-                unreachable!("Arena storage exceeded what can be represented by a u32")
-            });
-
-            self.storage
-                .push(Slot::Occupied(OccupiedSlot { generation, value }));
-
-            Index { slot, generation }
-        }
+        self.insert_next(value)
     }
 
     /// Compute the `Index` that the next call to [`Arena::insert`] would produce,
     /// without mutating the arena.
+    ///
+    /// The returned index takes the arena's [`ReusePolicy`] into account.
     pub fn next_index(&self) -> Index {
-        if let Some(free_pointer) = self.first_free {
-            let slot = free_pointer.slot();
-            let empty = self
-                .storage
-                .get(slot as usize)
-                .unwrap_or_else(|| {
-                    unreachable!("first_free pointed past the end of the arena's storage")
-                })
-                .as_empty()
-                .unwrap_or_else(|| unreachable!("first_free pointed to an occupied entry"));
-
-            Index {
-                slot,
-                generation: empty.generation.next(),
+        self.next_insert_index()
+    }
+
+    /// Rebuild the free list out of every empty slot that isn't quarantined,
+    /// ordered so that lower slots are reused before higher ones regardless of
+    /// the arena's reuse policy.
+    pub(crate) fn relink_free_list(&mut self) {
+        self.first_free = None;
+        self.last_free = None;
+
+        let len: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        });
+
+        let relink = |arena: &mut Self, slot: u32| {
+            let is_free = matches!(arena.storage[slot as usize], Slot::Empty(_))
+                && !arena.quarantine.pending.iter().any(|&(_, q)| q == slot);
+
+            if is_free {
+                arena.empty_slot_mut(slot).next_free = None;
+                arena.push_free(slot);
             }
-        } else {
-            let slot: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
-                unreachable!("Arena storage exceeded what can be represented by a u32")
-            });
+        };
 
-            Index {
-                slot,
-                generation: Generation::first(),
+        // Under `Lifo`, the slot pushed last is reused first.
+        match self.policy {
+            ReusePolicy::Lifo => (0..len).rev().for_each(|slot| relink(self, slot)),
+            ReusePolicy::Fifo | ReusePolicy::LowestSlot => {
+                (0..len).for_each(|slot| relink(self, slot))
             }
         }
     }
 
-    /// Traverse the free list and remove this known-empty slot from it, given the slot to remove
-    /// and the `next_free` pointer of that slot.
-    fn remove_slot_from_free_list(&mut self, slot: u32, new_next_free: Option<FreePointer>) {
-        // We will need to fix up the free list so that whatever pointer previously pointed
-        // to this empty entry will point to the next empty entry after it.
-        let mut next_fp = self
-            .first_free
-            .expect("Free entry exists but first_free is None");
-
-        // As state during this traversal, we keep the "next free" pointer which we are testing
-        // (which will always be `Some` as long as the free list is correct and contains this empty
-        // entry) as well as the current slot that contains that "next free" pointer. If the current
-        // slot is `None`, it means that the container of the relevant "next free" pointer is
-        // actually the root (`self.first_free`).
-        let mut current_slot = None;
-        while next_fp.slot() != slot {
-            current_slot = Some(next_fp.slot());
-            next_fp = self
-                .storage
-                .get(next_fp.slot() as usize)
-                .expect("Empty slot not in storage!")
-                .as_empty()
-                .expect("Slot in free list not actually empty!")
-                .next_free
-                .expect("Hit the end of the free list without finding the target slot!");
+    /// Replace the free list with the given empty slots, reused in the given
+    /// order, and the quarantine with the given slots, each paired with the
+    /// number of epochs until it is released. Callers are expected to list
+    /// every empty slot exactly once between the two.
+    pub(crate) fn set_free_list(&mut self, order: &[u32], quarantined: &[(u64, u32)]) {
+        let epoch = self.quarantine.epoch;
+        self.quarantine.pending = quarantined
+            .iter()
+            .map(|&(left, slot)| {
+                let release = epoch
+                    .checked_add(left)
+                    .unwrap_or_else(|| unreachable!("quarantine was checked against the arena"));
+                (release, slot)
+            })
+            .collect();
+
+        for pair in order.windows(2) {
+            self.empty_slot_mut(pair[0]).next_free = Some(FreePointer::from_slot(pair[1]));
         }
 
-        // If we found the slot to fix, then fix it; otherwise, we know that this slot is
-        // actually the very first in the free list, so fix it at the root.
-        match current_slot {
-            Some(slot_to_fix) => {
-                self.storage[slot_to_fix as usize]
-                    .as_empty_mut()
-                    .unwrap()
-                    .next_free = new_next_free
-            }
-            None => self.first_free = new_next_free,
+        if let Some(&last) = order.last() {
+            self.empty_slot_mut(last).next_free = None;
         }
+
+        self.first_free = order.first().copied().map(FreePointer::from_slot);
+        self.last_free = order.last().copied().map(FreePointer::from_slot);
+    }
+
+    /// Compute the `Index` that `insert_unlinked` would produce for the given
+    /// slot when not given a generation.
+    pub(crate) fn predict_unlinked(&self, slot: u32) -> Index {
+        let generation = match self.storage.get(slot as usize) {
+            Some(Slot::Empty(empty)) => empty.generation.next(),
+            Some(Slot::Occupied(_)) => unreachable!("predict_unlinked called on an occupied slot"),
+            None => Generation::first(),
+        };
+
+        Index { slot, generation }
     }
 
-    // Shared functionality between `insert_at` and `insert_at_slot`.
-    #[inline]
-    fn insert_at_inner(
+    /// Occupy an empty slot that is not linked into the free list, extending the
+    /// storage with unlinked empty slots if the slot is out of bounds. This lets
+    /// wrappers that keep their own free lists use the arena's storage.
+    pub(crate) fn insert_unlinked(
         &mut self,
         slot: u32,
         generation: Option<Generation>,
         value: T,
-    ) -> (Index, Option<T>) {
-        // Three cases to consider:
-        //
-        // 1.) The slot is free; we need to traverse the free list, remove it from the list, and
-        //     then insert the value.
-        // 2.) The slot is occupied; we can just replace the value and return the old one.
-        // 3.) The slot is beyond the current length of the arena. In this case, we must extend
-        //     the arena with new empty slots filling the free list accordingly, and then insert the
-        //     value.
-
-        let (index, old_value) = match self.storage.get_mut(slot as usize) {
-            Some(Slot::Empty(empty)) => {
-                let generation = generation.unwrap_or_else(|| empty.generation.next());
-                // We will need to fix up the free list so that whatever pointer previously pointed
-                // to this empty entry will point to the next empty entry after it.
-                let new_next_free = empty.next_free;
-                self.remove_slot_from_free_list(slot, new_next_free);
-                self.storage[slot as usize] = Slot::Occupied(OccupiedSlot { generation, value });
-
-                (Index { slot, generation }, None)
-            }
-            Some(Slot::Occupied(occupied)) => {
-                occupied.generation = generation.unwrap_or_else(|| occupied.generation.next());
-                let generation = occupied.generation;
-                let old_value = replace(&mut occupied.value, value);
+    ) -> Index {
+        let generation = generation.unwrap_or_else(|| self.predict_unlinked(slot).generation);
+        self.touch();
 
-                (Index { slot, generation }, Some(old_value))
-            }
-            None => {
-                let mut first_free = self.first_free;
-                while self.storage.len() < slot as usize {
-                    let new_slot: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
-                        unreachable!("Arena storage exceeded what can be represented by a u32")
-                    });
-
-                    self.storage.push(Slot::Empty(EmptySlot {
-                        generation: Generation::first(),
-                        next_free: first_free,
-                    }));
-
-                    first_free = Some(FreePointer::from_slot(new_slot));
-                }
+        while self.storage.len() <= slot as usize {
+            self.storage.push(Slot::Empty(EmptySlot {
+                generation: Generation::first(),
+                next_free: None,
+            }));
+        }
 
-                self.first_free = first_free;
-                let generation = generation.unwrap_or_else(Generation::first);
-                self.storage
-                    .push(Slot::Occupied(OccupiedSlot { generation, value }));
+        self.storage[slot as usize] = Slot::Occupied(OccupiedSlot { generation, value });
+        self.len = self
+            .len
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
 
-                (Index { slot, generation }, None)
-            }
-        };
+        Index { slot, generation }
+    }
 
-        // If this insertion didn't replace an old value, then the arena now contains one more
-        // element; we need to update its length accordingly.
-        if old_value.is_none() {
-            self.len = self
-                .len
-                .checked_add(1)
-                .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+    /// Remove a value without linking its slot into the free list or
+    /// quarantine. The counterpart to `insert_unlinked`.
+    pub(crate) fn remove_unlinked(&mut self, index: Index) -> Option<T> {
+        if !self.contains(index) {
+            return None;
         }
 
-        (index, old_value)
+        let entry = &mut self.storage[index.slot as usize];
+        let new_entry = Slot::Empty(EmptySlot {
+            generation: index.generation,
+            next_free: None,
+        });
+
+        let value = replace(entry, new_entry)
+            .into_value()
+            .unwrap_or_else(|| unreachable!());
+
+        self.touch();
+        self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+
+        Some(value)
     }
 
     /// Insert a new value at a given index, returning the old value if present. The entry's
@@ -472,57 +487,84 @@ impl<T> Arena<T> {
 
     /// Remove the value contained at the given index from the arena, returning
     /// it if it was present.
+    ///
+    /// The freed slot is reused by later insertions according to the arena's
+    /// [`ReusePolicy`].
     pub fn remove(&mut self, index: Index) -> Option<T> {
-        let entry = self.storage.get_mut(index.slot as usize)?;
-
-        match entry {
-            Slot::Occupied(occupied) if occupied.generation == index.generation => {
-                // We can replace an occupied entry with an empty entry with the
-                // same generation. On next insertion, this generation will
-                // increment.
-                let new_entry = Slot::Empty(EmptySlot {
-                    generation: occupied.generation,
-                    next_free: self.first_free,
-                });
-
-                // Swap our new entry into our storage and take ownership of the
-                // old entry. We'll consume it for its value so we can give that
-                // back to our caller.
-                let old_entry = replace(entry, new_entry);
-                let value = old_entry.into_value().unwrap_or_else(|| unreachable!());
-
-                // The next time we insert, we can re-use the empty entry we
-                // just created. If another removal happens before then, that
-                // entry will be used before this one (FILO).
-                self.first_free = Some(FreePointer::from_slot(index.slot));
-
-                self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
-
-                Some(value)
-            }
-            _ => None,
+        if !self.contains(index) {
+            return None;
         }
+
+        self.vacate(index.slot).map(|(_, value)| value)
     }
 
     /// Invalidate the given index and return a new index to the same value. This
     /// is roughly equivalent to `remove` followed by `insert`, but much faster.
     /// If the old index is already invalid, this method returns `None`.
     pub fn invalidate(&mut self, index: Index) -> Option<Index> {
-        let entry = self.storage.get_mut(index.slot as usize)?;
+        self.invalidate_slot(index)
+    }
 
-        match entry {
-            Slot::Occupied(occupied) if occupied.generation == index.generation => {
-                occupied.generation = occupied.generation.next();
+    /// Swap the values at two indices, leaving each slot's generation alone, so
+    /// that `a` refers to the value that was at `b` and the other way around.
+    /// Returns false without changing anything if either index isn't in the
+    /// arena.
+    ///
+    /// ```rust
+    /// # use thunderdome::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert("a");
+    /// let b = arena.insert("b");
+    ///
+    /// assert!(arena.swap(a, b));
+    /// assert_eq!(arena[a], "b");
+    /// assert_eq!(arena[b], "a");
+    /// ```
+    pub fn swap(&mut self, a: Index, b: Index) -> bool {
+        if a == b {
+            return self.contains(a);
+        }
 
-                Some(Index {
-                    generation: occupied.generation,
-                    ..index
-                })
+        match self.get2_mut(a, b) {
+            (Some(a), Some(b)) => {
+                swap(a, b);
+                true
             }
-            _ => None,
+            _ => false,
         }
     }
 
+    /// Move the value at the given index into the given empty slot, returning
+    /// its new index. The value's old slot is freed, like with
+    /// [`Arena::remove`], and the new index's generation follows the rules of
+    /// [`Arena::insert_at_slot`], so neither the old index nor any earlier
+    /// index to the new slot refers to the value.
+    ///
+    /// Slots past the end of the arena's storage are empty. Returns `None`
+    /// without changing anything if `index` isn't in the arena or `to_slot`
+    /// holds a value.
+    ///
+    /// ```rust
+    /// # use thunderdome::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert("a");
+    ///
+    /// let moved = arena.relocate(a, 3).unwrap();
+    /// assert_eq!(moved.slot(), 3);
+    /// assert_eq!(arena[moved], "a");
+    /// assert_eq!(arena.get(a), None);
+    /// ```
+    pub fn relocate(&mut self, index: Index, to_slot: u32) -> Option<Index> {
+        if !self.contains(index) || self.contains_slot(to_slot).is_some() {
+            return None;
+        }
+
+        let (_, value) = self.vacate(index.slot)?;
+        let (new_index, _) = self.insert_at_inner(to_slot, None, value);
+
+        Some(new_index)
+    }
+
     /// Attempt to look up the given slot in the arena, disregarding any generational
     /// information, and retrieve an immutable reference to it. Returns `None` if the
     /// slot is empty.
@@ -558,38 +600,7 @@ impl<T> Arena<T> {
     /// Remove an entry in the arena by its slot, disregarding any generational info.
     /// Returns `None` if the slot was already empty.
     pub fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
-        let entry = self.storage.get_mut(slot as usize)?;
-
-        match entry {
-            Slot::Occupied(occupied) => {
-                // Construct the index that would be used to access this entry.
-                let index = Index {
-                    generation: occupied.generation,
-                    slot,
-                };
-
-                // This occupied entry will be replaced with an empty one of the
-                // same generation. Generation will be incremented on the next
-                // insert.
-                let next_entry = Slot::Empty(EmptySlot {
-                    generation: occupied.generation,
-                    next_free: self.first_free,
-                });
-
-                // Swap new entry into place and consume the old one.
-                let old_entry = replace(entry, next_entry);
-                let value = old_entry.into_value().unwrap_or_else(|| unreachable!());
-
-                // Set this entry as the next one that should be inserted into,
-                // should an insertion happen.
-                self.first_free = Some(FreePointer::from_slot(slot));
-
-                self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
-
-                Some((index, value))
-            }
-            _ => None,
-        }
+        self.vacate(slot)
     }
 
     /// Clear the arena and drop all elements.
@@ -614,6 +625,13 @@ impl<T> Arena<T> {
         Values { inner: self.iter() }
     }
 
+    /// Iterate over the indices of all of the values contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn keys(&self) -> Keys<'_, T> {
+        Keys { inner: self.iter() }
+    }
+
     /// Iterate over all of the indexes and values contained in the arena, with
     /// mutable access to each value.
     ///
@@ -644,6 +662,98 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Consume the arena and iterate over the indices of all values contained
+    /// in it, dropping the values.
+    ///
+    /// Iteration order is not defined.
+    pub fn into_keys(self) -> IntoKeys<T> {
+        IntoKeys {
+            inner: self.into_iter(),
+        }
+    }
+
+    /// Iterate over every slot in the arena's storage in slot order, both
+    /// occupied and empty.
+    pub fn iter_slots(&self) -> IterSlots<'_, T> {
+        IterSlots {
+            inner: self.storage.iter().enumerate(),
+        }
+    }
+
+    /// Iterate over the arena's free list in the order its slots will be
+    /// reused, yielding the index each successive call to [`Arena::insert`]
+    /// would return. The first index is [`Arena::next_index`], unless the free
+    /// list is empty.
+    ///
+    /// Quarantined slots are not part of the free list.
+    pub fn free_slots(&self) -> FreeSlots<'_, T> {
+        // Every empty slot is either in the free list or in quarantine.
+        let remaining = self
+            .storage
+            .len()
+            .saturating_sub(self.len as usize)
+            .saturating_sub(self.quarantined_len());
+
+        FreeSlots {
+            arena: self,
+            next: self.first_free,
+            remaining,
+        }
+    }
+
+    /// Iterate over the values in the given range of slots, in slot order.
+    /// Parts of the range past the end of the arena's storage are ignored.
+    ///
+    /// Creating the iterator takes time proportional to the size of the range.
+    ///
+    /// ```rust
+    /// # use thunderdome::Arena;
+    /// let mut arena = Arena::new();
+    /// for i in 0..10 {
+    ///     arena.insert(i);
+    /// }
+    ///
+    /// let values: Vec<_> = arena.range(3..6).map(|(_, value)| *value).collect();
+    /// assert_eq!(values, [3, 4, 5]);
+    /// assert_eq!(arena.range(8..).len(), 2);
+    /// ```
+    pub fn range<R: RangeBounds<u32>>(&self, slots: R) -> Iter<'_, T> {
+        let (start, end) = self.slot_bounds(slots);
+        self.iter_range(start, end)
+    }
+
+    /// Iterate over the values in the given range of slots, in slot order, with
+    /// mutable access to each value. Parts of the range past the end of the
+    /// arena's storage are ignored.
+    ///
+    /// Creating the iterator takes time proportional to the size of the range.
+    pub fn range_mut<R: RangeBounds<u32>>(&mut self, slots: R) -> IterMut<'_, T> {
+        let (start, end) = self.slot_bounds(slots);
+        let window = &mut self.storage[..end];
+
+        let len = count_occupied(&window[start..]);
+        let mut inner = window.iter_mut().enumerate();
+        if let Some(skip) = start.checked_sub(1) {
+            inner.nth(skip);
+        }
+
+        IterMut { len, inner }
+    }
+
+    /// Iterate over the values in the slots from `start` up to `end`, which
+    /// must be within the arena's storage.
+    pub(crate) fn iter_range(&self, start: usize, end: usize) -> Iter<'_, T> {
+        let window = &self.storage[..end];
+
+        let len = count_occupied(&window[start..]);
+        let mut inner = window.iter().enumerate();
+        if let Some(skip) = start.checked_sub(1) {
+            inner.nth(skip);
+        }
+
+        Iter { len, inner }
+    }
+
     /// Returns an iterator that removes each element from the arena.
     ///
     /// Iteration order is not defined.
@@ -655,39 +765,199 @@ impl<T> Arena<T> {
         Drain {
             arena: self,
             slot: 0,
+            value: PhantomData,
+        }
+    }
+
+    /// Returns an iterator that removes the values in the given range of slots
+    /// from the arena. Parts of the range past the end of the arena's storage
+    /// are ignored.
+    ///
+    /// Values are returned in slot order.
+    ///
+    /// If the iterator is dropped before it is fully consumed, any uniterated
+    /// values in the range will be dropped from the arena.
+    pub fn drain_range<R: RangeBounds<u32>>(&mut self, slots: R) -> DrainRange<'_, T> {
+        let (start, end) = self.slot_bounds(slots);
+        let remaining = self.storage[start..end]
+            .iter()
+            .filter(|entry| matches!(entry, Slot::Occupied(_)))
+            .count();
+
+        DrainRange {
+            arena: self,
+            slot: start as u32,
+            end: end as u32,
+            remaining,
+        }
+    }
+
+    /// Returns an iterator that removes and returns each value for which the
+    /// given predicate returns true, leaving the others in the arena. Unlike
+    /// [`Arena::retain`], the predicate returns true for values that should be
+    /// removed.
+    ///
+    /// Values are visited in slot order, each exactly once.
+    ///
+    /// If the iterator is dropped before it is fully consumed, the predicate is
+    /// run on the remaining values and any that match are dropped from the
+    /// arena. If the predicate panics, no more values are visited or removed.
+    ///
+    /// ```rust
+    /// # use thunderdome::Arena;
+    /// let mut arena = Arena::new();
+    /// for i in 0..6 {
+    ///     arena.insert(i);
+    /// }
+    ///
+    /// let evens: Vec<_> = arena
+    ///     .extract_if(|_, value| *value % 2 == 0)
+    ///     .map(|(_, value)| value)
+    ///     .collect();
+    ///
+    /// assert_eq!(evens, [0, 2, 4]);
+    /// assert_eq!(arena.len(), 3);
+    /// ```
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, F>
+    where
+        F: FnMut(Index, &mut T) -> bool,
+    {
+        ExtractIf {
+            arena: self,
+            slot: 0,
+            pred,
+            panicked: false,
         }
     }
 
     /// Remove all entries in the `Arena` which don't satisfy the provided predicate.
     pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
-        for (i, entry) in self.storage.iter_mut().enumerate() {
-            if let Slot::Occupied(occupied) = entry {
-                let index = Index {
-                    slot: i as u32,
-                    generation: occupied.generation,
-                };
+        for i in 0..self.storage.len() {
+            let slot = i as u32;
 
-                if !f(index, &mut occupied.value) {
-                    // We can replace an occupied entry with an empty entry with the
-                    // same generation. On next insertion, this generation will
-                    // increment.
-                    *entry = Slot::Empty(EmptySlot {
+            let keep = match &mut self.storage[i] {
+                Slot::Occupied(occupied) => {
+                    let index = Index {
+                        slot,
                         generation: occupied.generation,
-                        next_free: self.first_free,
-                    });
+                    };
 
-                    // The next time we insert, we can re-use the empty entry we
-                    // just created. If another removal happens before then, that
-                    // entry will be used before this one (FILO).
-                    self.first_free = Some(FreePointer::from_slot(index.slot));
-
-                    // We just verified that this entry is (was) occupied, so there's
-                    // trivially no way for this `checked_sub` to fail.
-                    self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+                    f(index, &mut occupied.value)
                 }
+                Slot::Empty(_) => true,
+            };
+
+            if !keep {
+                self.vacate(slot);
             }
         }
     }
+
+    /// Consume the arena, converting every value with the given function into
+    /// an arena of the new values.
+    ///
+    /// The new arena's occupied slots, generations, free list, reuse policy,
+    /// and quarantine are identical to this one's, so every [`Index`] that was
+    /// valid for this arena is valid for the new one, and both arenas will
+    /// produce the same indices from future insertions.
+    pub fn map<U, F: FnMut(Index, T) -> U>(self, mut f: F) -> Arena<U> {
+        match self.try_map(|index, value| Ok::<U, Infallible>(f(index, value))) {
+            Ok(arena) => arena,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Consume the arena, converting every value with the given fallible
+    /// function into an arena of the new values.
+    ///
+    /// Values are converted in slot order. Conversion stops at the first error,
+    /// which is returned; the remaining values are dropped. On success, the new
+    /// arena has the same layout as this one, exactly like with
+    /// [`Arena::map`].
+    pub fn try_map<U, E, F>(self, mut f: F) -> Result<Arena<U>, E>
+    where
+        F: FnMut(Index, T) -> Result<U, E>,
+    {
+        let storage = self
+            .storage
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| match entry {
+                Slot::Occupied(occupied) => {
+                    let index = Index {
+                        slot: i as u32,
+                        generation: occupied.generation,
+                    };
+
+                    Ok(Slot::Occupied(OccupiedSlot {
+                        generation: occupied.generation,
+                        value: f(index, occupied.value)?,
+                    }))
+                }
+                Slot::Empty(empty) => Ok(Slot::Empty(empty)),
+            })
+            .collect::<Result<Vec<_>, E>>()?;
+
+        Ok(Arena {
+            storage,
+            len: self.len,
+            first_free: self.first_free,
+            last_free: self.last_free,
+            policy: self.policy,
+            quarantine: self.quarantine,
+            stamp: self.stamp,
+        })
+    }
+
+    /// Create a new arena by converting a reference to every value with the
+    /// given function, leaving this arena untouched.
+    ///
+    /// The new arena has the same layout as this one, exactly like with
+    /// [`Arena::map`].
+    pub fn map_ref<U, F: FnMut(Index, &T) -> U>(&self, mut f: F) -> Arena<U> {
+        let storage = self
+            .storage
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| match entry {
+                Slot::Occupied(occupied) => {
+                    let index = Index {
+                        slot: i as u32,
+                        generation: occupied.generation,
+                    };
+
+                    Slot::Occupied(OccupiedSlot {
+                        generation: occupied.generation,
+                        value: f(index, &occupied.value),
+                    })
+                }
+                Slot::Empty(empty) => Slot::Empty(*empty),
+            })
+            .collect();
+
+        Arena {
+            storage,
+            len: self.len,
+            first_free: self.first_free,
+            last_free: self.last_free,
+            policy: self.policy,
+            quarantine: self.quarantine.clone(),
+            stamp: self.stamp,
+        }
+    }
+}
+
+/// Count the occupied entries in a run of slots, which can't exceed the
+/// number of values in an arena.
+fn count_occupied<T>(slots: &[Slot<T>]) -> u32 {
+    let count = slots
+        .iter()
+        .filter(|entry| matches!(entry, Slot::Occupied(_)))
+        .count();
+
+    count
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("Arena held more than u32::MAX values"))
 }
 
 impl<T> Default for Arena<T> {
@@ -696,6 +966,106 @@ impl<T> Default for Arena<T> {
     }
 }
 
+impl<T> SlotStorage for Arena<T> {
+    type Value = T;
+
+    fn slot_count(&self) -> u32 {
+        self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        })
+    }
+
+    fn slot(&self, slot: u32) -> Option<&Slot<T>> {
+        self.storage.get(slot as usize)
+    }
+
+    fn policy(&self) -> ReusePolicy {
+        self.policy
+    }
+
+    fn first_free(&self) -> Option<FreePointer> {
+        self.first_free
+    }
+
+    fn last_free(&self) -> Option<FreePointer> {
+        self.last_free
+    }
+
+    fn quarantine_state(&self) -> &Quarantine {
+        &self.quarantine
+    }
+}
+
+impl<T> SlotStorageMut for Arena<T> {
+    fn slot_mut(&mut self, slot: u32) -> Option<&mut Slot<T>> {
+        self.storage.get_mut(slot as usize)
+    }
+
+    fn push_slot(&mut self, entry: Slot<T>) {
+        self.storage.push(entry);
+    }
+
+    fn len_mut(&mut self) -> &mut u32 {
+        &mut self.len
+    }
+
+    fn first_free_mut(&mut self) -> &mut Option<FreePointer> {
+        &mut self.first_free
+    }
+
+    fn last_free_mut(&mut self) -> &mut Option<FreePointer> {
+        &mut self.last_free
+    }
+
+    fn quarantine_state_mut(&mut self) -> &mut Quarantine {
+        &mut self.quarantine
+    }
+
+    /// Change the stamp, so that resumable iteration can tell the arena
+    /// changed. See [`Arena::iter_from`].
+    fn touch(&mut self) {
+        self.stamp = self.stamp.wrapping_add(1);
+    }
+}
+
+impl<T> ArenaLike<T> for Arena<T> {
+    fn len(&self) -> usize {
+        Arena::len(self)
+    }
+
+    fn contains(&self, index: Index) -> bool {
+        Arena::contains(self, index)
+    }
+
+    fn get(&self, index: Index) -> Option<&T> {
+        Arena::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        Arena::get_mut(self, index)
+    }
+
+    fn insert_at(&mut self, index: Index, value: T) -> Option<T> {
+        Arena::insert_at(self, index, value)
+    }
+
+    fn insert_at_slot(&mut self, slot: u32, value: T) -> (Index, Option<T>) {
+        Arena::insert_at_slot(self, slot, value)
+    }
+
+    fn remove(&mut self, index: Index) -> Option<T> {
+        Arena::remove(self, index)
+    }
+
+    fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
+        Arena::remove_by_slot(self, slot)
+    }
+
+    fn invalidate(&mut self, index: Index) -> Option<Index> {
+        Arena::invalidate(self, index)
+    }
+}
+
 impl<T> IntoIterator for Arena<T> {
     type Item = (Index, T);
     type IntoIter = IntoIter<T>;
@@ -746,10 +1116,14 @@ impl<T> ops::IndexMut<Index> for Arena<T> {
 mod test {
     use crate::free_pointer::FreePointer;
 
-    use super::{Arena, Generation, Index};
+    use super::{Arena, Generation, Index, ReusePolicy};
+    use crate::test_util::POLICIES;
 
     use core::mem::size_of;
 
+    #[cfg(not(feature = "std"))]
+    use alloc::vec::Vec;
+
     #[test]
     fn size_of_index() {
         assert_eq!(size_of::<Index>(), 8);
@@ -1055,4 +1429,345 @@ mod test {
         // we moved the goalpost, so the next_index won't be accurate anymore
         assert_ne!(next, next_next_insert);
     }
+
+    #[test]
+    fn map_preserves_indices() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+        arena.remove(b);
+        let c = arena.invalidate(c).unwrap();
+
+        let next = arena.next_index();
+        let mapped = arena.map(|index, value| (index, value * 10));
+
+        assert_eq!(mapped.len(), 2);
+        assert_eq!(mapped[a], (a, 10));
+        assert_eq!(mapped[c], (c, 30));
+        assert_eq!(mapped.get(b), None);
+        assert_eq!(mapped.next_index(), next);
+    }
+
+    #[test]
+    fn map_ref_preserves_indices() {
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        let indices: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+        arena.remove(indices[0]);
+
+        let mut mapped = arena.map_ref(|_, &value| Some(value));
+        assert_eq!(mapped.reuse_policy(), ReusePolicy::Fifo);
+        assert_eq!(mapped[indices[1]], Some(1));
+        assert_eq!(mapped[indices[3]], Some(3));
+
+        assert_eq!(mapped.insert(None), arena.insert(0));
+        assert_eq!(mapped.insert(None), arena.insert(0));
+        assert_eq!(mapped.insert(None), arena.insert(0));
+    }
+
+    #[test]
+    fn try_map_stops_at_first_error() {
+        let mut arena = Arena::new();
+        for i in 0..5 {
+            arena.insert(i);
+        }
+
+        let mut calls = 0;
+        let result = arena.clone().try_map(|_, value| {
+            calls += 1;
+            if value == 2 {
+                Err(value)
+            } else {
+                Ok(value)
+            }
+        });
+
+        assert_eq!(result.unwrap_err(), 2);
+        assert_eq!(calls, 3);
+
+        let mapped = arena.try_map(|_, value| Ok::<_, ()>(i64::from(value)));
+        assert_eq!(mapped.unwrap().len(), 5);
+    }
+
+    #[test]
+    fn quarantine_delays_reuse() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(2);
+        assert_eq!(arena.quarantine(), 2);
+
+        let a = arena.insert(1);
+        arena.remove(a);
+        assert_eq!(arena.quarantined_len(), 1);
+
+        // The quarantined slot must not be predicted or reused.
+        assert_eq!(arena.next_index().slot(), 1);
+
+        arena.advance_epoch();
+        let b = arena.insert(2);
+        assert_eq!(b.slot(), 1);
+        arena.remove(b);
+        assert_eq!(arena.quarantined_len(), 2);
+
+        // `b` was quarantined an epoch after `a`, so only `a` is released.
+        arena.advance_epoch();
+        assert_eq!(arena.quarantined_len(), 1);
+        assert!(!arena.contains(a));
+        assert_eq!(arena.next_index().slot(), a.slot());
+
+        let c = arena.insert(3);
+        assert_eq!(c.slot(), a.slot());
+        assert_ne!(c, a);
+        assert_eq!(arena.get(a), None);
+    }
+
+    #[test]
+    fn quarantine_drain_and_retain() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(1);
+
+        for i in 0..4 {
+            arena.insert(i);
+        }
+
+        arena.retain(|_, &mut i| i % 2 == 0);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.quarantined_len(), 2);
+
+        arena.drain().for_each(drop);
+        assert_eq!(arena.quarantined_len(), 4);
+        assert_eq!(arena.insert(10).slot(), 4);
+
+        arena.advance_epoch();
+        assert_eq!(arena.quarantined_len(), 0);
+
+        // Slots come out of quarantine in removal order: 1, 3, 0, 2.
+        let predicted = arena.next_index();
+        assert_eq!(predicted.slot(), 2);
+        assert_eq!(arena.insert(11), predicted);
+    }
+
+    #[test]
+    fn quarantine_insert_at() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(1);
+
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        arena.remove(a);
+        arena.remove(b);
+
+        // Explicitly inserting into a quarantined slot takes it out of
+        // quarantine.
+        arena.insert_at(a, "a again");
+        assert_eq!(arena.quarantined_len(), 1);
+        assert_eq!(arena[a], "a again");
+
+        arena.advance_epoch();
+        assert_eq!(arena.insert("c").slot(), b.slot());
+        assert_eq!(arena.insert("d").slot(), 2);
+    }
+
+    #[test]
+    fn quarantine_disabled() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(3);
+
+        let a = arena.insert(1);
+        arena.remove(a);
+
+        arena.set_quarantine(0);
+        let b = arena.insert(2);
+        arena.remove(b);
+
+        // `b` skipped quarantine, but `a` keeps waiting for its epochs.
+        assert_eq!(arena.quarantined_len(), 1);
+        assert_eq!(arena.next_index().slot(), b.slot());
+    }
+
+    #[test]
+    fn quarantine_lowered() {
+        let mut arena = Arena::new();
+        let x = arena.insert("x");
+        let y = arena.insert("y");
+        arena.insert("z");
+
+        arena.set_quarantine(5);
+        arena.remove(x);
+        arena.set_quarantine(1);
+        arena.remove(y);
+
+        // `y` was quarantined after `x`, but is released first.
+        arena.advance_epoch();
+        assert_eq!(arena.quarantined_len(), 1);
+        assert_eq!(arena.next_index().slot(), y.slot());
+
+        for _ in 0..4 {
+            arena.advance_epoch();
+        }
+        assert_eq!(arena.quarantined_len(), 0);
+
+        // `x` was released last, so it's reused first.
+        assert_eq!(arena.insert("x again").slot(), x.slot());
+        assert_eq!(arena.insert("y again").slot(), y.slot());
+    }
+
+    fn remove_slots(policy: ReusePolicy, slots: &[u32]) -> Arena<u32> {
+        let mut arena = Arena::with_reuse_policy(policy);
+        let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+
+        for &slot in slots {
+            arena.remove(indices[slot as usize]);
+        }
+
+        arena
+    }
+
+    fn reuse_order(arena: &mut Arena<u32>) -> Vec<u32> {
+        let free = arena
+            .storage
+            .iter()
+            .filter(|slot| slot.as_empty().is_some());
+        (0..free.count())
+            .map(|_| {
+                let predicted = arena.next_index();
+                let actual = arena.insert(0);
+                assert_eq!(predicted, actual);
+                actual.slot()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reuse_policy_lifo() {
+        let mut arena = remove_slots(ReusePolicy::Lifo, &[5, 1, 6, 3]);
+        assert_eq!(arena.reuse_policy(), ReusePolicy::Lifo);
+        assert_eq!(reuse_order(&mut arena), [3, 6, 1, 5]);
+    }
+
+    #[test]
+    fn reuse_policy_fifo() {
+        let mut arena = remove_slots(ReusePolicy::Fifo, &[5, 1, 6, 3]);
+        assert_eq!(reuse_order(&mut arena), [5, 1, 6, 3]);
+    }
+
+    #[test]
+    fn reuse_policy_lowest_slot() {
+        let mut arena = remove_slots(ReusePolicy::LowestSlot, &[5, 1, 6, 3]);
+        assert_eq!(reuse_order(&mut arena), [1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn reuse_policy_interleaved() {
+        let mut arena = remove_slots(ReusePolicy::Fifo, &[2, 4]);
+        assert_eq!(arena.insert(0).slot(), 2);
+
+        arena.remove_by_slot(0);
+        assert_eq!(reuse_order(&mut arena), [4, 0]);
+
+        let mut arena = remove_slots(ReusePolicy::LowestSlot, &[2, 4]);
+        assert_eq!(arena.insert(0).slot(), 2);
+
+        arena.retain(|index, _| index.slot() != 0 && index.slot() != 7);
+        assert_eq!(reuse_order(&mut arena), [0, 4, 7]);
+    }
+
+    #[test]
+    fn reuse_policy_insert_at_tail() {
+        // Taking the last free slot with `insert_at` must leave the free list's
+        // tail pointing at a slot that is still free.
+        let mut arena = remove_slots(ReusePolicy::Fifo, &[1, 3, 5]);
+        arena.insert_at_slot(5, 50);
+
+        arena.remove_by_slot(6);
+        assert_eq!(reuse_order(&mut arena), [1, 3, 6]);
+    }
+
+    #[test]
+    fn reuse_policy_insert_at_extends() {
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        arena.insert_at_slot(3, 30);
+        arena.remove_by_slot(3);
+
+        assert_eq!(reuse_order(&mut arena), [0, 1, 2, 3]);
+
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::LowestSlot);
+        arena.insert_at_slot(2, 20);
+        arena.remove_by_slot(2);
+        arena.insert_at_slot(5, 50);
+
+        assert_eq!(reuse_order(&mut arena), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn range() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[4]);
+
+        let mut iter = arena.range(2..=5);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some((indices[2], &2)));
+        assert_eq!(iter.next_back(), Some((indices[5], &5)));
+        assert_eq!(iter.next(), Some((indices[3], &3)));
+        assert_eq!(iter.next(), None);
+
+        for (_, value) in arena.range_mut(7..) {
+            *value *= 10;
+        }
+        let values: Vec<_> = arena.values().copied().collect();
+        assert_eq!(values, [0, 1, 2, 3, 5, 6, 70, 80, 90]);
+
+        assert_eq!(arena.range(..).len(), 9);
+        assert_eq!(arena.range(4..5).len(), 0);
+        assert_eq!(arena.range_mut(20..).len(), 0);
+    }
+
+    #[test]
+    fn swap() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+        arena.remove(c);
+
+        assert!(arena.swap(a, b));
+        assert_eq!((arena[a], arena[b]), (2, 1));
+        assert!(arena.swap(a, a));
+        assert!(!arena.swap(a, c));
+        assert_eq!((arena[a], arena[b]), (2, 1));
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn relocate() {
+        for &policy in &POLICIES {
+            let mut arena = Arena::with_reuse_policy(policy);
+            let a = arena.insert(1);
+            let b = arena.insert(2);
+            let c = arena.insert(3);
+            arena.remove(b);
+
+            let moved = arena.relocate(a, b.slot()).unwrap();
+            assert_eq!(moved.slot(), b.slot());
+            assert_ne!(moved, b);
+            assert_eq!(arena[moved], 1);
+            assert_eq!(arena.get(a), None);
+
+            assert_eq!(arena.relocate(a, 5), None);
+            assert_eq!(arena.relocate(moved, c.slot()), None);
+            assert_eq!(arena.relocate(moved, moved.slot()), None);
+
+            let moved_again = arena.relocate(moved, 5).unwrap();
+            assert_eq!(arena[moved_again], 1);
+            assert_eq!(arena.len(), 2);
+
+            // Every free slot is reused once the values are moved around.
+            let reused: Vec<_> = (0..4).map(|i| arena.insert(i).slot()).collect();
+            let mut sorted = reused.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, [0, 1, 3, 4]);
+            assert_ne!(arena.next_index().slot(), a.slot());
+        }
+    }
 }
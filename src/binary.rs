@@ -0,0 +1,810 @@
+//! A compact, versioned binary encoding for [`Arena`]. Requires the `binary`
+//! feature.
+//!
+//! The encoding captures an arena's complete state: values, the generations of
+//! empty slots, the order of the free list, the quarantine, and the reuse
+//! policy. Runs of empty
+//! slots that share a generation are stored once, and all integers are
+//! variable-length encoded, so sparse arenas stay small.
+//!
+//! Values are encoded by a user-provided [`ValueCodec`]. Reading and writing go
+//! through the small [`Reader`] and [`Writer`] traits, which work without the
+//! standard library.
+//!
+//! ```rust
+//! use std::convert::TryFrom;
+//! use thunderdome::Arena;
+//! use thunderdome::binary::{self, DecodeError, Reader, ValueCodec, Writer};
+//!
+//! struct U32Codec;
+//!
+//! impl ValueCodec<u32> for U32Codec {
+//!     fn encode<W: Writer + ?Sized>(&mut self, value: &u32, writer: &mut W) {
+//!         binary::write_varint(writer, u64::from(*value));
+//!     }
+//!
+//!     fn decode<R: Reader + ?Sized>(&mut self, reader: &mut R) -> Result<u32, DecodeError> {
+//!         let value = binary::read_varint(reader)?;
+//!         u32::try_from(value).map_err(|_| DecodeError::InvalidValue)
+//!     }
+//! }
+//!
+//! let mut arena = Arena::new();
+//! let a = arena.insert(5);
+//! arena.insert(6);
+//! arena.remove(a);
+//!
+//! let mut bytes = Vec::new();
+//! binary::encode_arena(&arena, &mut U32Codec, &mut bytes);
+//!
+//! let mut copy = binary::decode_arena(&mut bytes.as_slice(), &mut U32Codec, 1024).unwrap();
+//! assert_eq!(copy.len(), 1);
+//! assert_eq!(copy.insert(7), arena.insert(7));
+//! ```
+
+use core::convert::TryInto;
+use core::fmt;
+
+// Vec is part of the prelude when std is enabled.
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::arena::{Arena, Index, Slot};
+use crate::delta::{ArenaDelta, SlotChange};
+use crate::generation::Generation;
+use crate::policy::ReusePolicy;
+use crate::raw::{RawParts, RawPartsError, RawSlot};
+use crate::storage::SlotStorage;
+
+/// The version of the format written by [`encode_arena`]. Decoding rejects
+/// any other version.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// A destination for encoded bytes.
+pub trait Writer {
+    /// Append the given bytes to the output.
+    fn write_bytes(&mut self, bytes: &[u8]);
+}
+
+impl Writer for Vec<u8> {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+/// A source of encoded bytes.
+pub trait Reader {
+    /// Fill `buffer` with the next bytes of the input, returning
+    /// [`DecodeError::UnexpectedEof`] if there aren't enough bytes left.
+    fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<(), DecodeError>;
+}
+
+impl Reader for &[u8] {
+    fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<(), DecodeError> {
+        if self.len() < buffer.len() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+
+        let (head, tail) = self.split_at(buffer.len());
+        buffer.copy_from_slice(head);
+        *self = tail;
+
+        Ok(())
+    }
+}
+
+/// Encodes and decodes the values stored in an arena.
+pub trait ValueCodec<T> {
+    /// Write `value` to `writer`.
+    fn encode<W: Writer + ?Sized>(&mut self, value: &T, writer: &mut W);
+
+    /// Read a value written by [`ValueCodec::encode`] from `reader`.
+    fn decode<R: Reader + ?Sized>(&mut self, reader: &mut R) -> Result<T, DecodeError>;
+}
+
+/// The error type for decoding binary data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of the data.
+    UnexpectedEof,
+
+    /// The data was written with a format version this crate doesn't
+    /// understand.
+    UnsupportedVersion(u8),
+
+    /// A variable-length integer was longer than 10 bytes or did not fit in
+    /// its destination.
+    InvalidVarint,
+
+    /// The data describes more slots than the caller allowed.
+    TooManySlots,
+
+    /// The data is not laid out the way the format requires.
+    Malformed,
+
+    /// The data describes an arena that isn't valid.
+    InvalidArena(RawPartsError),
+
+    /// A [`ValueCodec`] rejected the bytes of a value.
+    InvalidValue,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of input"),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            DecodeError::InvalidVarint => write!(f, "invalid variable-length integer"),
+            DecodeError::TooManySlots => write!(f, "too many slots"),
+            DecodeError::Malformed => write!(f, "malformed data"),
+            DecodeError::InvalidArena(err) => write!(f, "invalid arena: {}", err),
+            DecodeError::InvalidValue => write!(f, "invalid value"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::InvalidArena(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<RawPartsError> for DecodeError {
+    fn from(err: RawPartsError) -> Self {
+        DecodeError::InvalidArena(err)
+    }
+}
+
+/// Write an unsigned integer using LEB128, taking one byte for every 7 bits.
+// Shifting and masking can't overflow here; every shift is by less than 64.
+#[allow(clippy::arithmetic_side_effects)]
+pub fn write_varint<W: Writer + ?Sized>(writer: &mut W, mut value: u64) {
+    let mut buffer = [0; 10];
+    let mut len = 0;
+
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buffer[len] = byte;
+            len += 1;
+            break;
+        }
+
+        buffer[len] = byte | 0x80;
+        len += 1;
+    }
+
+    writer.write_bytes(&buffer[..len]);
+}
+
+/// Read an unsigned integer written by [`write_varint`].
+// Shifting and masking can't overflow here; every shift is by less than 64.
+#[allow(clippy::arithmetic_side_effects)]
+pub fn read_varint<R: Reader + ?Sized>(reader: &mut R) -> Result<u64, DecodeError> {
+    let mut value: u64 = 0;
+
+    for i in 0..10 {
+        let mut byte = [0];
+        reader.read_bytes(&mut byte)?;
+        let byte = byte[0];
+
+        // The tenth byte only has room for the top bit of a u64.
+        if i == 9 && byte > 1 {
+            return Err(DecodeError::InvalidVarint);
+        }
+
+        value |= u64::from(byte & 0x7f) << (i * 7);
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(DecodeError::InvalidVarint)
+}
+
+fn read_u32<R: Reader + ?Sized>(reader: &mut R) -> Result<u32, DecodeError> {
+    read_varint(reader)?
+        .try_into()
+        .map_err(|_| DecodeError::InvalidVarint)
+}
+
+/// Map a signed integer to an unsigned one so that small magnitudes stay
+/// small.
+#[allow(clippy::arithmetic_side_effects)]
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn policy_code(policy: ReusePolicy) -> u64 {
+    match policy {
+        ReusePolicy::Lifo => 0,
+        ReusePolicy::Fifo => 1,
+        ReusePolicy::LowestSlot => 2,
+    }
+}
+
+/// Write a run length and a flag bit together as one varint.
+#[allow(clippy::arithmetic_side_effects)]
+fn write_run<W: Writer + ?Sized>(writer: &mut W, len: u32, occupied: bool) {
+    write_varint(writer, (u64::from(len) << 1) | u64::from(occupied));
+}
+
+/// Write a free list in reuse order as runs of equal differences between
+/// consecutive slots, which collapses the common orderings.
+fn write_free_list<W: Writer + ?Sized>(writer: &mut W, free_list: &[u32]) {
+    write_varint(writer, free_list.len() as u64);
+
+    let mut previous: i64 = -1;
+    let deltas: Vec<i64> = free_list
+        .iter()
+        .map(|&slot| {
+            let delta = i64::from(slot)
+                .checked_sub(previous)
+                .unwrap_or_else(|| unreachable!());
+            previous = i64::from(slot);
+            delta
+        })
+        .collect();
+
+    let mut rest = deltas.as_slice();
+    while let Some(&delta) = rest.first() {
+        let run = rest.iter().take_while(|&&d| d == delta).count();
+        rest = &rest[run..];
+
+        write_varint(writer, run as u64);
+        write_varint(writer, zigzag(delta));
+    }
+}
+
+/// Read a free list written by `write_free_list`, making sure that every slot
+/// is below `slot_count` and appears only once.
+fn read_free_list<R: Reader + ?Sized>(
+    reader: &mut R,
+    slot_count: u32,
+) -> Result<Vec<u32>, DecodeError> {
+    let free_count = read_u32(reader)?;
+    if free_count > slot_count {
+        return Err(DecodeError::Malformed);
+    }
+
+    let mut free_list: Vec<u32> = Vec::with_capacity(free_count as usize);
+    let mut visited = Vec::new();
+    visited.resize(slot_count as usize, false);
+
+    while free_list.len() < free_count as usize {
+        let run = read_u32(reader)?;
+        let delta = unzigzag(read_varint(reader)?);
+
+        let remaining = (free_count as usize).saturating_sub(free_list.len());
+        if run == 0 || run as usize > remaining {
+            return Err(DecodeError::Malformed);
+        }
+
+        for _ in 0..run {
+            let base = free_list.last().map_or(-1, |&slot| i64::from(slot));
+            let slot: u32 = base
+                .checked_add(delta)
+                .and_then(|slot| slot.try_into().ok())
+                .filter(|&slot| slot < slot_count)
+                .ok_or(DecodeError::Malformed)?;
+
+            if visited[slot as usize] {
+                return Err(DecodeError::InvalidArena(RawPartsError::Cycle { slot }));
+            }
+
+            visited[slot as usize] = true;
+            free_list.push(slot);
+        }
+    }
+
+    Ok(free_list)
+}
+
+/// Encode the complete state of an arena into `writer`, the same state as
+/// [`Arena::into_raw_parts`] returns.
+pub fn encode_arena<T, C, W>(arena: &Arena<T>, codec: &mut C, writer: &mut W)
+where
+    C: ValueCodec<T> + ?Sized,
+    W: Writer + ?Sized,
+{
+    writer.write_bytes(&[FORMAT_VERSION]);
+    write_varint(writer, arena.storage.len() as u64);
+    write_varint(writer, policy_code(arena.policy));
+
+    // Slots are written as runs: a run of occupied slots lists each slot's
+    // generation and value, and a run of empty slots shares one generation.
+    let mut rest = arena.storage.as_slice();
+    while let Some(first) = rest.first() {
+        let run = match first {
+            Slot::Occupied(_) => rest
+                .iter()
+                .take_while(|entry| matches!(entry, Slot::Occupied(_)))
+                .count(),
+            Slot::Empty(first) => rest
+                .iter()
+                .take_while(|entry| {
+                    matches!(entry, Slot::Empty(empty) if empty.generation == first.generation)
+                })
+                .count(),
+        };
+
+        let (run_slots, tail) = rest.split_at(run);
+        rest = tail;
+
+        write_run(writer, run as u32, matches!(first, Slot::Occupied(_)));
+
+        match first {
+            Slot::Occupied(_) => {
+                for entry in run_slots {
+                    if let Slot::Occupied(occupied) = entry {
+                        write_varint(writer, u64::from(occupied.generation.to_u32()));
+                        codec.encode(&occupied.value, writer);
+                    }
+                }
+            }
+            Slot::Empty(empty) => write_varint(writer, u64::from(empty.generation.to_u32())),
+        }
+    }
+
+    let free_list: Vec<u32> = arena.free_list().collect();
+    write_free_list(writer, &free_list);
+
+    let quarantine = &arena.quarantine;
+    write_varint(writer, u64::from(quarantine.epochs));
+    write_varint(writer, quarantine.epoch);
+    write_varint(writer, quarantine.pending.len() as u64);
+    for &(release, slot) in &quarantine.pending {
+        write_varint(writer, release);
+        write_varint(writer, u64::from(slot));
+    }
+}
+
+/// Decode an arena written by [`encode_arena`] from `reader`.
+///
+/// Because runs of empty slots take only a few bytes, a small input can
+/// describe a huge arena. Decoding fails with [`DecodeError::TooManySlots`]
+/// instead of allocating more than `max_slots` slots.
+///
+/// Decoding never panics on invalid input; every problem is reported as a
+/// [`DecodeError`].
+pub fn decode_arena<T, C, R>(
+    reader: &mut R,
+    codec: &mut C,
+    max_slots: u32,
+) -> Result<Arena<T>, DecodeError>
+where
+    C: ValueCodec<T> + ?Sized,
+    R: Reader + ?Sized,
+{
+    let mut version = [0];
+    reader.read_bytes(&mut version)?;
+    if version[0] != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version[0]));
+    }
+
+    let slot_count = read_u32(reader)?;
+    if slot_count > max_slots {
+        return Err(DecodeError::TooManySlots);
+    }
+
+    let policy = match read_varint(reader)? {
+        0 => ReusePolicy::Lifo,
+        1 => ReusePolicy::Fifo,
+        2 => ReusePolicy::LowestSlot,
+        _ => return Err(DecodeError::Malformed),
+    };
+
+    let mut slots: Vec<RawSlot<T>> = Vec::new();
+    while slots.len() < slot_count as usize {
+        let header = read_varint(reader)?;
+        let occupied = header & 1 == 1;
+        let run: usize = (header >> 1)
+            .try_into()
+            .map_err(|_| DecodeError::Malformed)?;
+
+        let remaining = (slot_count as usize).saturating_sub(slots.len());
+        if run == 0 || run > remaining {
+            return Err(DecodeError::Malformed);
+        }
+
+        if occupied {
+            for _ in 0..run {
+                let generation = read_u32(reader)?;
+                let value = codec.decode(reader)?;
+
+                slots.push(RawSlot {
+                    generation,
+                    value: Some(value),
+                    next_free: None,
+                });
+            }
+        } else {
+            let generation = read_u32(reader)?;
+
+            for _ in 0..run {
+                slots.push(RawSlot {
+                    generation,
+                    value: None,
+                    next_free: None,
+                });
+            }
+        }
+    }
+
+    let free_list = read_free_list(reader, slot_count)?;
+    for pair in free_list.windows(2) {
+        slots[pair[0] as usize].next_free = Some(pair[1]);
+    }
+    let first_free = free_list.first().copied();
+
+    let quarantine = read_u32(reader)?;
+    let epoch = read_varint(reader)?;
+
+    let quarantined_count = read_u32(reader)?;
+    if quarantined_count > slot_count {
+        return Err(DecodeError::Malformed);
+    }
+
+    let mut quarantined = Vec::with_capacity(quarantined_count as usize);
+    for _ in 0..quarantined_count {
+        let release = read_varint(reader)?;
+        let slot = read_u32(reader)?;
+        quarantined.push((release, slot));
+    }
+
+    let arena = Arena::from_raw_parts(RawParts {
+        slots,
+        first_free,
+        policy,
+        quarantine,
+        epoch,
+        quarantined,
+    })?;
+
+    Ok(arena)
+}
+
+/// Encode an [`ArenaDelta`] into `writer`. Only the changed slots and, if they
+/// changed, the free list and quarantined slots are written.
+pub fn encode_delta<T, C, W>(delta: &ArenaDelta<T>, codec: &mut C, writer: &mut W)
+where
+    C: ValueCodec<T> + ?Sized,
+    W: Writer + ?Sized,
+{
+    writer.write_bytes(&[FORMAT_VERSION]);
+    write_varint(writer, u64::from(delta.slot_count));
+    write_varint(writer, delta.changes.len() as u64);
+
+    // Changes are sorted by slot, so each slot is written as the gap from the
+    // slot after the previous change.
+    let mut next_slot: u32 = 0;
+    for change in &delta.changes {
+        let index = change.index();
+        let gap = index
+            .slot
+            .checked_sub(next_slot)
+            .unwrap_or_else(|| unreachable!("delta changes are sorted by slot"));
+        next_slot = index.slot.saturating_add(1);
+
+        write_varint(writer, u64::from(gap));
+
+        let (tag, value) = match change {
+            SlotChange::Inserted { value, .. } => (0, Some(value)),
+            SlotChange::Changed { value, .. } => (1, Some(value)),
+            SlotChange::Invalidated { .. } => (2, None),
+            SlotChange::Removed { .. } => (3, None),
+        };
+
+        write_varint(writer, tag);
+        write_varint(writer, u64::from(index.generation.to_u32()));
+        if let Some(value) = value {
+            codec.encode(value, writer);
+        }
+    }
+
+    match (&delta.free_list, &delta.quarantined) {
+        (Some(free_list), Some(quarantined)) => {
+            writer.write_bytes(&[1]);
+            write_free_list(writer, free_list);
+
+            write_varint(writer, quarantined.len() as u64);
+            for &(left, slot) in quarantined {
+                write_varint(writer, left);
+                write_varint(writer, u64::from(slot));
+            }
+        }
+        _ => writer.write_bytes(&[0]),
+    }
+}
+
+/// Decode an [`ArenaDelta`] written by [`encode_delta`] from `reader`.
+///
+/// Like with [`decode_arena`], deltas that describe more than `max_slots`
+/// slots are rejected with [`DecodeError::TooManySlots`]. A decoded delta is
+/// well-formed, but can still fail to apply to an arena that isn't in the
+/// state it was computed from.
+pub fn decode_delta<T, C, R>(
+    reader: &mut R,
+    codec: &mut C,
+    max_slots: u32,
+) -> Result<ArenaDelta<T>, DecodeError>
+where
+    C: ValueCodec<T> + ?Sized,
+    R: Reader + ?Sized,
+{
+    let mut version = [0];
+    reader.read_bytes(&mut version)?;
+    if version[0] != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(version[0]));
+    }
+
+    let slot_count = read_u32(reader)?;
+    if slot_count > max_slots {
+        return Err(DecodeError::TooManySlots);
+    }
+
+    let change_count = read_u32(reader)?;
+    if change_count > slot_count {
+        return Err(DecodeError::Malformed);
+    }
+
+    let mut changes = Vec::with_capacity(change_count as usize);
+    let mut next_slot: u32 = 0;
+
+    for _ in 0..change_count {
+        let slot = next_slot
+            .checked_add(read_u32(reader)?)
+            .filter(|&slot| slot < slot_count)
+            .ok_or(DecodeError::Malformed)?;
+        next_slot = slot.saturating_add(1);
+
+        let tag = read_varint(reader)?;
+        let generation = Generation::from_u32(read_u32(reader)?).ok_or(
+            DecodeError::InvalidArena(RawPartsError::ZeroGeneration { slot }),
+        )?;
+        let index = Index { slot, generation };
+
+        let change = match tag {
+            0 => SlotChange::Inserted {
+                index,
+                value: codec.decode(reader)?,
+            },
+            1 => SlotChange::Changed {
+                index,
+                value: codec.decode(reader)?,
+            },
+            2 => SlotChange::Invalidated { index },
+            3 => SlotChange::Removed { index },
+            _ => return Err(DecodeError::Malformed),
+        };
+
+        changes.push(change);
+    }
+
+    let mut has_free_list = [0];
+    reader.read_bytes(&mut has_free_list)?;
+    let (free_list, quarantined) = match has_free_list[0] {
+        0 => (None, None),
+        1 => {
+            let free_list = read_free_list(reader, slot_count)?;
+
+            let quarantined_count = read_u32(reader)?;
+            if quarantined_count > slot_count {
+                return Err(DecodeError::Malformed);
+            }
+
+            let mut quarantined = Vec::with_capacity(quarantined_count as usize);
+            for _ in 0..quarantined_count {
+                let left = read_varint(reader)?;
+                let slot = read_u32(reader)?;
+                if slot >= slot_count {
+                    return Err(DecodeError::Malformed);
+                }
+
+                quarantined.push((left, slot));
+            }
+
+            (Some(free_list), Some(quarantined))
+        }
+        _ => return Err(DecodeError::Malformed),
+    };
+
+    Ok(ArenaDelta {
+        slot_count,
+        changes,
+        free_list,
+        quarantined,
+    })
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{
+        decode_arena, decode_delta, encode_arena, encode_delta, read_varint, write_varint,
+        DecodeError, Reader, ValueCodec, Writer, FORMAT_VERSION,
+    };
+    use crate::{Arena, ReusePolicy};
+
+    struct StringCodec;
+
+    impl ValueCodec<String> for StringCodec {
+        fn encode<W: Writer + ?Sized>(&mut self, value: &String, writer: &mut W) {
+            write_varint(writer, value.len() as u64);
+            writer.write_bytes(value.as_bytes());
+        }
+
+        fn decode<R: Reader + ?Sized>(&mut self, reader: &mut R) -> Result<String, DecodeError> {
+            let len = read_varint(reader)?;
+            if len > 1024 {
+                return Err(DecodeError::InvalidValue);
+            }
+
+            let mut bytes = vec![0; len as usize];
+            reader.read_bytes(&mut bytes)?;
+            String::from_utf8(bytes).map_err(|_| DecodeError::InvalidValue)
+        }
+    }
+
+    fn encode(arena: &Arena<String>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        encode_arena(arena, &mut StringCodec, &mut bytes);
+        bytes
+    }
+
+    fn decode(mut bytes: &[u8]) -> Result<Arena<String>, DecodeError> {
+        decode_arena(&mut bytes, &mut StringCodec, 1 << 16)
+    }
+
+    fn sample() -> Arena<String> {
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        let indices: Vec<_> = (0..20).map(|i| arena.insert(i.to_string())).collect();
+
+        for &index in indices.iter().skip(3).step_by(2) {
+            arena.remove(index);
+        }
+        arena.invalidate(indices[0]);
+
+        arena
+    }
+
+    #[test]
+    fn varint_roundtrip() {
+        for &value in &[0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            assert_eq!(read_varint(&mut bytes.as_slice()), Ok(value));
+        }
+
+        let overlong = [0xff; 11];
+        assert_eq!(
+            read_varint(&mut &overlong[..]),
+            Err(DecodeError::InvalidVarint)
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut arena = sample();
+        let mut copy = decode(&encode(&arena)).unwrap();
+
+        assert_eq!(copy.reuse_policy(), ReusePolicy::Fifo);
+        assert_eq!(copy.to_entries(), arena.to_entries());
+
+        for i in 0..12 {
+            assert_eq!(copy.insert(i.to_string()), arena.insert(i.to_string()));
+        }
+    }
+
+    #[test]
+    fn sparse_arenas_stay_small() {
+        let mut arena = Arena::new();
+        arena.insert_at_slot(10_000, String::from("far away"));
+
+        // Nearly ten thousand empty slots collapse into a couple of runs.
+        let bytes = encode(&arena);
+        assert!(bytes.len() < 32, "encoded to {} bytes", bytes.len());
+
+        let mut copy = decode(&bytes).unwrap();
+        assert_eq!(copy.insert(String::new()), arena.insert(String::new()));
+    }
+
+    #[test]
+    fn delta_roundtrip() {
+        let mut previous = sample();
+        previous.set_quarantine(1);
+        let mut arena = previous.clone();
+        let indices: Vec<_> = arena.iter().map(|(index, _)| index).collect();
+
+        arena[indices[1]].push('!');
+        arena.remove(indices[2]);
+        arena.invalidate(indices[3]);
+        arena.insert(String::from("new"));
+        arena.insert_at_slot(40, String::from("far"));
+
+        let delta = arena.diff(&previous);
+        let mut bytes = Vec::new();
+        encode_delta(&delta, &mut StringCodec, &mut bytes);
+
+        let decoded = decode_delta(&mut bytes.as_slice(), &mut StringCodec, 1024).unwrap();
+        assert_eq!(decoded, delta);
+
+        let mut receiver = previous.clone();
+        receiver.apply(decoded).unwrap();
+        assert_eq!(receiver.into_raw_parts(), arena.into_raw_parts());
+
+        for len in 0..bytes.len() {
+            assert!(decode_delta(&mut &bytes[..len], &mut StringCodec, 1024).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let mut bytes = encode(&sample());
+
+        bytes[0] = FORMAT_VERSION + 1;
+        assert_eq!(
+            decode(&bytes).unwrap_err(),
+            DecodeError::UnsupportedVersion(FORMAT_VERSION + 1)
+        );
+
+        let mut arena = Arena::new();
+        arena.insert_at_slot(100, String::new());
+        let bytes = encode(&arena);
+        let result = decode_arena(&mut bytes.as_slice(), &mut StringCodec, 50);
+        assert_eq!(result.unwrap_err(), DecodeError::TooManySlots);
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let bytes = encode(&sample());
+
+        for len in 0..bytes.len() {
+            assert!(decode(&bytes[..len]).is_err(), "decoded {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn corrupted_input_does_not_panic() {
+        let bytes = encode(&sample());
+
+        // A small xorshift generator keeps this test deterministic without
+        // pulling in a dependency.
+        let mut state: u32 = 0x1234_5678;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        for _ in 0..2000 {
+            let mut corrupted = bytes.clone();
+            for _ in 0..(next() % 4 + 1) {
+                let position = next() as usize % corrupted.len();
+                corrupted[position] = next() as u8;
+            }
+
+            let _ = decode(&corrupted);
+        }
+
+        for _ in 0..2000 {
+            let len = next() as usize % 64;
+            let random: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            let _ = decode(&random);
+        }
+    }
+}
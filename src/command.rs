@@ -0,0 +1,403 @@
+// Vec is part of the prelude when std is enabled.
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use core::convert::TryInto;
+use core::iter::successors;
+
+use crate::arena::{Arena, Index, Slot};
+use crate::generation::Generation;
+use crate::policy::ReusePolicy;
+use crate::remap::IndexRemap;
+use crate::storage::SlotStorage;
+
+/// A set of changes that can be applied to an [`Arena`] with
+/// [`Arena::apply`]: an [`ArenaDelta`](crate::ArenaDelta) or a
+/// [`CommandBuffer`].
+pub trait Apply<T> {
+    /// What applying the changes returns.
+    type Output;
+
+    /// Apply the changes to the given arena.
+    fn apply_to(self, arena: &mut Arena<T>) -> Self::Output;
+}
+
+impl<T> Arena<T> {
+    /// Apply a set of changes to the arena: an
+    /// [`ArenaDelta`](crate::ArenaDelta) created by [`Arena::diff`], or the
+    /// commands recorded in a [`CommandBuffer`]. See the [`Apply`]
+    /// implementations of each for details.
+    pub fn apply<A: Apply<T>>(&mut self, changes: A) -> A::Output {
+        changes.apply_to(self)
+    }
+
+    /// Start recording changes to the arena that will be applied later with
+    /// [`Arena::apply`], such as while the arena is borrowed by
+    /// [`Arena::iter_mut`].
+    ///
+    /// Creating a command buffer takes time proportional to the number of
+    /// slots in the arena's free list.
+    pub fn command_buffer(&self) -> CommandBuffer<T> {
+        let mut free: Vec<Index> = successors(self.first_free, |pointer| {
+            self.empty_slot(pointer.slot()).next_free
+        })
+        .map(|pointer| Index {
+            slot: pointer.slot(),
+            generation: self.empty_slot(pointer.slot()).generation.next(),
+        })
+        .collect();
+        free.reverse();
+
+        let storage_len = self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        });
+
+        CommandBuffer {
+            commands: Vec::new(),
+            free,
+            next_slot: storage_len,
+            policy: self.policy,
+        }
+    }
+}
+
+/// Changes to an [`Arena`] that are recorded now and applied later, so that
+/// values can be inserted and removed while the arena is borrowed.
+///
+/// Created with [`Arena::command_buffer`] and applied with [`Arena::apply`].
+/// Inserting through the buffer returns the index the value will have once
+/// it's applied, which is the index that the same sequence of
+/// [`Arena::insert`] and [`Arena::insert_at`] calls would have returned when
+/// the buffer was created. The first index is the arena's
+/// [`Arena::next_index`]. Removals recorded in the buffer don't free slots for
+/// later inserts in the buffer.
+///
+/// Commands are applied in the order they were recorded:
+///
+/// * An insert takes the index it was given, as long as that index is still
+///   free. If the arena was changed since the buffer was created and the index
+///   is no longer free, the value is inserted like with [`Arena::insert`]
+///   instead, and the returned [`IndexRemap`] maps the index the buffer gave
+///   out to the value's actual index. Later commands that use the old index
+///   are translated.
+/// * Removing an index that isn't in the arena does nothing.
+/// * Invalidating an index that isn't in the arena does nothing.
+/// * Inserting at an index replaces and drops any value in its slot, like
+///   with [`Arena::insert_at`].
+///
+/// ```rust
+/// # use thunderdome::Arena;
+/// let mut arena = Arena::new();
+/// for i in 0..5 {
+///     arena.insert(i);
+/// }
+///
+/// let mut commands = arena.command_buffer();
+/// for (index, value) in arena.iter_mut() {
+///     if *value % 2 == 0 {
+///         commands.remove(index);
+///     } else {
+///         *value *= 10;
+///         commands.insert(*value + 1);
+///     }
+/// }
+///
+/// let remap = arena.apply(commands);
+/// assert!(remap.is_empty());
+///
+/// let mut values: Vec<_> = arena.values().copied().collect();
+/// values.sort();
+/// assert_eq!(values, [10, 11, 30, 31]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CommandBuffer<T> {
+    commands: Vec<Command<T>>,
+
+    /// Indices from the arena's free list that inserts will be given, in
+    /// reverse order so that the next one can be popped off the end.
+    free: Vec<Index>,
+
+    /// The slot past the end of the arena's storage that the next insert will
+    /// be given once `free` runs out.
+    next_slot: u32,
+
+    /// The arena's reuse policy, which decides where the slots skipped by an
+    /// `insert_at` past the end of the arena join the free list.
+    policy: ReusePolicy,
+}
+
+#[derive(Debug, Clone)]
+enum Command<T> {
+    Insert(Index, T),
+    InsertAt(Index, T),
+    Remove(Index),
+    Invalidate(Index),
+}
+
+impl<T> CommandBuffer<T> {
+    /// Return the number of commands in the buffer.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns true if the buffer has no commands.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Record an insertion, returning the index the value will have.
+    pub fn insert(&mut self, value: T) -> Index {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                let slot = self.next_slot;
+                self.next_slot = slot.checked_add(1).unwrap_or_else(|| {
+                    panic!("Cannot insert more than u32::MAX elements into Arena")
+                });
+
+                Index {
+                    slot,
+                    generation: Generation::first(),
+                }
+            }
+        };
+
+        self.commands.push(Command::Insert(index, value));
+        index
+    }
+
+    /// Record an insertion at the given index. See [`Arena::insert_at`].
+    ///
+    /// Later inserts in the buffer won't be given the index's slot. If the
+    /// slot is past the end of the arena, the slots before it are added to the
+    /// free list when the buffer is applied, and later inserts are given those
+    /// too.
+    pub fn insert_at(&mut self, index: Index, value: T) {
+        let slot = index.slot;
+
+        if slot < self.next_slot {
+            self.free.retain(|free| free.slot != slot);
+        } else {
+            // Applying this will extend the arena with empty slots, each
+            // linked into the free list like with `Arena::insert_at`.
+            for skipped in self.next_slot..slot {
+                let index = Index {
+                    slot: skipped,
+                    generation: Generation::first().next(),
+                };
+
+                match self.policy {
+                    ReusePolicy::Lifo => self.free.push(index),
+                    // Skipped slots are higher than every free slot, so
+                    // `LowestSlot` puts them at the back, like `Fifo`.
+                    ReusePolicy::Fifo | ReusePolicy::LowestSlot => self.free.insert(0, index),
+                }
+            }
+
+            self.next_slot = slot
+                .checked_add(1)
+                .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+        }
+
+        self.commands.push(Command::InsertAt(index, value));
+    }
+
+    /// Record the removal of the given index. See [`Arena::remove`].
+    pub fn remove(&mut self, index: Index) {
+        self.commands.push(Command::Remove(index));
+    }
+
+    /// Record the invalidation of the given index, returning the index the
+    /// value will have if `index` is in the arena when the buffer is applied.
+    /// See [`Arena::invalidate`].
+    pub fn invalidate(&mut self, index: Index) -> Index {
+        self.commands.push(Command::Invalidate(index));
+
+        Index {
+            generation: index.generation.next(),
+            ..index
+        }
+    }
+}
+
+/// Apply the commands in order, returning a remap for any inserts that
+/// couldn't be given the index the buffer returned for them. See
+/// [`CommandBuffer`].
+impl<T> Apply<T> for CommandBuffer<T> {
+    type Output = IndexRemap;
+
+    fn apply_to(self, arena: &mut Arena<T>) -> IndexRemap {
+        let mut remap = IndexRemap::new();
+        let translate = |remap: &IndexRemap, index: Index| remap.get(index).unwrap_or(index);
+
+        for command in self.commands {
+            match command {
+                Command::Insert(index, value) => {
+                    if is_free(arena, index) {
+                        arena.insert_at(index, value);
+                    } else {
+                        let new = arena.insert(value);
+                        remap.insert(index, new);
+                    }
+                }
+                Command::InsertAt(index, value) => {
+                    arena.insert_at(translate(&remap, index), value);
+                }
+                Command::Remove(index) => {
+                    arena.remove(translate(&remap, index));
+                }
+                Command::Invalidate(index) => {
+                    let translated = translate(&remap, index);
+
+                    if let Some(new) = arena.invalidate(translated) {
+                        if translated != index {
+                            let predicted = Index {
+                                generation: index.generation.next(),
+                                ..index
+                            };
+                            remap.insert(predicted, new);
+                        }
+                    }
+                }
+            }
+        }
+
+        remap
+    }
+}
+
+/// Returns true if inserting at `index` wouldn't replace a value, take a slot
+/// out of quarantine, or bring back an index that was used before.
+fn is_free<T>(arena: &Arena<T>, index: Index) -> bool {
+    match arena.storage.get(index.slot as usize) {
+        Some(Slot::Empty(empty)) => {
+            empty.generation.next() == index.generation
+                && !arena
+                    .quarantine
+                    .pending
+                    .iter()
+                    .any(|&(_, slot)| slot == index.slot)
+        }
+        Some(Slot::Occupied(_)) => false,
+        None => true,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::generation::Generation;
+    use crate::test_util::POLICIES;
+    use crate::{Arena, Index};
+
+    #[test]
+    fn inserts_match_next_index() {
+        for &policy in &POLICIES {
+            let mut arena = Arena::with_reuse_policy(policy);
+            let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+            for &i in &[5, 1, 6] {
+                arena.remove(indices[i]);
+            }
+
+            let mut expected = arena.clone();
+            let mut commands = arena.command_buffer();
+            assert_eq!(commands.insert(100), arena.next_index());
+
+            let reserved: Vec<_> = (0..4).map(|i| commands.insert(i)).collect();
+            assert_eq!(expected.insert(100), arena.next_index());
+            for (i, &index) in reserved.iter().enumerate() {
+                assert_eq!(expected.insert(i as u32), index);
+            }
+
+            let remap = arena.apply(commands);
+            assert!(remap.is_empty());
+            assert!(arena.same_state(&expected));
+        }
+    }
+
+    #[test]
+    fn inserts_after_insert_at() {
+        for &policy in &POLICIES {
+            let mut arena = Arena::with_reuse_policy(policy);
+            let indices: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+            arena.remove(indices[1]);
+
+            let mut expected = arena.clone();
+            let mut commands = arena.command_buffer();
+
+            let past_end = Index {
+                slot: 8,
+                generation: Generation::first(),
+            };
+            commands.insert_at(past_end, 100);
+            expected.insert_at(past_end, 100);
+
+            let reserved: Vec<_> = (0..4).map(|i| commands.insert(i)).collect();
+            for (i, &index) in reserved.iter().enumerate() {
+                assert_eq!(expected.insert(i as u32), index);
+            }
+
+            // Taking a free slot keeps later inserts from being given it.
+            let free = expected.next_index();
+            commands.insert_at(free, 200);
+            expected.insert_at(free, 200);
+            assert_eq!(commands.insert(300), expected.insert(300));
+
+            let remap = arena.apply(commands);
+            assert!(remap.is_empty());
+            assert!(arena.same_state(&expected));
+        }
+    }
+
+    #[test]
+    fn commands_run_in_order() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+
+        let mut commands = arena.command_buffer();
+        let c = commands.insert("c");
+        commands.remove(c);
+        let new_b = commands.invalidate(b);
+        commands.remove(b);
+        commands.remove(a);
+        commands.remove(a);
+        commands.insert_at(a, "A");
+        assert_eq!(commands.len(), 7);
+
+        let remap = arena.apply(commands);
+        assert!(remap.is_empty());
+        assert_eq!(arena.get(c), None);
+        assert_eq!(arena[new_b], "b");
+        assert_eq!(arena[a], "A");
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn conflicting_inserts_are_remapped() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        arena.remove(a);
+
+        let mut commands = arena.command_buffer();
+        let reserved = commands.insert(2);
+        let past_end = commands.insert(3);
+        let reserved_next = commands.invalidate(reserved);
+        commands.insert_at(past_end, 4);
+
+        // Someone else takes the reserved slot before the buffer is applied.
+        let taken = arena.insert(10);
+        assert_eq!(taken, reserved);
+
+        // The value meant for slot 0 moves to slot 1, so the value meant for
+        // slot 1 moves to slot 2.
+        let remap = arena.apply(commands);
+        let moved = remap.get(reserved_next).unwrap();
+        let moved_past_end = remap.get(past_end).unwrap();
+        assert_eq!(moved.slot(), 1);
+        assert_eq!(moved_past_end.slot(), 2);
+        assert_eq!(arena[moved], 2);
+        assert_eq!(arena[moved_past_end], 4);
+        assert_eq!(arena[taken], 10);
+        assert_eq!(arena.len(), 3);
+    }
+}
@@ -0,0 +1,1416 @@
+//! A copy-on-write arena whose snapshots share storage.
+
+use core::convert::TryInto;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::{Enumerate, FusedIterator};
+use core::marker::PhantomData;
+use core::mem::swap;
+use core::ops::{self, RangeBounds};
+use core::slice;
+
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+#[cfg(not(feature = "std"))]
+use alloc::{sync::Arc, vec::Vec};
+
+use crate::arena::{Arena, Index, OccupiedSlot, Slot};
+use crate::entry;
+use crate::free_pointer::FreePointer;
+use crate::hash;
+use crate::iter::{IntoIter, IntoValues};
+use crate::policy::ReusePolicy;
+use crate::quarantine::Quarantine;
+use crate::storage::sealed::ArenaLike;
+use crate::storage::{SlotStorage, SlotStorageMut};
+
+/// Slots are stored in chunks of `1 << CHUNK_BITS` slots.
+const CHUNK_BITS: u32 = 6;
+const CHUNK_LEN: usize = 1 << CHUNK_BITS;
+const CHUNK_MASK: u32 = (1 << CHUNK_BITS) - 1;
+
+type Chunk<T> = Arc<Vec<Slot<T>>>;
+
+/// A view into a single entry in a [`CowArena`], which may either be vacant
+/// or occupied. See [`crate::Entry`].
+pub type Entry<'a, T> = entry::Entry<'a, T, CowArena<T>>;
+
+/// A view into a vacant entry in a [`CowArena`]. See [`crate::VacantEntry`].
+pub type VacantEntry<'a, T> = entry::VacantEntry<'a, T, CowArena<T>>;
+
+/// A view into an occupied entry in a [`CowArena`]. See
+/// [`crate::OccupiedEntry`].
+pub type OccupiedEntry<'a, T> = entry::OccupiedEntry<'a, T, CowArena<T>>;
+
+/// See [`CowArena::drain`].
+pub type Drain<'a, T> = crate::iter::Drain<'a, T, CowArena<T>>;
+
+/// An arena with the same behavior as [`Arena`], whose storage is split into
+/// reference-counted chunks that are shared between snapshots.
+///
+/// [`CowArena::snapshot`] takes constant time, no matter how many values are
+/// in the arena. Changing a slot copies the chunk of 64 slots around it if that
+/// chunk is shared with a snapshot. The first change after taking a snapshot
+/// also copies the arena's list of chunk pointers, which takes time
+/// proportional to the number of slots divided by 64. A small change after
+/// taking a snapshot is still much cheaper than copying every value, which
+/// makes it practical to keep a snapshot of every frame for rollback.
+///
+/// Changing values requires `T: Clone`, since values in a shared chunk have to
+/// be cloned.
+///
+/// `CowArena` has the methods of [`Arena`] for inserting, looking up, removing,
+/// and iterating over values, as well as entries, quarantine, and `map`. The
+/// rest of the `Arena` API, such as [`Arena::range_mut`],
+/// [`Arena::drain_range`], [`Arena::extract_if`], [`Arena::iter_from`],
+/// transactions, deltas, and raw parts, is only available on `Arena`; convert
+/// with [`CowArena::to_arena`] and [`CowArena::from`] to use it.
+///
+/// ```rust
+/// # use thunderdome::CowArena;
+/// let mut arena = CowArena::new();
+/// let a = arena.insert(1);
+///
+/// let snapshot = arena.snapshot();
+/// arena[a] = 2;
+/// let b = arena.insert(3);
+///
+/// assert_eq!(snapshot[a], 1);
+/// assert_eq!(snapshot.get(b), None);
+/// assert_eq!(arena[a], 2);
+/// ```
+pub struct CowArena<T> {
+    chunks: Arc<Vec<Chunk<T>>>,
+    slot_count: u32,
+    len: u32,
+    first_free: Option<FreePointer>,
+    last_free: Option<FreePointer>,
+    policy: ReusePolicy,
+    quarantine: Arc<Quarantine>,
+}
+
+/// Split a slot into the position of its chunk and its position in the chunk.
+fn locate(slot: u32) -> (usize, usize) {
+    ((slot >> CHUNK_BITS) as usize, (slot & CHUNK_MASK) as usize)
+}
+
+/// The number of chunks needed to hold the given number of slots.
+fn chunks_for(slots: usize) -> usize {
+    slots.div_ceil(CHUNK_LEN)
+}
+
+/// The value in a slot of `get2_mut`, if the slot's index was found.
+fn occupied_value<T>(slot: &mut Slot<T>, found: bool) -> Option<&mut T> {
+    match slot {
+        Slot::Occupied(occupied) if found => Some(&mut occupied.value),
+        _ => None,
+    }
+}
+
+/// The slot at the given position of the given chunk.
+fn slot_at(chunk: usize, offset: usize) -> u32 {
+    let chunk: u32 = chunk
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("CowArena has more chunks than slots"));
+
+    (chunk << CHUNK_BITS) | offset as u32
+}
+
+impl<T> CowArena<T> {
+    /// Construct an empty arena.
+    pub fn new() -> Self {
+        Self::with_reuse_policy(ReusePolicy::Lifo)
+    }
+
+    /// Construct an empty arena whose list of chunks has room for at least
+    /// `capacity` elements. See [`CowArena::capacity`].
+    pub fn with_capacity(capacity: usize) -> Self {
+        CowArena {
+            chunks: Arc::new(Vec::with_capacity(chunks_for(capacity))),
+            ..Self::new()
+        }
+    }
+
+    /// Construct an empty arena that reuses free slots according to the given
+    /// [`ReusePolicy`].
+    pub fn with_reuse_policy(policy: ReusePolicy) -> Self {
+        CowArena {
+            chunks: Arc::new(Vec::new()),
+            slot_count: 0,
+            len: 0,
+            first_free: None,
+            last_free: None,
+            policy,
+            quarantine: Arc::new(Quarantine::new()),
+        }
+    }
+
+    /// Return the [`ReusePolicy`] this arena was created with.
+    pub fn reuse_policy(&self) -> ReusePolicy {
+        self.policy
+    }
+
+    /// Quarantine slots freed from now on for the given number of epochs. See
+    /// [`Arena::set_quarantine`].
+    pub fn set_quarantine(&mut self, epochs: u32) {
+        Arc::make_mut(&mut self.quarantine).epochs = epochs;
+    }
+
+    /// Return the number of epochs freed slots are quarantined for. See
+    /// [`CowArena::set_quarantine`].
+    pub fn quarantine(&self) -> u32 {
+        self.quarantine.epochs
+    }
+
+    /// Return the number of freed slots that are currently in quarantine.
+    pub fn quarantined_len(&self) -> usize {
+        self.quarantine.len() as usize
+    }
+
+    /// Take a snapshot of the arena in constant time. The snapshot shares
+    /// storage with the arena until one of them is changed.
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Return the number of elements contained in the arena.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Return the number of elements the arena can hold before its list of
+    /// chunks has to grow, including the elements currently in the arena.
+    /// Chunks themselves are allocated as they're needed.
+    pub fn capacity(&self) -> usize {
+        self.chunks.capacity().saturating_mul(CHUNK_LEN)
+    }
+
+    /// Reserve room in the arena's list of chunks for at least `additional`
+    /// more elements to be inserted. See [`CowArena::capacity`].
+    pub fn reserve(&mut self, additional: usize) {
+        let slot_count = self.slot_count as usize;
+        let currently_free = slot_count
+            .saturating_sub(self.len as usize)
+            .saturating_sub(self.quarantined_len());
+        let needed =
+            chunks_for(slot_count.saturating_add(additional.saturating_sub(currently_free)));
+
+        if needed > self.chunks.capacity() {
+            let chunks = Arc::make_mut(&mut self.chunks);
+            chunks.reserve(needed.saturating_sub(chunks.len()));
+        }
+    }
+
+    /// Returns whether the arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Compute the `Index` that the next call to [`CowArena::insert`] would
+    /// produce, without mutating the arena.
+    pub fn next_index(&self) -> Index {
+        self.next_insert_index()
+    }
+
+    /// Returns true if the given index is valid for the arena.
+    pub fn contains(&self, index: Index) -> bool {
+        self.get(index).is_some()
+    }
+
+    /// Checks to see whether a slot is occupied in the arena, and if it is,
+    /// returns `Some` with the true `Index` of that slot (slot plus generation.)
+    /// Otherwise, returns `None`.
+    pub fn contains_slot(&self, slot: u32) -> Option<Index> {
+        self.get_by_slot(slot).map(|(index, _)| index)
+    }
+
+    /// Get an immutable reference to a value inside the arena by
+    /// [`Index`], returning `None` if the index is not contained in the arena.
+    pub fn get(&self, index: Index) -> Option<&T> {
+        match self.slot(index.slot) {
+            Some(Slot::Occupied(occupied)) if occupied.generation == index.generation => {
+                Some(&occupied.value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Attempt to look up the given slot in the arena, disregarding any generational
+    /// information, and retrieve an immutable reference to it. Returns `None` if the
+    /// slot is empty.
+    pub fn get_by_slot(&self, slot: u32) -> Option<(Index, &T)> {
+        match self.slot(slot) {
+            Some(Slot::Occupied(occupied)) => {
+                let index = Index {
+                    slot,
+                    generation: occupied.generation,
+                };
+
+                Some((index, &occupied.value))
+            }
+            _ => None,
+        }
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            chunks: &self.chunks,
+            front: 0,
+            back: self.slot_count,
+            len: self.len,
+        }
+    }
+
+    /// Iterate over all of the values contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn values(&self) -> Values<'_, T> {
+        Values { inner: self.iter() }
+    }
+
+    /// Iterate over all of the indices contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn keys(&self) -> Keys<'_, T> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Iterate over the values in the given range of slots, in slot order.
+    /// Parts of the range past the end of the arena's storage are ignored.
+    ///
+    /// Creating the iterator takes time proportional to the size of the range.
+    pub fn range<R: RangeBounds<u32>>(&self, slots: R) -> Iter<'_, T> {
+        let (start, end) = self.slot_bounds(slots);
+        let (front, back) = (start as u32, end as u32);
+
+        let len = (front..back)
+            .filter(|&slot| matches!(self.slot(slot), Some(Slot::Occupied(_))))
+            .count()
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("CowArena held more than u32::MAX values"));
+
+        Iter {
+            chunks: &self.chunks,
+            front,
+            back,
+            len,
+        }
+    }
+
+    /// Returns true if both arenas share all of their storage, which is the
+    /// case for a snapshot that neither arena has changed since.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.chunks, &other.chunks)
+    }
+
+    /// Feed the complete state of the arena to a hasher. This is defined the
+    /// same way as [`Arena::state_hash`], so a `CowArena` and an [`Arena`] in
+    /// the same state hash the same.
+    pub fn state_hash<H: Hasher>(&self, state: &mut H)
+    where
+        T: Hash,
+    {
+        let slots = self.chunks.iter().flat_map(|chunk| chunk.iter());
+
+        hash::hash_state(
+            self.policy,
+            self.slot_count,
+            slots,
+            self.free_list(),
+            self.quarantine.epochs,
+            self.quarantined(),
+            state,
+        );
+    }
+
+    /// Create a new arena by converting a reference to every value with the
+    /// given function, leaving this arena untouched. See [`Arena::map_ref`].
+    pub fn map_ref<U, F: FnMut(Index, &T) -> U>(&self, mut f: F) -> CowArena<U> {
+        let chunks = self
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(chunk, slots)| {
+                let slots = slots
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, entry)| match entry {
+                        Slot::Occupied(occupied) => {
+                            let index = Index {
+                                slot: slot_at(chunk, offset),
+                                generation: occupied.generation,
+                            };
+
+                            Slot::Occupied(OccupiedSlot {
+                                generation: occupied.generation,
+                                value: f(index, &occupied.value),
+                            })
+                        }
+                        Slot::Empty(empty) => Slot::Empty(*empty),
+                    })
+                    .collect();
+
+                Arc::new(slots)
+            })
+            .collect();
+
+        CowArena {
+            chunks: Arc::new(chunks),
+            slot_count: self.slot_count,
+            len: self.len,
+            first_free: self.first_free,
+            last_free: self.last_free,
+            policy: self.policy,
+            quarantine: Arc::clone(&self.quarantine),
+        }
+    }
+}
+
+impl<T: Clone> CowArena<T> {
+    /// Advance the arena to its next epoch, releasing every quarantined slot
+    /// whose quarantine has expired back to the free list. See
+    /// [`Arena::advance_epoch`].
+    pub fn advance_epoch(&mut self) {
+        self.release_expired();
+    }
+
+    /// Insert a new value into the arena, returning an index that can be used
+    /// to later retrieve the value.
+    pub fn insert(&mut self, value: T) -> Index {
+        self.insert_next(value)
+    }
+
+    /// Insert a new value at a given index, returning the old value if present. The entry's
+    /// generation is set to the given index's generation.
+    ///
+    /// Like [`Arena::insert_at`], this method is capable of "resurrecting" an
+    /// old `Index`.
+    pub fn insert_at(&mut self, index: Index, value: T) -> Option<T> {
+        self.insert_at_inner(index.slot, Some(index.generation), value)
+            .1
+    }
+
+    /// Insert a new value at a given slot, returning the old value if present. If the slot is
+    /// already occupied, this will increment the generation of the slot, and invalidate any
+    /// previous indices pointing to it.
+    pub fn insert_at_slot(&mut self, slot: u32, value: T) -> (Index, Option<T>) {
+        self.insert_at_inner(slot, None, value)
+    }
+
+    /// Get a mutable reference to a value inside the arena by [`Index`],
+    /// returning `None` if the index is not contained in the arena.
+    pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        if !self.contains(index) {
+            return None;
+        }
+
+        self.slot_mut(index.slot)
+            .and_then(|entry| entry.get_value_mut(index.generation))
+    }
+
+    /// Get mutable references of two values inside this arena at once by
+    /// [`Index`], returning `None` if the corresponding `index` is not
+    /// contained in this arena.
+    ///
+    /// # Panics
+    ///
+    /// This function panics when the two indices are equal (having the same
+    /// slot number and generation).
+    pub fn get2_mut(&mut self, index1: Index, index2: Index) -> (Option<&mut T>, Option<&mut T>) {
+        if index1 == index2 {
+            panic!("CowArena::get2_mut is called with two identical indices");
+        }
+
+        // Same entry with a different generation. We'll prefer the first value
+        // that matches.
+        if index1.slot == index2.slot {
+            if self.contains(index1) {
+                return (self.get_mut(index1), None);
+            } else {
+                return (None, self.get_mut(index2));
+            }
+        }
+
+        let (found1, found2) = (self.contains(index1), self.contains(index2));
+        let (chunk1, offset1) = locate(index1.slot);
+        let (chunk2, offset2) = locate(index2.slot);
+
+        let chunks = Arc::make_mut(&mut self.chunks);
+
+        if chunk1 == chunk2 {
+            let chunk = Arc::make_mut(&mut chunks[chunk1]);
+
+            if offset1 < offset2 {
+                let (left, right) = chunk.split_at_mut(offset2);
+                (
+                    occupied_value(&mut left[offset1], found1),
+                    occupied_value(&mut right[0], found2),
+                )
+            } else {
+                let (left, right) = chunk.split_at_mut(offset1);
+                (
+                    occupied_value(&mut right[0], found1),
+                    occupied_value(&mut left[offset2], found2),
+                )
+            }
+        } else {
+            let (first, second) = if chunk1 < chunk2 {
+                let (left, right) = chunks.split_at_mut(chunk2);
+                (&mut left[chunk1], &mut right[0])
+            } else {
+                let (left, right) = chunks.split_at_mut(chunk1);
+                (&mut right[0], &mut left[chunk2])
+            };
+
+            (
+                Arc::make_mut(first)
+                    .get_mut(offset1)
+                    .and_then(|s| occupied_value(s, found1)),
+                Arc::make_mut(second)
+                    .get_mut(offset2)
+                    .and_then(|s| occupied_value(s, found2)),
+            )
+        }
+    }
+
+    /// Remove the value contained at the given index from the arena, returning
+    /// it if it was present.
+    pub fn remove(&mut self, index: Index) -> Option<T> {
+        if !self.contains(index) {
+            return None;
+        }
+
+        self.vacate(index.slot).map(|(_, value)| value)
+    }
+
+    /// Invalidate the given index and return a new index to the same value. This
+    /// is roughly equivalent to `remove` followed by `insert`, but much faster.
+    /// If the old index is already invalid, this method returns `None`.
+    pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        self.invalidate_slot(index)
+    }
+
+    /// Swap the values at two indices. See [`Arena::swap`].
+    pub fn swap(&mut self, a: Index, b: Index) -> bool {
+        if a == b {
+            return self.contains(a);
+        }
+
+        match self.get2_mut(a, b) {
+            (Some(a), Some(b)) => {
+                swap(a, b);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Move the value at the given index into the given empty slot, returning
+    /// its new index. See [`Arena::relocate`].
+    pub fn relocate(&mut self, index: Index, to_slot: u32) -> Option<Index> {
+        if !self.contains(index) || self.contains_slot(to_slot).is_some() {
+            return None;
+        }
+
+        let (_, value) = self.vacate(index.slot)?;
+        let (new_index, _) = self.insert_at_inner(to_slot, None, value);
+
+        Some(new_index)
+    }
+
+    /// Attempt to look up the given slot in the arena, disregarding any generational
+    /// information, and retrieve a mutable reference to it. Returns `None` if the
+    /// slot is empty.
+    pub fn get_by_slot_mut(&mut self, slot: u32) -> Option<(Index, &mut T)> {
+        let index = self.contains_slot(slot)?;
+        self.get_mut(index).map(|value| (index, value))
+    }
+
+    /// Remove an entry in the arena by its slot, disregarding any generational info.
+    /// Returns `None` if the slot was already empty.
+    pub fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
+        self.vacate(slot)
+    }
+
+    /// Clear the arena and drop all elements.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena, with
+    /// mutable access to each value. Chunks shared with a snapshot are copied
+    /// as the iterator reaches them.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            chunks: Arc::make_mut(&mut self.chunks).iter_mut().enumerate(),
+            front: None,
+            back: None,
+            len: self.len,
+        }
+    }
+
+    /// Iterate over all of the values contained in the arena, with mutable
+    /// access to each value.
+    ///
+    /// Iteration order is not defined.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, T> {
+        ValuesMut {
+            inner: self.iter_mut(),
+        }
+    }
+
+    /// Returns an iterator that removes each element from the arena. See
+    /// [`Arena::drain`].
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain {
+            arena: self,
+            slot: 0,
+            value: PhantomData,
+        }
+    }
+
+    /// Remove all entries in the arena for which the given function returns
+    /// false.
+    pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
+        for slot in 0..self.slot_count {
+            let keep = match self.slot(slot) {
+                Some(Slot::Occupied(_)) => match self.slot_mut(slot) {
+                    Some(Slot::Occupied(occupied)) => {
+                        let index = Index {
+                            slot,
+                            generation: occupied.generation,
+                        };
+
+                        f(index, &mut occupied.value)
+                    }
+                    _ => unreachable!(),
+                },
+                _ => true,
+            };
+
+            if !keep {
+                self.vacate(slot);
+            }
+        }
+    }
+
+    /// Gets the given key's corresponding entry in the arena for in-place
+    /// manipulation.
+    ///
+    /// The entry is occupied if `index` is currently contained in the arena,
+    /// and vacant otherwise.
+    pub fn entry(&mut self, index: Index) -> Entry<'_, T> {
+        Entry::new(self, index)
+    }
+
+    /// Gets the entry for the given slot in the arena for in-place
+    /// manipulation, whatever generation it's at. See [`Arena::entry_by_slot`].
+    pub fn entry_by_slot(&mut self, slot: u32) -> Entry<'_, T> {
+        let index = self
+            .contains_slot(slot)
+            .unwrap_or_else(|| self.next_index_at(slot));
+
+        Entry::new(self, index)
+    }
+
+    /// Gets a vacant entry for the index the next call to
+    /// [`CowArena::insert`] would produce.
+    pub fn vacant_entry(&mut self) -> VacantEntry<'_, T> {
+        let index = self.next_index();
+        VacantEntry::new(self, index)
+    }
+
+    /// Consume the arena, returning an iterator over its values. Chunks shared
+    /// with snapshots are cloned.
+    pub fn into_values(self) -> IntoValues<T> {
+        Arena::from(self).into_values()
+    }
+
+    /// Consume the arena, converting every value with the given function into
+    /// an arena of the new values. See [`Arena::map`].
+    pub fn map<U, F: FnMut(Index, T) -> U>(self, f: F) -> CowArena<U> {
+        CowArena::from(Arena::from(self).map(f))
+    }
+
+    /// Consume the arena, converting every value with the given fallible
+    /// function into an arena of the new values. See [`Arena::try_map`].
+    pub fn try_map<U, E, F>(self, f: F) -> Result<CowArena<U>, E>
+    where
+        F: FnMut(Index, T) -> Result<U, E>,
+    {
+        Arena::from(self).try_map(f).map(CowArena::from)
+    }
+
+    /// Copy the arena's values into an [`Arena`] with the same indices, free
+    /// list, and quarantine.
+    pub fn to_arena(&self) -> Arena<T> {
+        Arena::from(self.clone())
+    }
+}
+
+impl<T> SlotStorage for CowArena<T> {
+    type Value = T;
+
+    fn slot_count(&self) -> u32 {
+        self.slot_count
+    }
+
+    fn slot(&self, slot: u32) -> Option<&Slot<T>> {
+        let (chunk, offset) = locate(slot);
+        self.chunks.get(chunk)?.get(offset)
+    }
+
+    fn policy(&self) -> ReusePolicy {
+        self.policy
+    }
+
+    fn first_free(&self) -> Option<FreePointer> {
+        self.first_free
+    }
+
+    fn last_free(&self) -> Option<FreePointer> {
+        self.last_free
+    }
+
+    fn quarantine_state(&self) -> &Quarantine {
+        &self.quarantine
+    }
+}
+
+impl<T: Clone> SlotStorageMut for CowArena<T> {
+    fn slot_mut(&mut self, slot: u32) -> Option<&mut Slot<T>> {
+        if slot >= self.slot_count {
+            return None;
+        }
+
+        let (chunk, offset) = locate(slot);
+        let chunks = Arc::make_mut(&mut self.chunks);
+        Arc::make_mut(chunks.get_mut(chunk)?).get_mut(offset)
+    }
+
+    fn push_slot(&mut self, entry: Slot<T>) {
+        let chunks = Arc::make_mut(&mut self.chunks);
+
+        match chunks.last_mut() {
+            Some(chunk) if chunk.len() < CHUNK_LEN => Arc::make_mut(chunk).push(entry),
+            _ => {
+                let mut chunk = Vec::with_capacity(CHUNK_LEN);
+                chunk.push(entry);
+                chunks.push(Arc::new(chunk));
+            }
+        }
+
+        self.slot_count = self
+            .slot_count
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Cannot create more than u32::MAX slots in CowArena"));
+    }
+
+    fn len_mut(&mut self) -> &mut u32 {
+        &mut self.len
+    }
+
+    fn first_free_mut(&mut self) -> &mut Option<FreePointer> {
+        &mut self.first_free
+    }
+
+    fn last_free_mut(&mut self) -> &mut Option<FreePointer> {
+        &mut self.last_free
+    }
+
+    fn quarantine_state_mut(&mut self) -> &mut Quarantine {
+        Arc::make_mut(&mut self.quarantine)
+    }
+}
+
+impl<T: Clone> ArenaLike<T> for CowArena<T> {
+    fn len(&self) -> usize {
+        CowArena::len(self)
+    }
+
+    fn contains(&self, index: Index) -> bool {
+        CowArena::contains(self, index)
+    }
+
+    fn get(&self, index: Index) -> Option<&T> {
+        CowArena::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        CowArena::get_mut(self, index)
+    }
+
+    fn insert_at(&mut self, index: Index, value: T) -> Option<T> {
+        CowArena::insert_at(self, index, value)
+    }
+
+    fn insert_at_slot(&mut self, slot: u32, value: T) -> (Index, Option<T>) {
+        CowArena::insert_at_slot(self, slot, value)
+    }
+
+    fn remove(&mut self, index: Index) -> Option<T> {
+        CowArena::remove(self, index)
+    }
+
+    fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
+        CowArena::remove_by_slot(self, slot)
+    }
+
+    fn invalidate(&mut self, index: Index) -> Option<Index> {
+        CowArena::invalidate(self, index)
+    }
+}
+
+impl<T> Clone for CowArena<T> {
+    fn clone(&self) -> Self {
+        CowArena {
+            chunks: Arc::clone(&self.chunks),
+            slot_count: self.slot_count,
+            len: self.len,
+            first_free: self.first_free,
+            last_free: self.last_free,
+            policy: self.policy,
+            quarantine: Arc::clone(&self.quarantine),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CowArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Default for CowArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Arena<T>> for CowArena<T> {
+    /// Move the values of an arena into a `CowArena` with the same indices,
+    /// free list, and quarantine.
+    fn from(arena: Arena<T>) -> Self {
+        let slot_count = arena.slot_count();
+
+        let mut chunks = Vec::with_capacity(chunks_for(arena.storage.len()));
+        let mut storage = arena.storage.into_iter().peekable();
+        while storage.peek().is_some() {
+            let mut chunk = Vec::with_capacity(CHUNK_LEN);
+            chunk.extend(storage.by_ref().take(CHUNK_LEN));
+            chunks.push(Arc::new(chunk));
+        }
+
+        CowArena {
+            chunks: Arc::new(chunks),
+            slot_count,
+            len: arena.len,
+            first_free: arena.first_free,
+            last_free: arena.last_free,
+            policy: arena.policy,
+            quarantine: Arc::new(arena.quarantine),
+        }
+    }
+}
+
+impl<T: Clone> From<CowArena<T>> for Arena<T> {
+    /// Move the values of a `CowArena` into an [`Arena`] with the same indices,
+    /// free list, and quarantine. Chunks shared with snapshots are cloned.
+    fn from(arena: CowArena<T>) -> Self {
+        let chunks = Arc::try_unwrap(arena.chunks).unwrap_or_else(|chunks| (*chunks).clone());
+
+        let mut storage = Vec::with_capacity(arena.slot_count as usize);
+        for chunk in chunks {
+            match Arc::try_unwrap(chunk) {
+                Ok(chunk) => storage.extend(chunk),
+                Err(chunk) => storage.extend(chunk.iter().cloned()),
+            }
+        }
+
+        Arena {
+            storage,
+            len: arena.len,
+            first_free: arena.first_free,
+            last_free: arena.last_free,
+            quarantine: Arc::try_unwrap(arena.quarantine)
+                .unwrap_or_else(|quarantine| (*quarantine).clone()),
+            ..Arena::with_reuse_policy(arena.policy)
+        }
+    }
+}
+
+impl<T: Clone> IntoIterator for CowArena<T> {
+    type Item = (Index, T);
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Arena::from(self).into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CowArena<T> {
+    type Item = (Index, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a mut CowArena<T> {
+    type Item = (Index, &'a mut T);
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> ops::Index<Index> for CowArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        self.get(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+impl<T: Clone> ops::IndexMut<Index> for CowArena<T> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+/// See [`CowArena::iter`].
+pub struct Iter<'a, T> {
+    chunks: &'a [Chunk<T>],
+    front: u32,
+    back: u32,
+    len: u32,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn occupied(&mut self, slot: u32) -> Option<(Index, &'a T)> {
+        let (chunk, offset) = locate(slot);
+
+        match &self.chunks[chunk][offset] {
+            Slot::Occupied(occupied) => {
+                self.len = self
+                    .len
+                    .checked_sub(1)
+                    .unwrap_or_else(|| unreachable!("Underflowed u32 trying to iterate Arena"));
+
+                let index = Index {
+                    slot,
+                    generation: occupied.generation,
+                };
+
+                Some((index, &occupied.value))
+            }
+            Slot::Empty(_) => None,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (Index, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.len > 0 && self.front < self.back {
+            let slot = self.front;
+            self.front = slot.checked_add(1).unwrap_or_else(|| unreachable!());
+
+            if let Some(item) = self.occupied(slot) {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len as usize, Some(self.len as usize))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.len > 0 && self.front < self.back {
+            self.back = self.back.checked_sub(1).unwrap_or_else(|| unreachable!());
+
+            if let Some(item) = self.occupied(self.back) {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Iter { ..*self }
+    }
+}
+
+impl<T> fmt::Debug for Iter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iter").field("len", &self.len).finish()
+    }
+}
+
+/// See [`CowArena::iter_mut`].
+pub struct IterMut<'a, T> {
+    chunks: Enumerate<slice::IterMut<'a, Chunk<T>>>,
+    front: Option<(usize, Enumerate<slice::IterMut<'a, Slot<T>>>)>,
+    back: Option<(usize, Enumerate<slice::IterMut<'a, Slot<T>>>)>,
+    len: u32,
+}
+
+impl<'a, T: Clone> IterMut<'a, T> {
+    fn open(
+        (chunk, slots): (usize, &'a mut Chunk<T>),
+    ) -> (usize, Enumerate<slice::IterMut<'a, Slot<T>>>) {
+        (chunk, Arc::make_mut(slots).iter_mut().enumerate())
+    }
+
+    fn occupied(
+        len: &mut u32,
+        chunk: usize,
+        (offset, entry): (usize, &'a mut Slot<T>),
+    ) -> Option<(Index, &'a mut T)> {
+        match entry {
+            Slot::Occupied(occupied) => {
+                *len = len
+                    .checked_sub(1)
+                    .unwrap_or_else(|| unreachable!("Underflowed u32 trying to iterate Arena"));
+
+                let index = Index {
+                    slot: slot_at(chunk, offset),
+                    generation: occupied.generation,
+                };
+
+                Some((index, &mut occupied.value))
+            }
+            Slot::Empty(_) => None,
+        }
+    }
+}
+
+impl<'a, T: Clone> Iterator for IterMut<'a, T> {
+    type Item = (Index, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.len > 0 {
+            if let Some((chunk, slots)) = &mut self.front {
+                match slots.next() {
+                    Some(entry) => match Self::occupied(&mut self.len, *chunk, entry) {
+                        Some(item) => return Some(item),
+                        None => continue,
+                    },
+                    None => self.front = None,
+                }
+            }
+
+            match self.chunks.next() {
+                Some(next) => self.front = Some(Self::open(next)),
+                None => {
+                    let (chunk, slots) = self.back.as_mut()?;
+                    let entry = slots.next()?;
+                    if let Some(item) = Self::occupied(&mut self.len, *chunk, entry) {
+                        return Some(item);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len as usize, Some(self.len as usize))
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.len > 0 {
+            if let Some((chunk, slots)) = &mut self.back {
+                match slots.next_back() {
+                    Some(entry) => match Self::occupied(&mut self.len, *chunk, entry) {
+                        Some(item) => return Some(item),
+                        None => continue,
+                    },
+                    None => self.back = None,
+                }
+            }
+
+            match self.chunks.next_back() {
+                Some(next) => self.back = Some(Self::open(next)),
+                None => {
+                    let (chunk, slots) = self.front.as_mut()?;
+                    let entry = slots.next_back()?;
+                    if let Some(item) = Self::occupied(&mut self.len, *chunk, entry) {
+                        return Some(item);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for IterMut<'_, T> {}
+impl<T: Clone> FusedIterator for IterMut<'_, T> {}
+
+impl<T> fmt::Debug for IterMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IterMut").field("len", &self.len).finish()
+    }
+}
+
+/// See [`CowArena::values`].
+#[derive(Clone, Debug)]
+pub struct Values<'a, T> {
+    inner: Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Values<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Values<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<T> ExactSizeIterator for Values<'_, T> {}
+impl<T> FusedIterator for Values<'_, T> {}
+
+/// See [`CowArena::keys`].
+#[derive(Clone, Debug)]
+pub struct Keys<'a, T> {
+    inner: Iter<'a, T>,
+}
+
+impl<T> Iterator for Keys<'_, T> {
+    type Item = Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(index, _)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Keys<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(index, _)| index)
+    }
+}
+
+impl<T> ExactSizeIterator for Keys<'_, T> {}
+impl<T> FusedIterator for Keys<'_, T> {}
+
+/// See [`CowArena::values_mut`].
+#[derive(Debug)]
+pub struct ValuesMut<'a, T> {
+    inner: IterMut<'a, T>,
+}
+
+impl<'a, T: Clone> Iterator for ValuesMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for ValuesMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, value)| value)
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for ValuesMut<'_, T> {}
+impl<T: Clone> FusedIterator for ValuesMut<'_, T> {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{CowArena, Entry, CHUNK_LEN};
+    use crate::test_util::POLICIES;
+    use crate::{Arena, Index};
+
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    /// Count the chunks two arenas share.
+    fn shared_chunks<T>(a: &CowArena<T>, b: &CowArena<T>) -> usize {
+        a.chunks
+            .iter()
+            .zip(b.chunks.iter())
+            .filter(|(a, b)| std::sync::Arc::ptr_eq(a, b))
+            .count()
+    }
+
+    #[test]
+    fn snapshots_share_storage() {
+        let mut arena = CowArena::new();
+        let indices: Vec<_> = (0..(CHUNK_LEN * 4) as u32)
+            .map(|i| arena.insert(i))
+            .collect();
+
+        let snapshot = arena.snapshot();
+        assert!(arena.ptr_eq(&snapshot));
+
+        arena[indices[CHUNK_LEN + 1]] = 1000;
+        assert!(!arena.ptr_eq(&snapshot));
+        assert_eq!(shared_chunks(&arena, &snapshot), 3);
+
+        arena.remove(indices[0]);
+        assert_eq!(shared_chunks(&arena, &snapshot), 2);
+
+        assert_eq!(snapshot[indices[CHUNK_LEN + 1]], (CHUNK_LEN + 1) as u32);
+        assert_eq!(snapshot[indices[0]], 0);
+        assert_eq!(arena.get(indices[0]), None);
+    }
+
+    /// Apply the same changes to an `Arena` and a `CowArena`, taking
+    /// snapshots along the way, and make sure they always agree.
+    #[test]
+    fn matches_arena() {
+        for &policy in &POLICIES {
+            let mut arena = Arena::with_reuse_policy(policy);
+            let mut cow = CowArena::with_reuse_policy(policy);
+            arena.set_quarantine(2);
+            cow.set_quarantine(2);
+
+            let mut snapshots = Vec::new();
+            let mut live: Vec<Index> = Vec::new();
+
+            for step in 0..500u32 {
+                match step % 9 {
+                    0..=3 => {
+                        let index = arena.insert(step);
+                        assert_eq!(cow.insert(step), index);
+                        live.push(index);
+                    }
+                    4 | 5 => {
+                        let index = live.swap_remove((step as usize * 7) % live.len());
+                        assert_eq!(cow.remove(index), arena.remove(index));
+                    }
+                    6 => {
+                        let index = live[0];
+                        live[0] = arena.invalidate(index).unwrap();
+                        assert_eq!(cow.invalidate(index), Some(live[0]));
+                    }
+                    7 => {
+                        let slot = (step * 13) % 300;
+                        assert_eq!(
+                            cow.insert_at_slot(slot, step),
+                            arena.insert_at_slot(slot, step)
+                        );
+                        live.retain(|index| arena.contains(*index));
+                        if let Some(index) = arena.contains_slot(slot) {
+                            live.push(index);
+                        }
+                    }
+                    _ => {
+                        arena.advance_epoch();
+                        cow.advance_epoch();
+                        snapshots.push((arena.clone(), cow.snapshot()));
+                    }
+                }
+
+                assert_eq!(cow.next_index(), arena.next_index());
+                assert_eq!(cow.quarantined_len(), arena.quarantined_len());
+            }
+
+            for (arena, cow) in snapshots {
+                assert_eq!(cow.len(), arena.len());
+                assert!(cow.iter().eq(arena.iter()));
+                assert!(cow.iter().rev().eq(arena.iter().rev()));
+                assert!(cow.keys().eq(arena.keys()));
+                assert!(cow.range(40..200).eq(arena.range(40..200)));
+
+                let mut cow_hash = DefaultHasher::new();
+                let mut arena_hash = DefaultHasher::new();
+                cow.state_hash(&mut cow_hash);
+                arena.state_hash(&mut arena_hash);
+                assert_eq!(cow_hash.finish(), arena_hash.finish());
+
+                assert!(cow.to_arena().same_state(&arena));
+                assert!(CowArena::from(arena.clone()).to_arena().same_state(&arena));
+            }
+        }
+    }
+
+    #[test]
+    fn quarantine() {
+        let mut arena = CowArena::new();
+        arena.set_quarantine(1);
+        let a = arena.insert(1);
+        let snapshot = arena.snapshot();
+
+        arena.remove(a);
+        assert_eq!(arena.quarantined_len(), 1);
+        assert_eq!(snapshot.quarantined_len(), 0);
+        assert_ne!(arena.next_index().slot(), a.slot());
+
+        arena.advance_epoch();
+        assert_eq!(arena.quarantined_len(), 0);
+        assert_eq!(arena.insert(2).slot(), a.slot());
+    }
+
+    #[test]
+    fn keys_range_and_entries() {
+        let mut arena = CowArena::new();
+        let indices: Vec<_> = (0..(CHUNK_LEN * 2) as u32)
+            .map(|i| arena.insert(i))
+            .collect();
+        let snapshot = arena.snapshot();
+
+        assert!(arena.keys().eq(indices.iter().copied()));
+
+        let slots = CHUNK_LEN as u32 - 2..CHUNK_LEN as u32 + 2;
+        let in_range: Vec<_> = arena
+            .range(slots.clone())
+            .map(|(_, &value)| value)
+            .collect();
+        assert_eq!(in_range, slots.clone().collect::<Vec<_>>());
+        assert_eq!(arena.range(1000..).count(), 0);
+
+        let a = indices[CHUNK_LEN];
+        let b = match arena.entry_by_slot(a.slot()) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), a);
+                let b = entry.invalidate();
+                assert_eq!(entry.replace_entry(100), (b, CHUNK_LEN as u32));
+                entry.key()
+            }
+            Entry::Vacant(_) => unreachable!(),
+        };
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena[b], 100);
+        assert_eq!(snapshot[a], CHUNK_LEN as u32);
+
+        let past_end = (CHUNK_LEN * 3) as u32;
+        let entry = arena.entry_by_slot(past_end).insert_entry(7);
+        assert_eq!(entry.key().slot(), past_end);
+        assert_eq!(*entry.get(), 7);
+    }
+
+    #[test]
+    fn drain_map_and_capacity() {
+        let mut arena = CowArena::with_capacity(CHUNK_LEN * 2);
+        assert!(arena.capacity() >= CHUNK_LEN * 2);
+
+        let indices: Vec<_> = (0..10u32).map(|i| arena.insert(i)).collect();
+        arena.reserve(CHUNK_LEN * 4);
+        assert!(arena.capacity() >= CHUNK_LEN * 4 + 10);
+
+        let doubled = arena.map_ref(|_, value| value * 2);
+        assert_eq!(doubled[indices[3]], 6);
+
+        let strings = arena.clone().map(|index, value| (index, value.to_string()));
+        assert_eq!(strings[indices[4]], (indices[4], "4".to_string()));
+
+        let failed = arena.clone().try_map(|_, value| match value {
+            5 => Err(value),
+            _ => Ok(value),
+        });
+        assert_eq!(failed.unwrap_err(), 5);
+
+        let values: Vec<_> = arena.clone().into_values().collect();
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+
+        let snapshot = arena.snapshot();
+        let drained: Vec<_> = arena.drain().collect();
+        assert_eq!(drained.len(), 10);
+        assert!(arena.is_empty());
+        assert_eq!(snapshot.len(), 10);
+        assert_ne!(arena.insert(0), indices[9]);
+    }
+
+    #[test]
+    fn iter_mut_copies_chunks() {
+        let mut arena = CowArena::new();
+        for i in 0..(CHUNK_LEN * 2 + 3) as u32 {
+            arena.insert(i);
+        }
+        let snapshot = arena.snapshot();
+
+        for value in arena.values_mut() {
+            *value = value.wrapping_mul(2);
+        }
+
+        assert!(arena
+            .iter()
+            .zip(snapshot.iter())
+            .all(|((_, a), (_, b))| *a == b.wrapping_mul(2)));
+
+        let mut iter = arena.iter_mut();
+        let first = iter.next().unwrap().0;
+        let last = iter.next_back().unwrap().0;
+        assert_eq!(first.slot(), 0);
+        assert_eq!(last.slot(), (CHUNK_LEN * 2 + 2) as u32);
+        assert_eq!(iter.count(), CHUNK_LEN * 2 + 1);
+    }
+
+    #[test]
+    fn entries_and_get2_mut() {
+        let mut arena = CowArena::new();
+        let a = arena.insert(1);
+        let b = arena.insert_at_slot(CHUNK_LEN as u32 + 5, 2).0;
+        let snapshot = arena.snapshot();
+
+        match arena.entry(a) {
+            Entry::Occupied(mut entry) => *entry.get_mut() = 10,
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        let vacant = arena.vacant_entry();
+        let c = vacant.key();
+        vacant.insert(3);
+        assert_eq!(arena[c], 3);
+
+        let (x, y) = arena.get2_mut(b, a);
+        *x.unwrap() += 1;
+        *y.unwrap() += 1;
+
+        assert_eq!(arena[a], 11);
+        assert_eq!(arena[b], 3);
+        assert_eq!(snapshot[a], 1);
+        assert_eq!(snapshot[b], 2);
+        assert_eq!(snapshot.get(c), None);
+
+        *arena.entry(c).or_insert(0) += 1;
+        assert_eq!(arena[c], 4);
+    }
+}
@@ -0,0 +1,240 @@
+use core::mem::replace;
+
+use crate::arena::{Arena, Index, Slot};
+
+impl<T> Arena<T> {
+    /// Create a cursor that walks the arena's values in slot order, and can
+    /// remove and replace them along the way. The first call to
+    /// [`CursorMut::move_next`] moves to the first value.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        self.cursor_mut_at(0)
+    }
+
+    /// Create a cursor whose first call to [`CursorMut::move_next`] moves to
+    /// the first value at or after the given slot. This resumes a walk that was
+    /// stopped at [`CursorMut::position`].
+    pub fn cursor_mut_at(&mut self, slot: u32) -> CursorMut<'_, T> {
+        CursorMut {
+            arena: self,
+            current: None,
+            next: slot,
+        }
+    }
+}
+
+/// A cursor over the values of an [`Arena`] in slot order, which can remove,
+/// replace, and invalidate the value it points to.
+///
+/// Created with [`Arena::cursor_mut`] or [`Arena::cursor_mut_at`]. The cursor
+/// starts out before its first value; [`CursorMut::move_next`] moves it to
+/// the next value. [`CursorMut::position`] can be saved to continue walking
+/// later, such as on the next frame.
+///
+/// ```rust
+/// # use thunderdome::Arena;
+/// let mut arena = Arena::new();
+/// for i in 0..10 {
+///     arena.insert(i);
+/// }
+///
+/// // Process up to 4 values per frame.
+/// let mut position = 0;
+/// for _frame in 0..3 {
+///     let mut cursor = arena.cursor_mut_at(position);
+///     for _ in 0..4 {
+///         match cursor.move_next() {
+///             Some((_, value)) if *value % 3 == 0 => {
+///                 cursor.remove_current();
+///             }
+///             Some((_, value)) => *value *= 10,
+///             None => break,
+///         }
+///     }
+///     position = cursor.position();
+/// }
+///
+/// let values: Vec<_> = arena.values().copied().collect();
+/// assert_eq!(values, [10, 20, 40, 50, 70, 80]);
+/// ```
+#[derive(Debug)]
+pub struct CursorMut<'a, T> {
+    arena: &'a mut Arena<T>,
+
+    /// The slot the cursor points to, if it points to one.
+    current: Option<u32>,
+
+    /// The slot `move_next` starts looking at.
+    next: u32,
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    /// Move to the next value in slot order and return it, or return `None`
+    /// and point to nothing if there are no more values.
+    pub fn move_next(&mut self) -> Option<(Index, &mut T)> {
+        match self.find_next() {
+            Some(slot) => {
+                self.current = Some(slot);
+                self.next = slot
+                    .checked_add(1)
+                    .unwrap_or_else(|| panic!("Overflowed u32 trying to move cursor"));
+                self.current()
+            }
+            None => {
+                self.current = None;
+                self.next = self.next.max(self.arena.storage.len() as u32);
+                None
+            }
+        }
+    }
+
+    /// The value the cursor points to, or `None` if it points to nothing or
+    /// the value was removed.
+    pub fn current(&mut self) -> Option<(Index, &mut T)> {
+        let slot = self.current?;
+        self.arena.get_by_slot_mut(slot)
+    }
+
+    /// The index of the value the cursor points to.
+    pub fn index(&self) -> Option<Index> {
+        self.arena.contains_slot(self.current?)
+    }
+
+    /// The value that the next call to [`CursorMut::move_next`] would move to,
+    /// without moving.
+    pub fn peek_next(&self) -> Option<(Index, &T)> {
+        self.arena.get_by_slot(self.find_next()?)
+    }
+
+    /// Remove the value the cursor points to and return it. The cursor stays
+    /// in place, so the next call to [`CursorMut::move_next`] moves to the
+    /// value after the removed one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        self.arena
+            .remove_by_slot(self.current?)
+            .map(|(_, value)| value)
+    }
+
+    /// Replace the value the cursor points to, keeping its index, and return
+    /// the old value. Returns `None` without using `value` if the cursor
+    /// points to nothing.
+    pub fn replace_current(&mut self, value: T) -> Option<T> {
+        let (_, current) = self.current()?;
+        Some(replace(current, value))
+    }
+
+    /// Give the value the cursor points to a new index and return it. See
+    /// [`Arena::invalidate`].
+    pub fn invalidate_current(&mut self) -> Option<Index> {
+        let index = self.index()?;
+        self.arena.invalidate(index)
+    }
+
+    /// Insert a value into the arena, like [`Arena::insert`]. Depending on the
+    /// slot it's given, the cursor may or may not move to it later.
+    pub fn insert(&mut self, value: T) -> Index {
+        self.arena.insert(value)
+    }
+
+    /// Stop pointing to a value, so that the next call to
+    /// [`CursorMut::move_next`] moves to the first value at or after the given
+    /// slot.
+    pub fn seek(&mut self, slot: u32) {
+        self.current = None;
+        self.next = slot;
+    }
+
+    /// The slot the next call to [`CursorMut::move_next`] starts looking at.
+    /// Passing it to [`Arena::cursor_mut_at`] continues walking from the same
+    /// place.
+    pub fn position(&self) -> u32 {
+        self.next
+    }
+
+    /// Read the arena the cursor walks.
+    pub fn arena(&self) -> &Arena<T> {
+        self.arena
+    }
+
+    fn find_next(&self) -> Option<u32> {
+        let start = self.next as usize;
+
+        self.arena
+            .storage
+            .get(start..)?
+            .iter()
+            .position(|entry| matches!(entry, Slot::Occupied(_)))
+            .map(|offset| start.saturating_add(offset) as u32)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn walk_and_edit() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+
+        let mut cursor = arena.cursor_mut();
+        assert!(cursor.current().is_none());
+        assert_eq!(cursor.peek_next().map(|(index, _)| index), Some(indices[0]));
+
+        assert_eq!(cursor.move_next(), Some((indices[0], &mut 0)));
+        assert_eq!(cursor.replace_current(10), Some(0));
+        assert_eq!(cursor.move_next().map(|(index, _)| index), Some(indices[1]));
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+
+        // The removed slot 2 is skipped.
+        assert_eq!(cursor.move_next().map(|(index, _)| index), Some(indices[3]));
+        let invalidated = cursor.invalidate_current().unwrap();
+        assert_eq!(cursor.index(), Some(invalidated));
+        assert_eq!(cursor.position(), 4);
+
+        cursor.seek(5);
+        assert_eq!(cursor.move_next().map(|(index, _)| index), Some(indices[5]));
+        assert_eq!(cursor.move_next(), None);
+        assert_eq!(cursor.position(), 6);
+
+        // Values inserted into free slots behind the cursor aren't visited,
+        // but values inserted past the end are.
+        assert_eq!(cursor.insert(7).slot(), 1);
+        assert_eq!(cursor.insert(8).slot(), 2);
+        assert_eq!(cursor.insert(9).slot(), 6);
+        assert_eq!(cursor.move_next().map(|(_, value)| *value), Some(9));
+        assert_eq!(cursor.move_next(), None);
+
+        assert_eq!(arena.get(indices[3]), None);
+        assert_eq!(arena[invalidated], 3);
+        assert_eq!(arena[indices[0]], 10);
+    }
+
+    #[test]
+    fn resume_from_position() {
+        let mut arena = Arena::new();
+        for i in 0..100u32 {
+            arena.insert(i);
+        }
+
+        let mut visited = Vec::new();
+        let mut position = 0;
+        loop {
+            let mut cursor = arena.cursor_mut_at(position);
+            for _ in 0..7 {
+                if let Some((_, value)) = cursor.move_next() {
+                    visited.push(*value);
+                }
+            }
+
+            position = cursor.position();
+            if cursor.peek_next().is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(visited, (0..100).collect::<Vec<_>>());
+    }
+}
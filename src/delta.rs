@@ -0,0 +1,619 @@
+use core::convert::TryInto;
+use core::fmt;
+
+// Vec is part of the prelude when std is enabled.
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::arena::{Arena, EmptySlot, Index, OccupiedSlot, Slot};
+use crate::command::Apply;
+use crate::generation::Generation;
+use crate::policy::ReusePolicy;
+use crate::storage::{SlotStorage, SlotStorageMut};
+
+/// The changes that turn one state of an [`Arena`] into another, created by
+/// [`Arena::diff`] and replayed with [`Arena::apply`].
+///
+/// A delta describes the changed slots and, when the free list or quarantine
+/// changed, the new free list order and quarantined slots. Applying it
+/// reproduces the exact same indices, free list, and quarantine on the
+/// receiving arena, so indices can be sent over the network alongside deltas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArenaDelta<T> {
+    pub(crate) slot_count: u32,
+    pub(crate) changes: Vec<SlotChange<T>>,
+    pub(crate) free_list: Option<Vec<u32>>,
+
+    /// Sent along with the free list, and `None` exactly when it is.
+    pub(crate) quarantined: Option<Vec<(u64, u32)>>,
+}
+
+/// A change to a single slot, as part of an [`ArenaDelta`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotChange<T> {
+    /// A value was inserted, replacing whatever was in the slot before.
+    Inserted {
+        /// The index of the new value.
+        index: Index,
+        /// The new value.
+        value: T,
+    },
+
+    /// The value at an index was changed in place.
+    Changed {
+        /// The index of the value, which did not change.
+        index: Index,
+        /// The new value.
+        value: T,
+    },
+
+    /// A value was given a new generation with [`Arena::invalidate`], keeping
+    /// its value.
+    Invalidated {
+        /// The new index of the value.
+        index: Index,
+    },
+
+    /// The slot became empty, or stayed empty with a different generation.
+    Removed {
+        /// The index of the value that was last stored in the slot.
+        index: Index,
+    },
+}
+
+impl<T> SlotChange<T> {
+    /// The index this change describes.
+    pub fn index(&self) -> Index {
+        match self {
+            SlotChange::Inserted { index, .. }
+            | SlotChange::Changed { index, .. }
+            | SlotChange::Invalidated { index }
+            | SlotChange::Removed { index } => *index,
+        }
+    }
+
+    /// Returns true if the slot is empty after this change.
+    fn leaves_empty(&self) -> bool {
+        matches!(self, SlotChange::Removed { .. })
+    }
+}
+
+impl<T> ArenaDelta<T> {
+    /// Returns true if applying this delta would not change anything.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.free_list.is_none()
+    }
+
+    /// The number of slots in the arena after the delta is applied.
+    pub fn slot_count(&self) -> u32 {
+        self.slot_count
+    }
+
+    /// The changed slots, ordered by slot.
+    pub fn changes(&self) -> &[SlotChange<T>] {
+        &self.changes
+    }
+
+    /// The new free list, in reuse order, or `None` if neither the free list
+    /// nor the quarantine changed.
+    pub fn free_list(&self) -> Option<&[u32]> {
+        self.free_list.as_deref()
+    }
+
+    /// The new quarantined slots, in release order, each paired with the
+    /// number of epochs until it is released. `None` if neither the free list
+    /// nor the quarantine changed.
+    pub fn quarantined(&self) -> Option<&[(u64, u32)]> {
+        self.quarantined.as_deref()
+    }
+
+    /// Find the change for the given slot, if there is one.
+    fn change(&self, slot: u32) -> Option<&SlotChange<T>> {
+        self.changes
+            .binary_search_by_key(&slot, |change| change.index().slot)
+            .ok()
+            .map(|position| &self.changes[position])
+    }
+}
+
+/// The error returned by [`Arena::apply`] when the arena is not in the state
+/// the delta was computed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaMismatch {
+    slot: u32,
+}
+
+impl DeltaMismatch {
+    /// The first slot found that doesn't match the delta.
+    pub const fn slot(&self) -> u32 {
+        self.slot
+    }
+}
+
+impl fmt::Display for DeltaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slot {} is not in the state the delta was computed from",
+            self.slot
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DeltaMismatch {}
+
+impl<T> Arena<T> {
+    /// Compute the changes that turn `previous` into this arena. `previous` is
+    /// usually an earlier clone of the same arena.
+    ///
+    /// Values are compared with `PartialEq`; only values that were inserted or
+    /// changed are cloned into the delta. Quarantined slots are sent with the
+    /// number of epochs left until their release, so the receiving arena
+    /// releases them after as many calls to [`Arena::advance_epoch`].
+    pub fn diff(&self, previous: &Arena<T>) -> ArenaDelta<T>
+    where
+        T: Clone + PartialEq,
+    {
+        let slot_count: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        });
+
+        let mut changes = Vec::new();
+
+        for (slot, entry) in (0..slot_count).zip(&self.storage) {
+            let change = match (previous.storage.get(slot as usize), entry) {
+                (Some(Slot::Occupied(old)), Slot::Occupied(new)) => {
+                    let index = Index {
+                        slot,
+                        generation: new.generation,
+                    };
+
+                    match (old.generation == new.generation, old.value == new.value) {
+                        (true, true) => None,
+                        (true, false) => Some(SlotChange::Changed {
+                            index,
+                            value: new.value.clone(),
+                        }),
+                        (false, true) => Some(SlotChange::Invalidated { index }),
+                        (false, false) => Some(SlotChange::Inserted {
+                            index,
+                            value: new.value.clone(),
+                        }),
+                    }
+                }
+                (_, Slot::Occupied(new)) => Some(SlotChange::Inserted {
+                    index: Index {
+                        slot,
+                        generation: new.generation,
+                    },
+                    value: new.value.clone(),
+                }),
+                (old, Slot::Empty(new)) => {
+                    // Slots past the end of `previous` start out empty with
+                    // their first generation.
+                    let unchanged = match old {
+                        Some(Slot::Empty(old)) => old.generation == new.generation,
+                        Some(Slot::Occupied(_)) => false,
+                        None => new.generation == Generation::first(),
+                    };
+
+                    if unchanged {
+                        None
+                    } else {
+                        Some(SlotChange::Removed {
+                            index: Index {
+                                slot,
+                                generation: new.generation,
+                            },
+                        })
+                    }
+                }
+            };
+
+            changes.extend(change);
+        }
+
+        // Quarantined slots aren't in the free list, but releasing one changes
+        // the indices given out next, so both are sent whenever either changed.
+        let (free_list, quarantined) = if self.storage.len() == previous.storage.len()
+            && self.free_list().eq(previous.free_list())
+            && self.quarantined().eq(previous.quarantined())
+        {
+            (None, None)
+        } else {
+            (
+                Some(self.free_list().collect()),
+                Some(self.quarantined().collect()),
+            )
+        };
+
+        ArenaDelta {
+            slot_count,
+            changes,
+            free_list,
+            quarantined,
+        }
+    }
+
+    fn apply_delta(&mut self, delta: ArenaDelta<T>) -> Result<(), DeltaMismatch> {
+        self.check_delta(&delta)?;
+        self.touch();
+
+        let slot_count = delta.slot_count as usize;
+
+        // Values past the new end of the storage are dropped, and new slots
+        // start out empty with their first generation.
+        for entry in self.storage.drain(slot_count.min(self.storage.len())..) {
+            if let Slot::Occupied(_) = entry {
+                self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+            }
+        }
+
+        while self.storage.len() < slot_count {
+            self.storage.push(Slot::Empty(EmptySlot {
+                generation: Generation::first(),
+                next_free: None,
+            }));
+        }
+
+        for change in delta.changes {
+            let entry = &mut self.storage[change.index().slot as usize];
+            let was_occupied = matches!(entry, Slot::Occupied(_));
+
+            match change {
+                SlotChange::Inserted { index, value } | SlotChange::Changed { index, value } => {
+                    *entry = Slot::Occupied(OccupiedSlot {
+                        generation: index.generation,
+                        value,
+                    });
+                }
+                SlotChange::Invalidated { index } => match entry {
+                    Slot::Occupied(occupied) => occupied.generation = index.generation,
+                    Slot::Empty(_) => unreachable!("delta was checked against the arena"),
+                },
+                SlotChange::Removed { index } => match entry {
+                    // Keep the free list link of slots that stay empty.
+                    Slot::Empty(empty) => empty.generation = index.generation,
+                    Slot::Occupied(_) => {
+                        *entry = Slot::Empty(EmptySlot {
+                            generation: index.generation,
+                            next_free: None,
+                        });
+                    }
+                },
+            }
+
+            match (was_occupied, matches!(entry, Slot::Occupied(_))) {
+                (false, true) => {
+                    self.len = self
+                        .len
+                        .checked_add(1)
+                        .unwrap_or_else(|| unreachable!("delta was checked against the arena"));
+                }
+                (true, false) => {
+                    self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+                }
+                _ => {}
+            }
+        }
+
+        if let (Some(free_list), Some(quarantined)) = (delta.free_list, delta.quarantined) {
+            self.set_free_list(&free_list, &quarantined);
+        }
+
+        Ok(())
+    }
+
+    /// Make sure that applying `delta` leaves the arena consistent, without
+    /// changing anything.
+    fn check_delta(&self, delta: &ArenaDelta<T>) -> Result<(), DeltaMismatch> {
+        let mismatch = |slot| Err(DeltaMismatch { slot });
+
+        // Slots that don't exist yet are empty with their first generation.
+        let current = |slot: u32| self.storage.get(slot as usize);
+
+        for change in &delta.changes {
+            let slot = change.index().slot;
+
+            let fits = match (change, current(slot)) {
+                (SlotChange::Changed { index, .. }, Some(Slot::Occupied(occupied))) => {
+                    occupied.generation == index.generation
+                }
+                (SlotChange::Changed { .. }, _) => false,
+                (SlotChange::Invalidated { .. }, entry) => {
+                    matches!(entry, Some(Slot::Occupied(_)))
+                }
+                (SlotChange::Inserted { .. }, _) | (SlotChange::Removed { .. }, _) => true,
+            };
+
+            if !fits {
+                return mismatch(slot);
+            }
+        }
+
+        let is_empty_after = |slot: u32| match delta.change(slot) {
+            Some(change) => change.leaves_empty(),
+            None => !matches!(current(slot), Some(Slot::Occupied(_))),
+        };
+
+        let (free_list, quarantined) = match (&delta.free_list, &delta.quarantined) {
+            (Some(free_list), Some(quarantined)) => (free_list, quarantined),
+            (Some(_), None) | (None, Some(_)) => return mismatch(delta.slot_count),
+            (None, None) => {
+                // The free list is kept, so nothing may become empty or
+                // occupied.
+                if delta.slot_count as usize != self.storage.len() {
+                    return mismatch(delta.slot_count);
+                }
+
+                for change in &delta.changes {
+                    let slot = change.index().slot;
+                    let was_empty = !matches!(current(slot), Some(Slot::Occupied(_)));
+                    if change.leaves_empty() != was_empty {
+                        return mismatch(slot);
+                    }
+                }
+
+                return Ok(());
+            }
+        };
+
+        let mut visited = Vec::new();
+        visited.resize(delta.slot_count as usize, false);
+        let mut previous: Option<u32> = None;
+
+        for &slot in free_list {
+            if slot >= delta.slot_count || visited[slot as usize] || !is_empty_after(slot) {
+                return mismatch(slot);
+            }
+
+            if self.policy == ReusePolicy::LowestSlot && previous.is_some_and(|p| p > slot) {
+                return mismatch(slot);
+            }
+
+            visited[slot as usize] = true;
+            previous = Some(slot);
+        }
+
+        let mut previous_left = 0;
+
+        for &(left, slot) in quarantined {
+            if slot >= delta.slot_count || visited[slot as usize] || !is_empty_after(slot) {
+                return mismatch(slot);
+            }
+
+            // Slots are released in order, and their release epoch must fit.
+            if left < previous_left || self.quarantine.epoch.checked_add(left).is_none() {
+                return mismatch(slot);
+            }
+
+            visited[slot as usize] = true;
+            previous_left = left;
+        }
+
+        if let Some(slot) =
+            (0..delta.slot_count).find(|&slot| is_empty_after(slot) && !visited[slot as usize])
+        {
+            return mismatch(slot);
+        }
+
+        Ok(())
+    }
+}
+
+/// Apply a delta created by [`Arena::diff`], turning the arena into the state
+/// the delta was computed from. The arena must be in the same state as the
+/// `previous` arena that was passed to `diff`.
+///
+/// If the delta changes the free list or quarantine, both are replaced with
+/// the ones in the delta.
+///
+/// Returns an error without changing the arena if the delta doesn't fit the
+/// arena's current state.
+impl<T> Apply<T> for ArenaDelta<T> {
+    type Output = Result<(), DeltaMismatch>;
+
+    fn apply_to(self, arena: &mut Arena<T>) -> Self::Output {
+        arena.apply_delta(self)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{DeltaMismatch, SlotChange};
+    use crate::test_util::{assert_same_state, POLICIES};
+    use crate::Arena;
+
+    fn roundtrip(current: &Arena<u32>, previous: &Arena<u32>) {
+        let mut receiver = previous.clone();
+        receiver.apply(current.diff(previous)).unwrap();
+        assert_same_state(&receiver, current);
+    }
+
+    #[test]
+    fn unchanged() {
+        let mut arena = Arena::new();
+        arena.insert(1);
+
+        let delta = arena.diff(&arena.clone());
+        assert!(delta.is_empty());
+        roundtrip(&arena, &arena.clone());
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+        let d = arena.insert(4);
+        let previous = arena.clone();
+
+        arena[a] = 10;
+        let b2 = arena.invalidate(b).unwrap();
+        arena.remove(c);
+        arena.remove(d);
+        let d2 = arena.insert(40);
+        let e = arena.insert(5);
+
+        let delta = arena.diff(&previous);
+        assert_eq!(
+            delta.changes(),
+            &[
+                SlotChange::Changed {
+                    index: a,
+                    value: 10
+                },
+                SlotChange::Invalidated { index: b2 },
+                SlotChange::Inserted { index: e, value: 5 },
+                SlotChange::Inserted {
+                    index: d2,
+                    value: 40
+                },
+            ]
+        );
+        assert_eq!(delta.free_list(), None);
+
+        roundtrip(&arena, &previous);
+    }
+
+    #[test]
+    fn removals_keep_free_list_order() {
+        for &policy in &POLICIES {
+            let mut arena = Arena::with_reuse_policy(policy);
+            let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+            let previous = arena.clone();
+
+            for &i in &[7, 2, 5] {
+                arena.remove(indices[i]);
+            }
+
+            // A slot that was used and freed again between the two states
+            // changes only its generation.
+            let temporary = arena.insert(100);
+            arena.remove(temporary);
+
+            roundtrip(&arena, &previous);
+
+            let mut receiver = previous.clone();
+            receiver.apply(arena.diff(&previous)).unwrap();
+            for i in 0..5 {
+                assert_eq!(receiver.insert(i), arena.insert(i));
+            }
+        }
+    }
+
+    #[test]
+    fn growth_and_quarantine() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(2);
+        let a = arena.insert(1);
+        let previous = arena.clone();
+
+        arena.insert_at_slot(6, 6);
+        arena.remove(a);
+
+        roundtrip(&arena, &previous);
+    }
+
+    #[test]
+    fn quarantine_is_replicated() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(2);
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        arena.remove(a);
+        arena.advance_epoch();
+        arena.remove(b);
+
+        // The quarantine setting isn't part of the delta.
+        let mut previous = Arena::new();
+        previous.set_quarantine(2);
+
+        let delta = arena.diff(&previous);
+        assert_eq!(delta.quarantined(), Some(&[(1, 0), (2, 1)][..]));
+        assert_eq!(delta.free_list(), Some(&[][..]));
+
+        let mut receiver = previous.clone();
+        receiver.apply(delta).unwrap();
+        assert_same_state(&receiver, &arena);
+        assert_eq!(receiver.quarantined_len(), 2);
+
+        // The receiver releases the slots on the same schedule.
+        for _ in 0..2 {
+            arena.advance_epoch();
+            receiver.advance_epoch();
+            assert_same_state(&receiver, &arena);
+        }
+        assert_eq!(receiver.quarantined_len(), 0);
+    }
+
+    #[test]
+    fn released_quarantine() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(1);
+        let a = arena.insert(1);
+        arena.insert(2);
+        arena.remove(a);
+        let previous = arena.clone();
+
+        // Releasing the slot only moves it from quarantine to the free list.
+        arena.advance_epoch();
+        let delta = arena.diff(&previous);
+        assert!(!delta.is_empty());
+
+        let mut receiver = previous.clone();
+        receiver.apply(delta).unwrap();
+        assert_eq!(receiver.next_index(), arena.next_index());
+        assert_eq!(receiver.next_index().slot(), a.slot());
+    }
+
+    #[test]
+    fn mismatched_base() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let previous = arena.clone();
+        arena[a] = 2;
+
+        let delta = arena.diff(&previous);
+
+        let mut other = Arena::new();
+        other.insert(1);
+        other.invalidate(a);
+        let before = other.clone();
+
+        assert_eq!(other.apply(delta), Err(DeltaMismatch { slot: 0 }));
+        assert_same_state(&other, &before);
+    }
+
+    #[test]
+    fn sequence_of_deltas() {
+        let mut sender = Arena::new();
+        let mut receiver = Arena::new();
+        let mut last = sender.clone();
+        let mut live = Vec::new();
+
+        for step in 0..200u32 {
+            match step % 5 {
+                0..=2 => live.push(sender.insert(step)),
+                3 => {
+                    if let Some(index) = live.pop() {
+                        sender.remove(index);
+                    }
+                }
+                _ => {
+                    if let Some(index) = live.first_mut() {
+                        *index = sender.invalidate(*index).unwrap();
+                    }
+                }
+            }
+
+            if step % 7 == 0 {
+                receiver.apply(sender.diff(&last)).unwrap();
+                last = sender.clone();
+                assert_same_state(&receiver, &sender);
+            }
+        }
+    }
+}
@@ -1,24 +1,30 @@
 //! Entry API for Thunderdome.
 
 use core::fmt;
+use core::marker::PhantomData;
 
 use crate::arena::{Arena, Index};
+use crate::storage::sealed::ArenaLike;
+use crate::storage::SlotStorage;
 
 /// A view into a single entry in an [`Arena`], which may either be vacant or
 /// occupied.
 ///
-/// This `enum` is constructed from the [`entry`] method on [`Arena`].
+/// This `enum` is constructed from the [`entry`] and [`entry_by_slot`] methods
+/// on [`Arena`]. The same types are used for entries in a
+/// [`CowArena`](crate::CowArena), with `A` set to the arena type.
 ///
 /// [`entry`]: Arena::entry
-pub enum Entry<'a, T> {
+/// [`entry_by_slot`]: Arena::entry_by_slot
+pub enum Entry<'a, T, A = Arena<T>> {
     /// A vacant entry.
-    Vacant(VacantEntry<'a, T>),
+    Vacant(VacantEntry<'a, T, A>),
 
     /// An occupied entry.
-    Occupied(OccupiedEntry<'a, T>),
+    Occupied(OccupiedEntry<'a, T, A>),
 }
 
-impl<T: fmt::Debug> fmt::Debug for Entry<'_, T> {
+impl<T: fmt::Debug, A: fmt::Debug> fmt::Debug for Entry<'_, T, A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Entry::Vacant(v) => f.debug_tuple("Entry").field(v).finish(),
@@ -30,20 +36,32 @@ impl<T: fmt::Debug> fmt::Debug for Entry<'_, T> {
 /// A view into a vacant entry in an [`Arena`].
 /// It is part of the [`Entry`] enum.
 #[derive(Debug)]
-pub struct VacantEntry<'a, T> {
-    arena: &'a mut Arena<T>,
+pub struct VacantEntry<'a, T, A = Arena<T>> {
+    arena: &'a mut A,
     index: Index,
+    value: PhantomData<T>,
 }
 
 /// A view into an occupied entry in an [`Arena`].
 /// It is part of the [`Entry`] enum.
 #[derive(Debug)]
-pub struct OccupiedEntry<'a, T> {
-    arena: &'a mut Arena<T>,
+pub struct OccupiedEntry<'a, T, A = Arena<T>> {
+    arena: &'a mut A,
     index: Index,
+    value: PhantomData<T>,
 }
 
-impl<'a, T> Entry<'a, T> {
+impl<'a, T, A: ArenaLike<T>> Entry<'a, T, A> {
+    /// The entry for the given index: occupied if the index is in the arena,
+    /// and vacant otherwise.
+    pub(crate) fn new(arena: &'a mut A, index: Index) -> Self {
+        if arena.contains(index) {
+            Entry::Occupied(OccupiedEntry::new(arena, index))
+        } else {
+            Entry::Vacant(VacantEntry::new(arena, index))
+        }
+    }
+
     /// Ensures a value is in the entry by inserting the default if empty, and
     /// returns a mutable reference to the value in the entry.
     ///
@@ -110,9 +128,24 @@ impl<'a, T> Entry<'a, T> {
             Entry::Vacant(entry) => Entry::Vacant(entry),
         }
     }
+
+    /// Sets the value of the entry, and returns an [`OccupiedEntry`].
+    ///
+    /// If this entry is occupied, its value is replaced and dropped, keeping
+    /// its key. If it is vacant, this calls [`Arena::insert_at`] internally, so
+    /// it is capable of "resurrecting" an old index.
+    pub fn insert_entry(self, value: T) -> OccupiedEntry<'a, T, A> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                entry.insert(value);
+                entry
+            }
+            Entry::Vacant(entry) => entry.insert_entry(value),
+        }
+    }
 }
 
-impl<'a, T: Default> Entry<'a, T> {
+impl<'a, T: Default, A: ArenaLike<T>> Entry<'a, T, A> {
     /// Ensures a value is in the entry by inserting the default value if empty,
     /// and returns a mutable reference to the value in the entry.
     ///
@@ -123,7 +156,15 @@ impl<'a, T: Default> Entry<'a, T> {
     }
 }
 
-impl<'a, T> VacantEntry<'a, T> {
+impl<'a, T, A: ArenaLike<T>> VacantEntry<'a, T, A> {
+    pub(crate) fn new(arena: &'a mut A, index: Index) -> Self {
+        VacantEntry {
+            arena,
+            index,
+            value: PhantomData,
+        }
+    }
+
     /// Gets the key that would be used when inserting a value through the
     /// `VacantEntry`.
     pub fn key(&self) -> Index {
@@ -136,14 +177,29 @@ impl<'a, T> VacantEntry<'a, T> {
     /// This calls [`Arena::insert_at`] internally, so it is capable of
     /// "resurrecting" an old index.
     pub fn insert(self, value: T) -> &'a mut T {
+        self.insert_entry(value).into_mut()
+    }
+
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns an
+    /// [`OccupiedEntry`] for it.
+    ///
+    /// This calls [`Arena::insert_at`] internally, so it is capable of
+    /// "resurrecting" an old index.
+    pub fn insert_entry(self, value: T) -> OccupiedEntry<'a, T, A> {
         self.arena.insert_at(self.index, value);
-        self.arena
-            .get_mut(self.index)
-            .unwrap_or_else(|| unreachable!("insert_at must create an occupied entry"))
+        OccupiedEntry::new(self.arena, self.index)
     }
 }
 
-impl<'a, T> OccupiedEntry<'a, T> {
+impl<'a, T, A: ArenaLike<T>> OccupiedEntry<'a, T, A> {
+    pub(crate) fn new(arena: &'a mut A, index: Index) -> Self {
+        OccupiedEntry {
+            arena,
+            index,
+            value: PhantomData,
+        }
+    }
+
     /// Gets the key in the entry.
     pub fn key(&self) -> Index {
         self.index
@@ -191,6 +247,31 @@ impl<'a, T> OccupiedEntry<'a, T> {
             .remove(self.index)
             .unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"))
     }
+
+    /// Gives the entry's value a new index, so that the entry's old key is no
+    /// longer valid, and returns the new key. See [`Arena::invalidate`].
+    pub fn invalidate(&mut self) -> Index {
+        self.index = self
+            .arena
+            .invalidate(self.index)
+            .unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"));
+
+        self.index
+    }
+
+    /// Replaces the entry's value and gives it a new index, like
+    /// [`Arena::insert_at_slot`], returning the entry's old key and value. The
+    /// entry's key becomes the new index.
+    pub fn replace_entry(&mut self, value: T) -> (Index, T) {
+        let old_index = self.index;
+        let (index, old_value) = self.arena.insert_at_slot(old_index.slot, value);
+        self.index = index;
+
+        let old_value =
+            old_value.unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"));
+
+        (old_index, old_value)
+    }
 }
 
 impl<T> Arena<T> {
@@ -201,11 +282,21 @@ impl<T> Arena<T> {
     /// and vacant otherwise (that is, when the slot is empty, out of bounds, or
     /// occupied by a different generation).
     pub fn entry(&mut self, index: Index) -> Entry<'_, T> {
-        if self.contains(index) {
-            Entry::Occupied(OccupiedEntry { arena: self, index })
-        } else {
-            Entry::Vacant(VacantEntry { arena: self, index })
-        }
+        Entry::new(self, index)
+    }
+
+    /// Gets the entry for the given slot in the arena for in-place
+    /// manipulation, whatever generation it's at.
+    ///
+    /// The entry is occupied if the slot holds a value, with that value's
+    /// index as its key. Otherwise it's vacant, with the key that
+    /// [`Arena::insert_at_slot`] would give a value inserted there.
+    pub fn entry_by_slot(&mut self, slot: u32) -> Entry<'_, T> {
+        let index = self
+            .contains_slot(slot)
+            .unwrap_or_else(|| self.next_index_at(slot));
+
+        Entry::new(self, index)
     }
 
     /// Gets a vacant entry in the arena, with its key computed up front.
@@ -215,7 +306,7 @@ impl<T> Arena<T> {
     /// [`Arena::next_index`].
     pub fn vacant_entry(&mut self) -> VacantEntry<'_, T> {
         let index = self.next_index();
-        VacantEntry { arena: self, index }
+        VacantEntry::new(self, index)
     }
 }
 
@@ -379,4 +470,71 @@ mod test {
         *value = 9;
         assert_eq!(arena[index], 9); // Mutable reference works.
     }
+
+    #[test]
+    fn entry_by_slot() {
+        let mut arena = Arena::new();
+        let first = arena.insert(1);
+        let second = arena.insert(2);
+        arena.remove(first);
+
+        match arena.entry_by_slot(1) {
+            Entry::Occupied(entry) => assert_eq!(entry.key(), second),
+            Entry::Vacant(_) => panic!("expected occupied"),
+        }
+
+        // Vacant entries get the key insert_at_slot would produce.
+        let key = arena.entry_by_slot(0).key();
+        assert_eq!(key.slot(), 0);
+        assert_eq!(key.generation(), first.generation() + 1);
+        assert_eq!(arena.entry_by_slot(4).key().generation(), 1);
+
+        arena.entry_by_slot(4).or_insert(5);
+        let (index, _) = arena.insert_at_slot(0, 0);
+        assert_eq!(index, key);
+        assert_eq!(arena.values().copied().collect::<Vec<_>>(), [0, 2, 5]);
+    }
+
+    #[test]
+    fn occupied_invalidate_and_replace() {
+        let mut arena = Arena::new();
+        let index = arena.insert("a");
+
+        let mut entry = match arena.entry(index) {
+            Entry::Occupied(entry) => entry,
+            Entry::Vacant(_) => panic!("expected occupied"),
+        };
+
+        let invalidated = entry.invalidate();
+        assert_ne!(invalidated, index);
+        assert_eq!(entry.key(), invalidated);
+        assert_eq!(entry.get(), &"a");
+
+        assert_eq!(entry.replace_entry("b"), (invalidated, "a"));
+        let replaced = entry.key();
+        assert_ne!(replaced, invalidated);
+        assert_eq!(entry.into_mut(), &mut "b");
+
+        assert!(!arena.contains(index));
+        assert!(!arena.contains(invalidated));
+        assert_eq!(arena[replaced], "b");
+    }
+
+    #[test]
+    fn insert_entry() {
+        let mut arena = Arena::new();
+        let index = arena.insert(1);
+        arena.remove(index);
+
+        let entry = arena.entry(index).insert_entry(2);
+        assert_eq!(entry.key(), index);
+        assert_eq!(entry.get(), &2);
+
+        let entry = arena.entry(index).insert_entry(3);
+        assert_eq!(entry.remove(), 3);
+
+        let entry = arena.vacant_entry().insert_entry(4);
+        let key = entry.key();
+        assert_eq!(arena[key], 4);
+    }
 }
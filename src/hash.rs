@@ -0,0 +1,393 @@
+use core::convert::TryInto;
+use core::hash::{Hash, Hasher};
+
+use crate::arena::{Arena, Slot};
+use crate::policy::ReusePolicy;
+use crate::storage::SlotStorage;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A [`Hasher`] that gives the same result on every platform, for comparing
+/// [`Arena::state_hash`] across machines.
+///
+/// It computes the 64-bit FNV-1a hash, with integers always written as
+/// little-endian bytes and `usize` and `isize` widened to 64 bits. FNV-1a is
+/// fast and simple, but it is not resistant to collisions crafted on purpose.
+#[derive(Debug, Clone)]
+pub struct StateHasher {
+    hash: u64,
+}
+
+impl StateHasher {
+    /// Construct a hasher with the standard FNV-1a offset basis.
+    pub fn new() -> Self {
+        StateHasher {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StateHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = (self.hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16);
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as i64 as u64);
+    }
+}
+
+/// Feed the complete state of an arena to a hasher. See `Arena::state_hash`
+/// for the definition.
+pub(crate) fn hash_state<'a, T, H>(
+    policy: ReusePolicy,
+    slot_count: u32,
+    slots: impl Iterator<Item = &'a Slot<T>>,
+    free_list: impl Iterator<Item = u32>,
+    quarantine_epochs: u32,
+    quarantined: impl Iterator<Item = (u64, u32)>,
+    state: &mut H,
+) where
+    T: Hash + 'a,
+    H: Hasher,
+{
+    state.write_u8(match policy {
+        ReusePolicy::Lifo => 0,
+        ReusePolicy::Fifo => 1,
+        ReusePolicy::LowestSlot => 2,
+    });
+
+    state.write_u32(slot_count);
+
+    for slot in slots {
+        match slot {
+            Slot::Occupied(occupied) => {
+                state.write_u8(1);
+                state.write_u32(occupied.generation.to_u32());
+                occupied.value.hash(state);
+            }
+            Slot::Empty(empty) => {
+                state.write_u8(0);
+                state.write_u32(empty.generation.to_u32());
+            }
+        }
+    }
+
+    let mut count = 0u64;
+    for slot in free_list {
+        state.write_u32(slot);
+        count = count.wrapping_add(1);
+    }
+    state.write_u64(count);
+
+    state.write_u32(quarantine_epochs);
+
+    let mut count = 0u64;
+    for (remaining, slot) in quarantined {
+        state.write_u64(remaining);
+        state.write_u32(slot);
+        count = count.wrapping_add(1);
+    }
+    state.write_u64(count);
+}
+
+impl<T> Arena<T> {
+    /// Feed the complete state of the arena to a hasher, so that arenas on
+    /// different machines can be checked for desyncs. Two arenas hash the same
+    /// if they hold the same values at the same indices, and will also give
+    /// out the same indices from now on.
+    ///
+    /// The following are written to the hasher, in order:
+    ///
+    /// 1. The [`ReusePolicy`], as a `u8`: 0 for `Lifo`, 1 for `Fifo`, and 2
+    ///    for `LowestSlot`.
+    /// 2. The number of slots, as a `u32`.
+    /// 3. For each slot in order, a `u8` of 1 followed by the `u32` generation
+    ///    and the value's `Hash` if the slot is occupied, or a `u8` of 0 and
+    ///    the `u32` generation if it is empty.
+    /// 4. The slot number of each entry in the free list as a `u32`, in the
+    ///    order they will be reused, followed by their count as a `u64`.
+    /// 5. The quarantine length set with [`Arena::set_quarantine`] as a `u32`.
+    ///    Then for each quarantined slot, in the order they are released, the
+    ///    number of epochs until it is released as a `u64` and the slot number
+    ///    as a `u32`, followed by their count as a `u64`.
+    ///
+    /// Integers are passed to the matching `Hasher::write_*` method. Hashes
+    /// only match across platforms if the hasher writes integers the same way
+    /// everywhere, like [`StateHasher`] does, and if `T`'s `Hash`
+    /// implementation is platform-independent.
+    ///
+    /// ```rust
+    /// # use thunderdome::{Arena, StateHasher};
+    /// # use core::hash::Hasher;
+    /// let mut a = Arena::new();
+    /// let mut b = Arena::new();
+    /// a.insert("foo");
+    /// b.insert("foo");
+    ///
+    /// let hash = |arena: &Arena<&str>| {
+    ///     let mut hasher = StateHasher::new();
+    ///     arena.state_hash(&mut hasher);
+    ///     hasher.finish()
+    /// };
+    /// assert_eq!(hash(&a), hash(&b));
+    ///
+    /// // Removing and reinserting changes the generation of the slot.
+    /// let index = b.insert("bar");
+    /// b.remove(index);
+    /// b.insert("bar");
+    /// a.insert("bar");
+    /// assert_ne!(hash(&a), hash(&b));
+    /// ```
+    pub fn state_hash<H: Hasher>(&self, state: &mut H)
+    where
+        T: Hash,
+    {
+        let slot_count = self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        });
+
+        hash_state(
+            self.policy,
+            slot_count,
+            self.storage.iter(),
+            self.free_list(),
+            self.quarantine.epochs,
+            self.quarantined(),
+            state,
+        );
+    }
+
+    /// Returns true if both arenas are in the same complete state: the same
+    /// values at the same indices, the same generations in empty slots, the
+    /// same free list order, quarantine, and [`ReusePolicy`]. Arenas in the
+    /// same state give out the same indices from now on.
+    ///
+    /// This is stricter than `==`, which only compares the indices and values
+    /// the arenas contain.
+    ///
+    /// ```rust
+    /// # use thunderdome::Arena;
+    /// let mut a = Arena::new();
+    /// let mut b = Arena::new();
+    /// a.insert(1);
+    ///
+    /// let index = b.insert(0);
+    /// b.remove(index);
+    /// b.insert_at_slot(0, 1);
+    ///
+    /// // Both arenas contain 1 in slot 0, but with different generations.
+    /// assert_ne!(a, b);
+    /// assert!(!a.same_state(&b));
+    ///
+    /// let c = a.clone();
+    /// assert_eq!(a, c);
+    /// assert!(a.same_state(&c));
+    /// ```
+    pub fn same_state(&self, other: &Arena<T>) -> bool
+    where
+        T: PartialEq,
+    {
+        let same_slot = |(a, b): (&Slot<T>, &Slot<T>)| match (a, b) {
+            (Slot::Occupied(a), Slot::Occupied(b)) => {
+                a.generation == b.generation && a.value == b.value
+            }
+            (Slot::Empty(a), Slot::Empty(b)) => a.generation == b.generation,
+            _ => false,
+        };
+
+        self.policy == other.policy
+            && self.len == other.len
+            && self.storage.len() == other.storage.len()
+            && self.storage.iter().zip(&other.storage).all(same_slot)
+            && self.free_list().eq(other.free_list())
+            && self.quarantine.epochs == other.quarantine.epochs
+            && self.quarantined().eq(other.quarantined())
+    }
+}
+
+/// Arenas are equal if they contain the same values at the same indices. Empty
+/// slots, the free list, and other state that only affects which indices are
+/// given out in the future are ignored; use [`Arena::same_state`] to compare
+/// those too.
+impl<T: PartialEq> PartialEq for Arena<T> {
+    fn eq(&self, other: &Arena<T>) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .all(|(index, value)| other.get(index) == Some(value))
+    }
+}
+
+impl<T: Eq> Eq for Arena<T> {}
+
+/// Hashes the indices and values the arena contains, consistent with its
+/// `PartialEq` implementation. Use [`Arena::state_hash`] to hash the complete
+/// state of the arena.
+impl<T: Hash> Hash for Arena<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+
+        for (index, value) in self {
+            index.hash(state);
+            value.hash(state);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use core::hash::{Hash, Hasher};
+
+    use super::StateHasher;
+    use crate::{Arena, CowArena, ReusePolicy};
+
+    fn state_hash(arena: &Arena<u32>) -> u64 {
+        let mut hasher = StateHasher::new();
+        arena.state_hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn contents_hash(arena: &Arena<u32>) -> u64 {
+        let mut hasher = StateHasher::new();
+        arena.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn fnv1a_reference_values() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StateHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn integers_are_little_endian() {
+        let mut a = StateHasher::new();
+        a.write_u32(0x0102_0304);
+        a.write_usize(5);
+
+        let mut b = StateHasher::new();
+        b.write(&[4, 3, 2, 1]);
+        b.write(&[5, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert_eq!(a.finish(), b.finish());
+    }
+
+    #[test]
+    fn state_hash_covers_full_state() {
+        let mut a = Arena::new();
+        let x = a.insert(1);
+        a.insert(2);
+        a.remove(x);
+
+        let mut b = a.clone();
+        assert_eq!(state_hash(&a), state_hash(&b));
+        assert!(a.same_state(&b));
+
+        // An empty slot past the end changes the state but not the contents.
+        let extra = b.insert_at_slot(5, 0).0;
+        b.remove(extra);
+        assert_eq!(a, b);
+        assert_eq!(contents_hash(&a), contents_hash(&b));
+        assert!(!a.same_state(&b));
+        assert_ne!(state_hash(&a), state_hash(&b));
+
+        // So does quarantine.
+        let mut c = a.clone();
+        c.set_quarantine(2);
+        assert!(!a.same_state(&c));
+        assert_ne!(state_hash(&a), state_hash(&c));
+
+        let d = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        assert!(!Arena::new().same_state(&d));
+        assert_ne!(state_hash(&Arena::new()), state_hash(&d));
+    }
+
+    #[test]
+    fn quarantine_is_relative_to_epoch() {
+        let mut a = Arena::new();
+        a.set_quarantine(2);
+        let mut b = a.clone();
+
+        b.advance_epoch();
+        assert!(a.same_state(&b));
+
+        let index = a.insert(1);
+        a.remove(index);
+        let index = b.insert(1);
+        b.remove(index);
+        assert!(a.same_state(&b));
+        assert_eq!(state_hash(&a), state_hash(&b));
+    }
+
+    #[test]
+    fn cow_arena_hashes_the_same() {
+        let mut arena = Arena::new();
+        for i in 0..100 {
+            arena.insert(i);
+        }
+        arena.retain(|_, value| *value % 3 != 0);
+
+        let cow = CowArena::from(arena.clone());
+        let mut hasher = StateHasher::new();
+        cow.state_hash(&mut hasher);
+
+        assert_eq!(hasher.finish(), state_hash(&arena));
+    }
+}
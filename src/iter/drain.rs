@@ -1,15 +1,18 @@
 use core::iter::{ExactSizeIterator, FusedIterator};
+use core::marker::PhantomData;
 
 use crate::arena::{Arena, Index};
+use crate::storage::sealed::ArenaLike;
 
-/// See [`Arena::drain`].
+/// See [`Arena::drain`] and [`CowArena::drain`](crate::CowArena::drain).
 #[derive(Debug)]
-pub struct Drain<'a, T> {
-    pub(crate) arena: &'a mut Arena<T>,
+pub struct Drain<'a, T, A: ArenaLike<T> = Arena<T>> {
+    pub(crate) arena: &'a mut A,
     pub(crate) slot: u32,
+    pub(crate) value: PhantomData<T>,
 }
 
-impl<'a, T> Iterator for Drain<'a, T> {
+impl<'a, T, A: ArenaLike<T>> Iterator for Drain<'a, T, A> {
     type Item = (Index, T);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -17,7 +20,7 @@ impl<'a, T> Iterator for Drain<'a, T> {
             // If there are no entries remaining in the arena, we should always
             // return None. Using this check instead of comparing with the
             // arena's size allows us to skip any trailing empty entries.
-            if self.arena.is_empty() {
+            if self.arena.len() == 0 {
                 return None;
             }
 
@@ -44,10 +47,10 @@ impl<'a, T> Iterator for Drain<'a, T> {
     }
 }
 
-impl<'a, T> FusedIterator for Drain<'a, T> {}
-impl<'a, T> ExactSizeIterator for Drain<'a, T> {}
+impl<'a, T, A: ArenaLike<T>> FusedIterator for Drain<'a, T, A> {}
+impl<'a, T, A: ArenaLike<T>> ExactSizeIterator for Drain<'a, T, A> {}
 
-impl<'a, T> Drop for Drain<'a, T> {
+impl<'a, T, A: ArenaLike<T>> Drop for Drain<'a, T, A> {
     // Continue iterating/dropping if there are any elements left.
     fn drop(&mut self) {
         self.for_each(drop);
@@ -0,0 +1,82 @@
+use core::iter::{ExactSizeIterator, FusedIterator};
+
+use crate::arena::{Arena, Index};
+
+/// See [`Arena::drain_range`].
+///
+/// Unlike [`Drain`](crate::iter::Drain), this iterator only works with
+/// [`Arena`], since [`CowArena`](crate::CowArena) has no way to drain a range.
+#[derive(Debug)]
+pub struct DrainRange<'a, T> {
+    pub(crate) arena: &'a mut Arena<T>,
+    pub(crate) slot: u32,
+    pub(crate) end: u32,
+
+    /// The number of occupied slots left in the range.
+    pub(crate) remaining: usize,
+}
+
+impl<'a, T> Iterator for DrainRange<'a, T> {
+    type Item = (Index, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.slot < self.end {
+            let slot = self.slot;
+
+            // slot can't overflow here, since it's less than end.
+            self.slot = slot.wrapping_add(1);
+
+            if let Some((index, value)) = self.arena.remove_by_slot(slot) {
+                self.remaining = self.remaining.saturating_sub(1);
+                return Some((index, value));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> FusedIterator for DrainRange<'a, T> {}
+impl<'a, T> ExactSizeIterator for DrainRange<'a, T> {}
+
+impl<'a, T> Drop for DrainRange<'a, T> {
+    // Continue iterating/dropping if there are any elements left.
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn drain_range() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[3]);
+
+        {
+            let mut drain = arena.drain_range(2..=5);
+            assert_eq!(drain.len(), 3);
+            assert_eq!(drain.next(), Some((indices[2], 2)));
+            assert_eq!(drain.len(), 2);
+            assert_eq!(drain.next(), Some((indices[4], 4)));
+        }
+
+        let values: Vec<_> = arena.values().copied().collect();
+        assert_eq!(values, [0, 1, 6, 7, 8, 9]);
+
+        let drained: Vec<_> = arena.drain_range(8..).map(|(_, value)| value).collect();
+        assert_eq!(drained, [8, 9]);
+
+        // Ranges past the end of the arena are empty.
+        assert_eq!(arena.drain_range(20..30).len(), 0);
+        assert_eq!(arena.drain_range(..).len(), 4);
+        assert!(arena.is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use core::fmt;
+use core::iter::FusedIterator;
+
+use crate::arena::{Arena, Index, Slot};
+
+/// See [`Arena::extract_if`].
+///
+/// Unlike [`Drain`](crate::iter::Drain), this iterator only works with
+/// [`Arena`], since [`CowArena`](crate::CowArena) has no way to extract values.
+pub struct ExtractIf<'a, T, F>
+where
+    F: FnMut(Index, &mut T) -> bool,
+{
+    pub(crate) arena: &'a mut Arena<T>,
+    pub(crate) slot: u32,
+    pub(crate) pred: F,
+
+    /// Set while the predicate runs, so that if it panics, dropping the
+    /// iterator during unwinding doesn't run it again.
+    pub(crate) panicked: bool,
+}
+
+/// Only the position of the iterator is shown, since showing the whole arena
+/// would be more noise than help.
+impl<'a, T, F> fmt::Debug for ExtractIf<'a, T, F>
+where
+    F: FnMut(Index, &mut T) -> bool,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf")
+            .field("slot", &self.slot)
+            .field("panicked", &self.panicked)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, T, F> Iterator for ExtractIf<'a, T, F>
+where
+    F: FnMut(Index, &mut T) -> bool,
+{
+    type Item = (Index, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while (self.slot as usize) < self.arena.storage.len() {
+            let slot = self.slot;
+
+            // slot can't overflow here, since it's less than the length of the
+            // arena's storage, which never exceeds u32::MAX slots.
+            self.slot = slot.wrapping_add(1);
+
+            let extract = match &mut self.arena.storage[slot as usize] {
+                Slot::Occupied(occupied) => {
+                    let index = Index {
+                        slot,
+                        generation: occupied.generation,
+                    };
+
+                    self.panicked = true;
+                    let extract = (self.pred)(index, &mut occupied.value);
+                    self.panicked = false;
+                    extract
+                }
+                Slot::Empty(_) => false,
+            };
+
+            if extract {
+                return self.arena.remove_by_slot(slot);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.arena.len()))
+    }
+}
+
+impl<'a, T, F> FusedIterator for ExtractIf<'a, T, F> where F: FnMut(Index, &mut T) -> bool {}
+
+impl<'a, T, F> Drop for ExtractIf<'a, T, F>
+where
+    F: FnMut(Index, &mut T) -> bool,
+{
+    // Continue extracting and dropping any matching values that are left,
+    // unless the predicate panicked, in which case running it again could
+    // panic while unwinding and abort.
+    fn drop(&mut self) {
+        if !self.panicked {
+            self.for_each(drop);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::Arena;
+
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn extract_if() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+
+        let mut extracted = Vec::new();
+        {
+            let mut extract = arena.extract_if(|_, value| {
+                *value += 100;
+                *value % 2 == 0
+            });
+
+            extracted.push(extract.next().unwrap());
+            extracted.push(extract.next().unwrap());
+
+            // The rest of the matching values are removed on drop.
+        }
+
+        assert_eq!(extracted, [(indices[0], 100), (indices[4], 104)]);
+        assert_eq!(
+            format!("{:?}", arena.extract_if(|_, _| false)),
+            "ExtractIf { slot: 0, panicked: false, .. }"
+        );
+        assert_eq!(arena.len(), 5);
+        assert_eq!(arena.get(indices[8]), None);
+
+        let values: Vec<_> = arena.values().copied().collect();
+        assert_eq!(values, [101, 103, 105, 107, 109]);
+    }
+
+    #[test]
+    fn extract_if_predicate_panics() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+
+        let mut calls = 0;
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            arena
+                .extract_if(|_, value| {
+                    calls += 1;
+                    if *value == 3 {
+                        panic!("predicate failed");
+                    }
+                    *value % 2 == 0
+                })
+                .count()
+        }));
+
+        // The predicate isn't run again after it panics.
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.get(indices[0]), None);
+        assert_eq!(arena.get(indices[2]), None);
+        assert_eq!(arena[indices[4]], 4);
+    }
+}
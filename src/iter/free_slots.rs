@@ -0,0 +1,90 @@
+use core::iter::{ExactSizeIterator, FusedIterator};
+
+use crate::arena::{Arena, Index};
+use crate::free_pointer::FreePointer;
+use crate::storage::SlotStorage;
+
+/// See [`Arena::free_slots`](crate::Arena::free_slots).
+#[derive(Debug)]
+pub struct FreeSlots<'a, T> {
+    pub(crate) arena: &'a Arena<T>,
+    pub(crate) next: Option<FreePointer>,
+    pub(crate) remaining: usize,
+}
+
+impl<'a, T> Clone for FreeSlots<'a, T> {
+    fn clone(&self) -> Self {
+        FreeSlots {
+            arena: self.arena,
+            next: self.next,
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, T> Iterator for FreeSlots<'a, T> {
+    type Item = Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.next?.slot();
+        let empty = self.arena.empty_slot(slot);
+        self.next = empty.next_free;
+        self.remaining = self.remaining.saturating_sub(1);
+
+        Some(Index {
+            slot,
+            generation: empty.generation.next(),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> FusedIterator for FreeSlots<'a, T> {}
+impl<'a, T> ExactSizeIterator for FreeSlots<'a, T> {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::test_util::POLICIES;
+    use crate::Arena;
+
+    #[test]
+    fn free_slots_in_reuse_order() {
+        for &policy in &POLICIES {
+            let mut arena = Arena::with_reuse_policy(policy);
+            let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+            for &i in &[4, 1, 3] {
+                arena.remove(indices[i]);
+            }
+
+            let mut iter = arena.free_slots();
+            assert_eq!(iter.len(), 3);
+            iter.next();
+            assert_eq!(iter.len(), 2);
+
+            let free: Vec<_> = arena.free_slots().collect();
+            assert_eq!(free.len(), 3);
+            assert_eq!(free[0], arena.next_index());
+
+            for &index in &free {
+                assert_eq!(arena.insert(0), index);
+            }
+            assert_eq!(arena.free_slots().next(), None);
+        }
+    }
+
+    #[test]
+    fn free_slots_len_skips_quarantine() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[0]);
+        arena.set_quarantine(1);
+        arena.remove(indices[1]);
+
+        let free = arena.free_slots();
+        assert_eq!(free.len(), 1);
+        assert_eq!(free.count(), 1);
+    }
+}
@@ -0,0 +1,51 @@
+use core::iter::{ExactSizeIterator, FusedIterator};
+
+use super::IntoIter;
+use crate::arena::Index;
+
+/// See [`Arena::into_keys`](crate::Arena::into_keys).
+#[derive(Clone, Debug, Default)]
+pub struct IntoKeys<T> {
+    pub(crate) inner: IntoIter<T>,
+}
+
+impl<T> Iterator for IntoKeys<T> {
+    type Item = Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(index, _)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoKeys<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(index, _)| index)
+    }
+}
+
+impl<T> FusedIterator for IntoKeys<T> {}
+impl<T> ExactSizeIterator for IntoKeys<T> {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn into_keys() {
+        let mut arena = Arena::new();
+        let one = arena.insert(String::from("one"));
+        let two = arena.insert(String::from("two"));
+        let three = arena.insert(String::from("three"));
+        arena.remove(one);
+
+        let mut iter = arena.into_keys();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.next(), Some(two));
+        assert_eq!(iter.next_back(), Some(three));
+        assert_eq!(iter.next(), None);
+    }
+}
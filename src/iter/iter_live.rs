@@ -0,0 +1,59 @@
+use core::iter::{ExactSizeIterator, FusedIterator};
+
+use super::Iter;
+use crate::arena::Index;
+
+/// See [`IndexAllocator::iter_live`](crate::IndexAllocator::iter_live).
+#[derive(Clone, Debug, Default)]
+pub struct IterLive<'a> {
+    pub(crate) inner: Iter<'a, ()>,
+}
+
+impl<'a> Iterator for IterLive<'a> {
+    type Item = Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(index, _)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for IterLive<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(index, _)| index)
+    }
+}
+
+impl<'a> FusedIterator for IterLive<'a> {}
+impl<'a> ExactSizeIterator for IterLive<'a> {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::IndexAllocator;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn iter_live() {
+        let mut allocator = IndexAllocator::new();
+        let one = allocator.allocate();
+        let two = allocator.allocate();
+        let three = allocator.allocate();
+        allocator.free(two);
+
+        let mut live = HashSet::new();
+        let mut iter = allocator.iter_live();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+
+        live.insert(iter.next().unwrap());
+        live.insert(iter.next_back().unwrap());
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+
+        assert!(live.contains(&one));
+        assert!(live.contains(&three));
+    }
+}
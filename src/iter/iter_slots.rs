@@ -0,0 +1,132 @@
+use core::convert::TryInto;
+use core::iter::{Enumerate, ExactSizeIterator, FusedIterator};
+use core::slice;
+
+use crate::arena::{Index, Slot};
+
+/// One slot of an [`Arena`](crate::Arena)'s storage, as returned by
+/// [`Arena::iter_slots`](crate::Arena::iter_slots).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotView<'a, T> {
+    /// The slot holds a value.
+    Occupied(Index, &'a T),
+
+    /// The slot is empty. Its generation is that of the last value that lived
+    /// in it, or the first generation if it has never held a value. The next
+    /// value inserted into the slot gets a later generation.
+    Empty {
+        /// The slot's position in the arena's storage.
+        slot: u32,
+
+        /// The slot's current generation.
+        generation: u32,
+    },
+}
+
+impl<'a, T> SlotView<'a, T> {
+    /// The slot's position in the arena's storage.
+    pub fn slot(&self) -> u32 {
+        match self {
+            SlotView::Occupied(index, _) => index.slot(),
+            SlotView::Empty { slot, .. } => *slot,
+        }
+    }
+
+    /// Returns true if the slot holds a value.
+    pub fn is_occupied(&self) -> bool {
+        matches!(self, SlotView::Occupied(..))
+    }
+}
+
+/// See [`Arena::iter_slots`](crate::Arena::iter_slots).
+#[derive(Clone, Debug)]
+pub struct IterSlots<'a, T> {
+    pub(crate) inner: Enumerate<slice::Iter<'a, Slot<T>>>,
+}
+
+fn view<T>((slot, entry): (usize, &Slot<T>)) -> SlotView<'_, T> {
+    let slot = slot
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("Overflowed u32 trying to iterate Arena"));
+
+    match entry {
+        Slot::Occupied(occupied) => SlotView::Occupied(
+            Index {
+                slot,
+                generation: occupied.generation,
+            },
+            &occupied.value,
+        ),
+        Slot::Empty(empty) => SlotView::Empty {
+            slot,
+            generation: empty.generation.to_u32(),
+        },
+    }
+}
+
+impl<'a, T> Iterator for IterSlots<'a, T> {
+    type Item = SlotView<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(view)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterSlots<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(view)
+    }
+}
+
+impl<'a, T> FusedIterator for IterSlots<'a, T> {}
+impl<'a, T> ExactSizeIterator for IterSlots<'a, T> {}
+
+impl<T> Default for IterSlots<'_, T> {
+    fn default() -> Self {
+        Self {
+            inner: slice::Iter::<Slot<T>>::default().enumerate(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::SlotView;
+    use crate::Arena;
+
+    #[test]
+    fn iter_slots() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+        let two = arena.insert(2);
+        arena.remove(one);
+        arena.insert_at_slot(3, 4);
+
+        let slots: Vec<_> = arena.iter_slots().collect();
+        assert_eq!(
+            slots,
+            [
+                SlotView::Empty {
+                    slot: 0,
+                    generation: 1
+                },
+                SlotView::Occupied(two, &2),
+                SlotView::Empty {
+                    slot: 2,
+                    generation: 1
+                },
+                SlotView::Occupied(arena.contains_slot(3).unwrap(), &4),
+            ]
+        );
+
+        let mut iter = arena.iter_slots();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next_back().map(|view| view.slot()), Some(3));
+        assert_eq!(iter.next().map(|view| view.is_occupied()), Some(false));
+        assert_eq!(iter.len(), 2);
+    }
+}
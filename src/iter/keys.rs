@@ -0,0 +1,52 @@
+use core::iter::{ExactSizeIterator, FusedIterator};
+
+use super::Iter;
+use crate::arena::Index;
+
+/// See [`Arena::keys`](crate::Arena::keys).
+#[derive(Clone, Debug, Default)]
+pub struct Keys<'a, T> {
+    pub(crate) inner: Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Keys<'a, T> {
+    type Item = Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(index, _)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Keys<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(index, _)| index)
+    }
+}
+
+impl<'a, T> FusedIterator for Keys<'a, T> {}
+impl<'a, T> ExactSizeIterator for Keys<'a, T> {}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn keys() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+        let two = arena.insert(2);
+        let three = arena.insert(3);
+        arena.remove(two);
+
+        let mut iter = arena.keys();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+        assert_eq!(iter.next_back(), Some(three));
+        assert_eq!(iter.next(), Some(one));
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+    }
+}
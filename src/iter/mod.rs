@@ -1,17 +1,32 @@
 //! Contains all of the iterator types for Thunderdome.
 
 mod drain;
+mod drain_range;
+mod extract_if;
+mod free_slots;
 mod into_iter;
+mod into_keys;
 mod into_values;
+#[allow(clippy::module_inception)]
 mod iter;
+mod iter_live;
 mod iter_mut;
+mod iter_slots;
+mod keys;
 mod values;
 mod values_mut;
 
 pub use drain::Drain;
+pub use drain_range::DrainRange;
+pub use extract_if::ExtractIf;
+pub use free_slots::FreeSlots;
 pub use into_iter::IntoIter;
+pub use into_keys::IntoKeys;
 pub use into_values::IntoValues;
 pub use iter::Iter;
+pub use iter_live::IterLive;
 pub use iter_mut::IterMut;
+pub use iter_slots::{IterSlots, SlotView};
+pub use keys::Keys;
 pub use values::Values;
 pub use values_mut::ValuesMut;
@@ -0,0 +1,911 @@
+//! Crash-safe persistence for arenas through a write-ahead log. Requires the
+//! `journal` feature.
+//!
+//! A [`Journal`] wraps an [`Arena`] and appends a record to a log for every
+//! change before making it. Every so often, [`Journal::checkpoint`] writes the
+//! whole arena to a snapshot and starts a new, empty log. After a crash,
+//! [`recover`] loads the last snapshot and replays the log on top of it,
+//! producing the exact same indices as before the crash.
+//!
+//! Log records are framed with their length and a checksum. If the process
+//! died in the middle of writing a record, recovery stops at the last complete
+//! record and reports how much of the log is valid, so the torn tail can be
+//! cut off before appending to the log again.
+//!
+//! Values are encoded with a [`ValueCodec`] from the [`binary`]
+//! module. Logs and snapshots can be any [`Write`], but nothing is synced to
+//! disk by this module; call [`File::sync_data`][std::fs::File::sync_data] on
+//! files as needed.
+//!
+//! ```rust
+//! use std::convert::TryFrom;
+//! use thunderdome::Arena;
+//! use thunderdome::binary::{self, DecodeError, Reader, ValueCodec, Writer};
+//! use thunderdome::journal::{self, Journal};
+//!
+//! struct U32Codec;
+//!
+//! impl ValueCodec<u32> for U32Codec {
+//!     fn encode<W: Writer + ?Sized>(&mut self, value: &u32, writer: &mut W) {
+//!         binary::write_varint(writer, u64::from(*value));
+//!     }
+//!
+//!     fn decode<R: Reader + ?Sized>(&mut self, reader: &mut R) -> Result<u32, DecodeError> {
+//!         let value = binary::read_varint(reader)?;
+//!         u32::try_from(value).map_err(|_| DecodeError::InvalidValue)
+//!     }
+//! }
+//!
+//! # fn main() -> std::io::Result<()> {
+//! let mut snapshot = Vec::new();
+//! let mut journal = Journal::create(Arena::new(), U32Codec, &mut snapshot, Vec::new())?;
+//!
+//! let a = journal.insert(1)?;
+//! let b = journal.insert(2)?;
+//! journal.remove(a)?;
+//! journal.replace(b, 3)?;
+//!
+//! let (arena, _, log) = journal.into_parts();
+//!
+//! let recovery = journal::recover(snapshot.as_slice(), log.as_slice(), &mut U32Codec, 1024)?;
+//! assert_eq!(recovery.arena.to_entries(), arena.to_entries());
+//! assert_eq!(recovery.replayed, 4);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+
+use crate::arena::{Arena, Index};
+use crate::binary::{self, decode_arena, encode_arena, DecodeError, ValueCodec};
+use crate::generation::Generation;
+
+const LOG_MAGIC: [u8; 4] = *b"TDJL";
+const SNAPSHOT_MAGIC: [u8; 4] = *b"TDSN";
+
+/// Logs start with the magic bytes followed by the checkpoint number.
+const LOG_HEADER_LEN: usize = 12;
+
+/// Records start with the payload length followed by the payload checksum.
+const RECORD_HEADER_LEN: usize = 8;
+
+/// Snapshots start with the magic bytes, the checkpoint number, the payload
+/// length, and the payload checksum.
+const SNAPSHOT_HEADER_LEN: usize = 24;
+
+const TAG_INSERT: u8 = 0;
+const TAG_INSERT_AT: u8 = 1;
+const TAG_REMOVE: u8 = 2;
+const TAG_INVALIDATE: u8 = 3;
+const TAG_REPLACE: u8 = 4;
+const TAG_SET_QUARANTINE: u8 = 5;
+const TAG_ADVANCE_EPOCH: u8 = 6;
+
+/// An [`Arena`] that records every change to a write-ahead log.
+///
+/// Each change is written to the log before it is made. If writing a record
+/// fails, the change is not made and the journal refuses further changes until
+/// the next [`Journal::checkpoint`], because the log may end with a partial
+/// record.
+///
+/// Changes to the quarantine are journaled too, through
+/// [`Journal::set_quarantine`] and [`Journal::advance_epoch`].
+#[derive(Debug)]
+pub struct Journal<T, C, W> {
+    arena: Arena<T>,
+    codec: C,
+    log: W,
+    checkpoint: u64,
+    poisoned: bool,
+}
+
+/// The state recovered from a snapshot and a log by [`recover`].
+#[derive(Debug)]
+pub struct Recovery<T> {
+    /// The recovered arena.
+    pub arena: Arena<T>,
+
+    /// The number of the checkpoint the snapshot was written at.
+    pub checkpoint: u64,
+
+    /// The number of log records that were replayed onto the snapshot.
+    pub replayed: usize,
+
+    /// The number of bytes at the start of the log that are valid and belong
+    /// to the snapshot. The log should be truncated to this length before
+    /// appending to it with [`Journal::resume`].
+    pub valid_len: u64,
+
+    /// True if the log ended with a partial or corrupted record, which was
+    /// discarded.
+    pub torn: bool,
+}
+
+impl<T, C, W> Journal<T, C, W>
+where
+    C: ValueCodec<T>,
+    W: Write,
+{
+    /// Start journaling `arena`, writing its initial state to `snapshot` and
+    /// starting a new log in `log`.
+    pub fn create<S: Write>(
+        arena: Arena<T>,
+        mut codec: C,
+        snapshot: S,
+        mut log: W,
+    ) -> io::Result<Self> {
+        write_snapshot(&arena, &mut codec, 0, snapshot)?;
+        write_log_header(&mut log, 0)?;
+
+        Ok(Journal {
+            arena,
+            codec,
+            log,
+            checkpoint: 0,
+            poisoned: false,
+        })
+    }
+
+    /// Continue journaling an arena returned by [`recover`], appending to
+    /// `log`.
+    ///
+    /// `log` must be positioned at [`Recovery::valid_len`], with everything
+    /// after it truncated. If no part of the old log was valid, a new log is
+    /// started.
+    pub fn resume(recovery: Recovery<T>, codec: C, mut log: W) -> io::Result<Self> {
+        if recovery.valid_len == 0 {
+            write_log_header(&mut log, recovery.checkpoint)?;
+        }
+
+        Ok(Journal {
+            arena: recovery.arena,
+            codec,
+            log,
+            checkpoint: recovery.checkpoint,
+            poisoned: false,
+        })
+    }
+
+    /// The journaled arena.
+    pub fn arena(&self) -> &Arena<T> {
+        &self.arena
+    }
+
+    /// Get an immutable reference to a value inside the arena by [`Index`].
+    pub fn get(&self, index: Index) -> Option<&T> {
+        self.arena.get(index)
+    }
+
+    /// The current log.
+    pub fn log(&self) -> &W {
+        &self.log
+    }
+
+    /// The number of the last checkpoint.
+    pub fn checkpoint_number(&self) -> u64 {
+        self.checkpoint
+    }
+
+    /// Insert a new value into the arena, recording it in the log.
+    pub fn insert(&mut self, value: T) -> io::Result<Index> {
+        let index = self.arena.next_index();
+        self.write_record(TAG_INSERT, &index_fields(index), Some(&value))?;
+
+        Ok(self.arena.insert(value))
+    }
+
+    /// Insert a value at the given index with [`Arena::insert_at`], recording
+    /// it in the log.
+    pub fn insert_at(&mut self, index: Index, value: T) -> io::Result<Option<T>> {
+        self.write_record(TAG_INSERT_AT, &index_fields(index), Some(&value))?;
+
+        Ok(self.arena.insert_at(index, value))
+    }
+
+    /// Remove the value at the given index, recording it in the log. Nothing
+    /// is recorded if the index isn't in the arena.
+    pub fn remove(&mut self, index: Index) -> io::Result<Option<T>> {
+        if !self.arena.contains(index) {
+            return Ok(None);
+        }
+
+        self.write_record(TAG_REMOVE, &index_fields(index), None)?;
+
+        Ok(self.arena.remove(index))
+    }
+
+    /// Give the value at the given index a new generation with
+    /// [`Arena::invalidate`], recording it in the log. Nothing is recorded if
+    /// the index isn't in the arena.
+    pub fn invalidate(&mut self, index: Index) -> io::Result<Option<Index>> {
+        if !self.arena.contains(index) {
+            return Ok(None);
+        }
+
+        self.write_record(TAG_INVALIDATE, &index_fields(index), None)?;
+
+        Ok(self.arena.invalidate(index))
+    }
+
+    /// Replace the value at the given index, recording the new value in the
+    /// log and returning the old one. Nothing is recorded if the index isn't
+    /// in the arena.
+    pub fn replace(&mut self, index: Index, value: T) -> io::Result<Option<T>> {
+        if !self.arena.contains(index) {
+            return Ok(None);
+        }
+
+        self.write_record(TAG_REPLACE, &index_fields(index), Some(&value))?;
+
+        Ok(self
+            .arena
+            .get_mut(index)
+            .map(|slot| core::mem::replace(slot, value)))
+    }
+
+    /// Change the value at the given index with a closure, recording the new
+    /// value in the log. The closure works on a copy of the value, which
+    /// replaces the original once it has been recorded.
+    pub fn update<F, R>(&mut self, index: Index, f: F) -> io::Result<Option<R>>
+    where
+        T: Clone,
+        F: FnOnce(&mut T) -> R,
+    {
+        let mut value = match self.arena.get(index) {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        };
+
+        let result = f(&mut value);
+        self.replace(index, value)?;
+
+        Ok(Some(result))
+    }
+
+    /// Quarantine slots freed from now on for the given number of epochs,
+    /// recording it in the log. See [`Arena::set_quarantine`].
+    pub fn set_quarantine(&mut self, epochs: u32) -> io::Result<()> {
+        self.write_record(TAG_SET_QUARANTINE, &[u64::from(epochs)], None)?;
+        self.arena.set_quarantine(epochs);
+
+        Ok(())
+    }
+
+    /// Advance the arena to its next epoch, recording it in the log. See
+    /// [`Arena::advance_epoch`].
+    pub fn advance_epoch(&mut self) -> io::Result<()> {
+        self.write_record(TAG_ADVANCE_EPOCH, &[], None)?;
+        self.arena.advance_epoch();
+
+        Ok(())
+    }
+
+    /// Flush the log.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.log.flush()
+    }
+
+    /// Write the whole arena to `snapshot`, then switch to the new, empty log
+    /// `log`, returning the old one.
+    ///
+    /// To survive a crash at any point, write the snapshot and the new log to
+    /// temporary files, sync them, and then rename the snapshot over the old
+    /// snapshot before renaming the log over the old log. Recovery ignores a
+    /// log that is older than its snapshot.
+    ///
+    /// A successful checkpoint also lifts the refusal to make changes after a
+    /// failed write.
+    pub fn checkpoint<S: Write>(&mut self, snapshot: S, mut log: W) -> io::Result<W> {
+        self.log.flush()?;
+
+        let checkpoint = self
+            .checkpoint
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Cannot checkpoint a journal more than u64::MAX times"));
+
+        write_snapshot(&self.arena, &mut self.codec, checkpoint, snapshot)?;
+        write_log_header(&mut log, checkpoint)?;
+
+        self.checkpoint = checkpoint;
+        self.poisoned = false;
+
+        Ok(core::mem::replace(&mut self.log, log))
+    }
+
+    /// Stop journaling, returning the arena, the codec, and the log.
+    pub fn into_parts(self) -> (Arena<T>, C, W) {
+        (self.arena, self.codec, self.log)
+    }
+
+    fn write_record(&mut self, tag: u8, fields: &[u64], value: Option<&T>) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other(
+                "journal is refusing changes after a failed write",
+            ));
+        }
+
+        let mut payload = vec![tag];
+        for &field in fields {
+            binary::write_varint(&mut payload, field);
+        }
+        if let Some(value) = value {
+            self.codec.encode(value, &mut payload);
+        }
+
+        let len: u32 = payload
+            .len()
+            .try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record is too large"))?;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN.saturating_add(payload.len()));
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(&checksum(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+
+        self.log.write_all(&record).inspect_err(|_| {
+            self.poisoned = true;
+        })
+    }
+}
+
+/// Load the snapshot written by the last checkpoint and replay the log on top
+/// of it.
+///
+/// A log that ends with a partial or corrupted record is fine; the record is
+/// discarded and [`Recovery::torn`] is set. A log that was started before the
+/// snapshot was written is ignored entirely. Everything else that doesn't make
+/// sense is an error of kind [`io::ErrorKind::InvalidData`].
+///
+/// Like with [`decode_arena`], snapshots and logs that would grow the arena
+/// past `max_slots` slots are rejected instead of allocating them.
+pub fn recover<T, C, S, L>(
+    mut snapshot: S,
+    mut log: L,
+    codec: &mut C,
+    max_slots: u32,
+) -> io::Result<Recovery<T>>
+where
+    C: ValueCodec<T> + ?Sized,
+    S: Read,
+    L: Read,
+{
+    let mut bytes = Vec::new();
+    snapshot.read_to_end(&mut bytes)?;
+    let (checkpoint, mut arena) = read_snapshot(&bytes, codec, max_slots)?;
+
+    bytes.clear();
+    log.read_to_end(&mut bytes)?;
+
+    let mut recovery = Recovery {
+        arena: Arena::new(),
+        checkpoint,
+        replayed: 0,
+        valid_len: 0,
+        torn: false,
+    };
+
+    if bytes.len() < LOG_HEADER_LEN {
+        // The crash happened while the log was being started.
+        recovery.torn = !bytes.is_empty();
+        recovery.arena = arena;
+        return Ok(recovery);
+    }
+
+    if bytes[..4] != LOG_MAGIC {
+        return Err(invalid_data("log has the wrong magic bytes"));
+    }
+
+    let log_checkpoint = u64::from_le_bytes(array(&bytes[4..LOG_HEADER_LEN]));
+    if log_checkpoint < checkpoint {
+        // The crash happened after the snapshot was replaced but before the
+        // log was.
+        recovery.arena = arena;
+        return Ok(recovery);
+    } else if log_checkpoint > checkpoint {
+        return Err(invalid_data("log is newer than the snapshot"));
+    }
+
+    let mut rest = &bytes[LOG_HEADER_LEN..];
+    let mut valid_len = LOG_HEADER_LEN;
+
+    while !rest.is_empty() {
+        let payload = match read_record(rest) {
+            Some(payload) => payload,
+            None => {
+                recovery.torn = true;
+                break;
+            }
+        };
+
+        replay(&mut arena, codec, payload, max_slots)?;
+
+        let record_len = RECORD_HEADER_LEN.saturating_add(payload.len());
+        rest = &rest[record_len..];
+        valid_len = valid_len.saturating_add(record_len);
+        recovery.replayed = recovery.replayed.saturating_add(1);
+    }
+
+    recovery.arena = arena;
+    recovery.valid_len = valid_len as u64;
+    Ok(recovery)
+}
+
+/// Returns the payload of the record at the start of `bytes`, or `None` if the
+/// record is incomplete or its checksum doesn't match.
+fn read_record(bytes: &[u8]) -> Option<&[u8]> {
+    let header = bytes.get(..RECORD_HEADER_LEN)?;
+    let len = u32::from_le_bytes(array(&header[..4])) as usize;
+    let expected = u32::from_le_bytes(array(&header[4..]));
+
+    let payload = bytes.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN.checked_add(len)?)?;
+
+    (checksum(payload) == expected).then_some(payload)
+}
+
+fn replay<T, C>(
+    arena: &mut Arena<T>,
+    codec: &mut C,
+    mut payload: &[u8],
+    max_slots: u32,
+) -> io::Result<()>
+where
+    C: ValueCodec<T> + ?Sized,
+{
+    let mismatch = || invalid_data("log record doesn't match the arena");
+
+    let (&tag, rest) = payload.split_first().ok_or_else(mismatch)?;
+    payload = rest;
+
+    let applied = match tag {
+        TAG_SET_QUARANTINE => {
+            let epochs = binary::read_varint(&mut payload)
+                .ok()
+                .and_then(|epochs| epochs.try_into().ok())
+                .ok_or_else(mismatch)?;
+            arena.set_quarantine(epochs);
+            true
+        }
+        TAG_ADVANCE_EPOCH => {
+            arena.advance_epoch();
+            true
+        }
+        _ => {
+            let index = read_index(&mut payload).ok_or_else(mismatch)?;
+            let mut value = || codec.decode(&mut payload).map_err(invalid_data);
+
+            match tag {
+                TAG_INSERT => {
+                    let value = value()?;
+                    arena.insert(value) == index
+                }
+                TAG_INSERT_AT => {
+                    if index.slot >= max_slots {
+                        return Err(invalid_data(DecodeError::TooManySlots));
+                    }
+                    let value = value()?;
+                    arena.insert_at(index, value);
+                    true
+                }
+                TAG_REMOVE => arena.remove(index).is_some(),
+                TAG_INVALIDATE => arena.invalidate(index).is_some(),
+                TAG_REPLACE => {
+                    let value = value()?;
+                    arena.get_mut(index).map(|slot| *slot = value).is_some()
+                }
+                _ => false,
+            }
+        }
+    };
+
+    if !applied || !payload.is_empty() {
+        return Err(mismatch());
+    }
+
+    Ok(())
+}
+
+/// The fields of a record that refers to an index.
+fn index_fields(index: Index) -> [u64; 2] {
+    [u64::from(index.slot), u64::from(index.generation.to_u32())]
+}
+
+/// Read the index written by `index_fields`.
+fn read_index(payload: &mut &[u8]) -> Option<Index> {
+    let slot = binary::read_varint(payload).ok()?.try_into().ok()?;
+    let generation = binary::read_varint(payload).ok()?.try_into().ok()?;
+
+    Some(Index {
+        slot,
+        generation: Generation::from_u32(generation)?,
+    })
+}
+
+fn write_log_header<W: Write + ?Sized>(log: &mut W, checkpoint: u64) -> io::Result<()> {
+    let mut header = [0; LOG_HEADER_LEN];
+    header[..4].copy_from_slice(&LOG_MAGIC);
+    header[4..].copy_from_slice(&checkpoint.to_le_bytes());
+
+    log.write_all(&header)?;
+    log.flush()
+}
+
+fn write_snapshot<T, C, S>(
+    arena: &Arena<T>,
+    codec: &mut C,
+    checkpoint: u64,
+    mut snapshot: S,
+) -> io::Result<()>
+where
+    C: ValueCodec<T> + ?Sized,
+    S: Write,
+{
+    let mut payload = Vec::new();
+    encode_arena(arena, codec, &mut payload);
+
+    let mut header = [0; SNAPSHOT_HEADER_LEN];
+    header[..4].copy_from_slice(&SNAPSHOT_MAGIC);
+    header[4..12].copy_from_slice(&checkpoint.to_le_bytes());
+    header[12..20].copy_from_slice(&(payload.len() as u64).to_le_bytes());
+    header[20..].copy_from_slice(&checksum(&payload).to_le_bytes());
+
+    snapshot.write_all(&header)?;
+    snapshot.write_all(&payload)?;
+    snapshot.flush()
+}
+
+fn read_snapshot<T, C>(bytes: &[u8], codec: &mut C, max_slots: u32) -> io::Result<(u64, Arena<T>)>
+where
+    C: ValueCodec<T> + ?Sized,
+{
+    let corrupted = || invalid_data("snapshot is truncated or corrupted");
+
+    let header = bytes.get(..SNAPSHOT_HEADER_LEN).ok_or_else(corrupted)?;
+    if header[..4] != SNAPSHOT_MAGIC {
+        return Err(invalid_data("snapshot has the wrong magic bytes"));
+    }
+
+    let checkpoint = u64::from_le_bytes(array(&header[4..12]));
+    let len = u64::from_le_bytes(array(&header[12..20]));
+    let expected = u32::from_le_bytes(array(&header[20..]));
+
+    let mut payload = &bytes[SNAPSHOT_HEADER_LEN..];
+    if payload.len() as u64 != len || checksum(payload) != expected {
+        return Err(corrupted());
+    }
+
+    let arena = decode_arena(&mut payload, codec, max_slots).map_err(invalid_data)?;
+    if !payload.is_empty() {
+        return Err(corrupted());
+    }
+
+    Ok((checkpoint, arena))
+}
+
+/// The 32-bit FNV-1a hash of `bytes`.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("slice has the wrong length"))
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{recover, Journal, Recovery};
+
+    use std::convert::TryFrom;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, Write};
+    use std::path::PathBuf;
+
+    use crate::binary::{self, DecodeError, Reader, ValueCodec, Writer};
+    use crate::test_util::assert_same_state;
+    use crate::{Arena, Index, ReusePolicy};
+
+    const MAX_SLOTS: u32 = 1024;
+
+    struct U32Codec;
+
+    impl ValueCodec<u32> for U32Codec {
+        fn encode<W: Writer + ?Sized>(&mut self, value: &u32, writer: &mut W) {
+            binary::write_varint(writer, u64::from(*value));
+        }
+
+        fn decode<R: Reader + ?Sized>(&mut self, reader: &mut R) -> Result<u32, DecodeError> {
+            let value = binary::read_varint(reader)?;
+            u32::try_from(value).map_err(|_| DecodeError::InvalidValue)
+        }
+    }
+
+    fn recover_bytes(snapshot: &[u8], log: &[u8]) -> io::Result<Recovery<u32>> {
+        recover(snapshot, log, &mut U32Codec, MAX_SLOTS)
+    }
+
+    /// Make a mix of changes, returning the arena's state after each one.
+    fn run<W: Write>(journal: &mut Journal<u32, U32Codec, W>) -> Vec<Arena<u32>> {
+        let mut states = vec![journal.arena().clone()];
+        let mut live: Vec<Index> = Vec::new();
+
+        for step in 0..40u32 {
+            if step == 20 {
+                journal.set_quarantine(1).unwrap();
+                states.push(journal.arena().clone());
+            }
+
+            match step % 7 {
+                0..=2 => live.push(journal.insert(step).unwrap()),
+                3 => {
+                    let index = live.remove(0);
+                    journal.remove(index).unwrap();
+                }
+                4 => {
+                    let index = live[0];
+                    live[0] = journal.invalidate(index).unwrap().unwrap();
+                }
+                5 => journal.advance_epoch().unwrap(),
+                _ => {
+                    journal
+                        .update(live[0], |value| *value = value.wrapping_mul(3))
+                        .unwrap();
+                }
+            }
+
+            states.push(journal.arena().clone());
+        }
+
+        states
+    }
+
+    #[test]
+    fn replay_produces_same_state() {
+        let mut snapshot = Vec::new();
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        arena.set_quarantine(2);
+        let mut journal = Journal::create(arena, U32Codec, &mut snapshot, Vec::new()).unwrap();
+
+        let states = run(&mut journal);
+        assert!(states.last().unwrap().quarantined_len() > 0);
+        let (arena, _, log) = journal.into_parts();
+
+        let mut recovery = recover_bytes(&snapshot, &log).unwrap();
+        assert_eq!(recovery.replayed, states.len() - 1);
+        assert_eq!(recovery.valid_len, log.len() as u64);
+        assert!(!recovery.torn);
+        assert_same_state(&recovery.arena, states.last().unwrap());
+
+        let mut arena = arena;
+        assert_eq!(recovery.arena.insert(0), arena.insert(0));
+    }
+
+    #[test]
+    fn truncated_log() {
+        let mut snapshot = Vec::new();
+        let mut journal =
+            Journal::create(Arena::new(), U32Codec, &mut snapshot, Vec::new()).unwrap();
+
+        let states = run(&mut journal);
+        let log = journal.into_parts().2;
+
+        for len in 0..=log.len() {
+            let recovery = recover_bytes(&snapshot, &log[..len]).unwrap();
+            assert_same_state(&recovery.arena, &states[recovery.replayed]);
+            assert!(recovery.valid_len <= len as u64);
+            assert_eq!(recovery.torn, recovery.valid_len != len as u64);
+        }
+    }
+
+    #[test]
+    fn corrupted_tail_is_discarded() {
+        let mut snapshot = Vec::new();
+        let mut journal =
+            Journal::create(Arena::new(), U32Codec, &mut snapshot, Vec::new()).unwrap();
+
+        let states = run(&mut journal);
+        let mut log = journal.into_parts().2;
+        let last = log.len() - 1;
+        log[last] ^= 0xff;
+
+        let recovery = recover_bytes(&snapshot, &log).unwrap();
+        assert!(recovery.torn);
+        assert_eq!(recovery.replayed, states.len() - 2);
+        assert_same_state(&recovery.arena, &states[states.len() - 2]);
+
+        snapshot[24] ^= 0xff;
+        let err = recover_bytes(&snapshot, &log).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn too_many_slots() {
+        let mut arena = Arena::new();
+        arena.insert_at(Index::from_bits((1 << 32) | 4096).unwrap(), 1);
+
+        let mut snapshot = Vec::new();
+        let journal = Journal::create(arena, U32Codec, &mut snapshot, Vec::new()).unwrap();
+        let log = journal.into_parts().2;
+        let err = recover_bytes(&snapshot, &log).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut snapshot = Vec::new();
+        let mut journal =
+            Journal::create(Arena::new(), U32Codec, &mut snapshot, Vec::new()).unwrap();
+        journal
+            .insert_at(Index::from_bits((1 << 32) | 4096).unwrap(), 1)
+            .unwrap();
+        let log = journal.into_parts().2;
+        let err = recover_bytes(&snapshot, &log).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// A writer that accepts a limited number of bytes, then fails.
+    struct FailingWriter {
+        written: Vec<u8>,
+        budget: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::Error::other("disk full"));
+            }
+
+            let len = buf.len().min(self.budget).min(3);
+            self.written.extend_from_slice(&buf[..len]);
+            self.budget = self.budget.saturating_sub(len);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_write_refuses_changes() {
+        let mut snapshot = Vec::new();
+        let log = FailingWriter {
+            written: Vec::new(),
+            budget: 40,
+        };
+        let mut journal = Journal::create(Arena::new(), U32Codec, &mut snapshot, log).unwrap();
+
+        let mut inserted = 0;
+        while journal.insert(inserted).is_ok() {
+            inserted += 1;
+        }
+
+        // The change that failed to be written was not made.
+        assert_eq!(journal.arena().len(), inserted as usize);
+        assert!(journal.insert(0).is_err());
+
+        let recovery = recover_bytes(&snapshot, &journal.log().written).unwrap();
+        assert!(recovery.torn);
+        assert_same_state(&recovery.arena, journal.arena());
+
+        let mut snapshot = Vec::new();
+        let log = FailingWriter {
+            written: Vec::new(),
+            budget: usize::MAX,
+        };
+        journal.checkpoint(&mut snapshot, log).unwrap();
+        journal.insert(100).unwrap();
+
+        let recovery = recover_bytes(&snapshot, &journal.log().written).unwrap();
+        assert_same_state(&recovery.arena, journal.arena());
+    }
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "thunderdome-journal-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn files_with_checkpoints() {
+        let dir = TempDir::new("files");
+        let snapshot_path = dir.0.join("arena.snapshot");
+        let log_path = dir.0.join("arena.log");
+
+        let snapshot = File::create(&snapshot_path).unwrap();
+        let log = File::create(&log_path).unwrap();
+        let mut journal = Journal::create(Arena::new(), U32Codec, snapshot, log).unwrap();
+
+        let a = journal.insert(1).unwrap();
+        journal.insert(2).unwrap();
+
+        // Checkpoint through temporary files, crashing after the snapshot has
+        // been replaced but before the log has.
+        let snapshot_tmp = dir.0.join("arena.snapshot.tmp");
+        let log_tmp = dir.0.join("arena.log.tmp");
+        let snapshot = File::create(&snapshot_tmp).unwrap();
+        let log = File::create(&log_tmp).unwrap();
+        journal.checkpoint(snapshot, log).unwrap();
+        fs::rename(&snapshot_tmp, &snapshot_path).unwrap();
+
+        let recovery = recover(
+            File::open(&snapshot_path).unwrap(),
+            File::open(&log_path).unwrap(),
+            &mut U32Codec,
+            MAX_SLOTS,
+        )
+        .unwrap();
+        assert_eq!(recovery.checkpoint, 1);
+        assert_eq!(recovery.replayed, 0);
+        assert_eq!(recovery.valid_len, 0);
+        assert_same_state(&recovery.arena, journal.arena());
+
+        fs::rename(&log_tmp, &log_path).unwrap();
+        journal.remove(a).unwrap();
+        journal.insert(3).unwrap();
+        journal.flush().unwrap();
+        let expected = journal.arena().clone();
+        drop(journal);
+
+        // Simulate a torn write by cutting the last record short.
+        let len = fs::metadata(&log_path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&log_path)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+
+        let recovery = recover(
+            File::open(&snapshot_path).unwrap(),
+            File::open(&log_path).unwrap(),
+            &mut U32Codec,
+            MAX_SLOTS,
+        )
+        .unwrap();
+        assert!(recovery.torn);
+        assert_eq!(recovery.replayed, 1);
+
+        // Cut off the torn record and keep going.
+        let log = OpenOptions::new().write(true).open(&log_path).unwrap();
+        log.set_len(recovery.valid_len).unwrap();
+        let mut log = log;
+        io::Seek::seek(&mut log, io::SeekFrom::End(0)).unwrap();
+
+        let mut journal = Journal::resume(recovery, U32Codec, log).unwrap();
+        journal.insert(3).unwrap();
+        assert_same_state(journal.arena(), &expected);
+        drop(journal);
+
+        let recovery = recover(
+            File::open(&snapshot_path).unwrap(),
+            File::open(&log_path).unwrap(),
+            &mut U32Codec,
+            MAX_SLOTS,
+        )
+        .unwrap();
+        assert!(!recovery.torn);
+        assert_same_state(&recovery.arena, &expected);
+    }
+}
@@ -67,6 +67,8 @@ will only require minor version bumps, but will need significant justification.
 
 # Crate Features
 * `std` (default): Use the standard library. Disable to make this crate `no-std` compatible.
+* `binary`: Enable the `binary` module, a compact binary encoding for arenas.
+* `journal`: Enable the `journal` module, a write-ahead log for persisting arenas. Implies `std` and `binary`.
 */
 
 #![forbid(missing_docs)]
@@ -83,11 +85,51 @@ will only require minor version bumps, but will need significant justification.
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
+mod allocator;
 mod arena;
+#[cfg(feature = "binary")]
+pub mod binary;
+mod command;
+pub mod cow;
+mod cursor;
+mod delta;
 pub mod entry;
 mod free_pointer;
 mod generation;
+mod hash;
 pub mod iter;
-
+#[cfg(feature = "journal")]
+pub mod journal;
+pub mod observer;
+pub mod partition;
+mod policy;
+mod quarantine;
+mod raw;
+mod remap;
+mod resume;
+mod rollback;
+mod storage;
+#[cfg(test)]
+mod test_util;
+pub mod tracked;
+mod transaction;
+mod undo;
+
+pub use crate::allocator::IndexAllocator;
 pub use crate::arena::{Arena, Index};
+pub use crate::command::{Apply, CommandBuffer};
+pub use crate::cow::CowArena;
+pub use crate::cursor::CursorMut;
+pub use crate::delta::{ArenaDelta, DeltaMismatch, SlotChange};
 pub use crate::entry::{Entry, OccupiedEntry, VacantEntry};
+pub use crate::hash::StateHasher;
+pub use crate::observer::{ObservedArena, Observer};
+pub use crate::partition::{PartitionError, PartitionedArena};
+pub use crate::policy::ReusePolicy;
+pub use crate::raw::{FromEntriesError, RawParts, RawPartsError, RawSlot};
+pub use crate::remap::{IndexRemap, RemapIndices};
+pub use crate::resume::IterToken;
+pub use crate::rollback::{RollbackArena, UnknownFrame};
+pub use crate::tracked::TrackedArena;
+pub use crate::transaction::Transaction;
+pub use crate::undo::UndoArena;
@@ -0,0 +1,463 @@
+//! Hooks that are told about values being added to and removed from an arena.
+
+use core::iter::FusedIterator;
+use core::ops;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::arena::{Arena, Index};
+use crate::iter::{self, IterMut, ValuesMut};
+
+/// Receives a call for every value added to or removed from an
+/// [`ObservedArena`], for keeping lookup tables and spatial indices in sync
+/// with an arena.
+///
+/// Every method does nothing by default. `()` is the observer that ignores
+/// everything, which costs nothing at runtime. Observers can be combined with
+/// tuples and `Vec`s, and `Box<dyn Observer<T>>` can be used to pick observers
+/// at runtime.
+pub trait Observer<T> {
+    /// Called after a value is inserted at the given index.
+    fn inserted(&mut self, index: Index, value: &T) {
+        let _ = (index, value);
+    }
+
+    /// Called after a value is removed from the given index. Replacing a value
+    /// with [`ObservedArena::insert_at`] or [`ObservedArena::insert_at_slot`]
+    /// removes the old value before inserting the new one.
+    fn removed(&mut self, index: Index, value: &T) {
+        let _ = (index, value);
+    }
+
+    /// Called after the value at `old` is given the new index `new` by
+    /// [`ObservedArena::invalidate`].
+    fn invalidated(&mut self, old: Index, new: Index, value: &T) {
+        let _ = (old, new, value);
+    }
+}
+
+impl<T> Observer<T> for () {}
+
+impl<T, O: Observer<T> + ?Sized> Observer<T> for &mut O {
+    fn inserted(&mut self, index: Index, value: &T) {
+        (**self).inserted(index, value);
+    }
+
+    fn removed(&mut self, index: Index, value: &T) {
+        (**self).removed(index, value);
+    }
+
+    fn invalidated(&mut self, old: Index, new: Index, value: &T) {
+        (**self).invalidated(old, new, value);
+    }
+}
+
+impl<T, O: Observer<T> + ?Sized> Observer<T> for Box<O> {
+    fn inserted(&mut self, index: Index, value: &T) {
+        (**self).inserted(index, value);
+    }
+
+    fn removed(&mut self, index: Index, value: &T) {
+        (**self).removed(index, value);
+    }
+
+    fn invalidated(&mut self, old: Index, new: Index, value: &T) {
+        (**self).invalidated(old, new, value);
+    }
+}
+
+impl<T, O: Observer<T>> Observer<T> for Vec<O> {
+    fn inserted(&mut self, index: Index, value: &T) {
+        for observer in self {
+            observer.inserted(index, value);
+        }
+    }
+
+    fn removed(&mut self, index: Index, value: &T) {
+        for observer in self {
+            observer.removed(index, value);
+        }
+    }
+
+    fn invalidated(&mut self, old: Index, new: Index, value: &T) {
+        for observer in self {
+            observer.invalidated(old, new, value);
+        }
+    }
+}
+
+impl<T, A: Observer<T>, B: Observer<T>> Observer<T> for (A, B) {
+    fn inserted(&mut self, index: Index, value: &T) {
+        self.0.inserted(index, value);
+        self.1.inserted(index, value);
+    }
+
+    fn removed(&mut self, index: Index, value: &T) {
+        self.0.removed(index, value);
+        self.1.removed(index, value);
+    }
+
+    fn invalidated(&mut self, old: Index, new: Index, value: &T) {
+        self.0.invalidated(old, new, value);
+        self.1.invalidated(old, new, value);
+    }
+}
+
+/// An [`Arena`] that tells an [`Observer`] about every value that is added or
+/// removed.
+///
+/// Every way of adding or removing values goes through the observer: inserting,
+/// replacing, removing, invalidating, [`ObservedArena::retain`],
+/// [`ObservedArena::drain`], and [`ObservedArena::clear`]. Values can be
+/// changed in place without telling the observer.
+///
+/// The arena can be read through the `ObservedArena`, which dereferences to
+/// [`Arena`].
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use thunderdome::{Index, ObservedArena, Observer};
+/// #[derive(Default)]
+/// struct ByName(HashMap<String, Index>);
+///
+/// impl Observer<String> for ByName {
+///     fn inserted(&mut self, index: Index, name: &String) {
+///         self.0.insert(name.clone(), index);
+///     }
+///
+///     fn removed(&mut self, _index: Index, name: &String) {
+///         self.0.remove(name);
+///     }
+///
+///     fn invalidated(&mut self, _old: Index, new: Index, name: &String) {
+///         self.0.insert(name.clone(), new);
+///     }
+/// }
+///
+/// let mut arena = ObservedArena::with_observer(ByName::default());
+/// let alice = arena.insert("alice".to_owned());
+/// arena.insert("bob".to_owned());
+/// arena.retain(|_, name| name != "bob");
+///
+/// assert_eq!(arena.observer().0.get("alice"), Some(&alice));
+/// assert_eq!(arena.observer().0.get("bob"), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObservedArena<T, O = ()> {
+    arena: Arena<T>,
+    observer: O,
+}
+
+impl<T> ObservedArena<T> {
+    /// Construct an empty arena with no observer.
+    pub fn new() -> Self {
+        Self::with_observer(())
+    }
+}
+
+impl<T, O: Observer<T>> ObservedArena<T, O> {
+    /// Construct an empty arena with the given observer.
+    pub fn with_observer(observer: O) -> Self {
+        ObservedArena {
+            arena: Arena::new(),
+            observer,
+        }
+    }
+
+    /// Start observing an existing arena. The observer is not told about
+    /// values that are already in the arena.
+    pub fn from_arena(arena: Arena<T>, observer: O) -> Self {
+        ObservedArena { arena, observer }
+    }
+
+    /// Stop observing the arena, returning it along with the observer.
+    pub fn into_parts(self) -> (Arena<T>, O) {
+        (self.arena, self.observer)
+    }
+
+    /// The arena's observer.
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// The arena's observer, mutably.
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Insert a new value. See [`Arena::insert`].
+    pub fn insert(&mut self, value: T) -> Index {
+        let index = self.arena.insert(value);
+        self.observer.inserted(index, &self.arena[index]);
+        index
+    }
+
+    /// Insert a value at the given index. See [`Arena::insert_at`].
+    pub fn insert_at(&mut self, index: Index, value: T) -> Option<T> {
+        let old_index = self.arena.contains_slot(index.slot);
+        let old_value = self.arena.insert_at(index, value);
+        self.replaced(old_index, old_value.as_ref(), index);
+        old_value
+    }
+
+    /// Insert a value at the given slot. See [`Arena::insert_at_slot`].
+    pub fn insert_at_slot(&mut self, slot: u32, value: T) -> (Index, Option<T>) {
+        let old_index = self.arena.contains_slot(slot);
+        let (index, old_value) = self.arena.insert_at_slot(slot, value);
+        self.replaced(old_index, old_value.as_ref(), index);
+        (index, old_value)
+    }
+
+    /// Remove a value. See [`Arena::remove`].
+    pub fn remove(&mut self, index: Index) -> Option<T> {
+        let value = self.arena.remove(index)?;
+        self.observer.removed(index, &value);
+        Some(value)
+    }
+
+    /// Remove the value in a slot. See [`Arena::remove_by_slot`].
+    pub fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
+        let (index, value) = self.arena.remove_by_slot(slot)?;
+        self.observer.removed(index, &value);
+        Some((index, value))
+    }
+
+    /// Give a value a new index. See [`Arena::invalidate`].
+    pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        let new_index = self.arena.invalidate(index)?;
+        self.observer
+            .invalidated(index, new_index, &self.arena[new_index]);
+        Some(new_index)
+    }
+
+    /// Remove the values for which the given function returns false. See
+    /// [`Arena::retain`].
+    pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
+        let observer = &mut self.observer;
+
+        self.arena.retain(|index, value| {
+            let keep = f(index, value);
+            if !keep {
+                observer.removed(index, value);
+            }
+            keep
+        });
+    }
+
+    /// Returns an iterator that removes each value from the arena, telling the
+    /// observer as each value is removed. See [`Arena::drain`].
+    pub fn drain(&mut self) -> Drain<'_, T, O> {
+        Drain {
+            inner: self.arena.drain(),
+            observer: &mut self.observer,
+        }
+    }
+
+    /// Remove every value. See [`Arena::clear`].
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    /// Get a mutable reference to a value. See [`Arena::get_mut`].
+    pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        self.arena.get_mut(index)
+    }
+
+    /// Get mutable references to two values. See [`Arena::get2_mut`].
+    pub fn get2_mut(&mut self, index1: Index, index2: Index) -> (Option<&mut T>, Option<&mut T>) {
+        self.arena.get2_mut(index1, index2)
+    }
+
+    /// Get a mutable reference to the value in a slot. See
+    /// [`Arena::get_by_slot_mut`].
+    pub fn get_by_slot_mut(&mut self, slot: u32) -> Option<(Index, &mut T)> {
+        self.arena.get_by_slot_mut(slot)
+    }
+
+    /// Iterate over the arena with mutable access to each value. See
+    /// [`Arena::iter_mut`].
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.arena.iter_mut()
+    }
+
+    /// Iterate over the values with mutable access to each one. See
+    /// [`Arena::values_mut`].
+    pub fn values_mut(&mut self) -> ValuesMut<'_, T> {
+        self.arena.values_mut()
+    }
+
+    /// Tell the observer about an insertion into a slot that held `old_index`
+    /// and `old_value` before, if it was occupied.
+    fn replaced(&mut self, old_index: Option<Index>, old_value: Option<&T>, index: Index) {
+        if let (Some(old_index), Some(old_value)) = (old_index, old_value) {
+            self.observer.removed(old_index, old_value);
+        }
+
+        self.observer.inserted(index, &self.arena[index]);
+    }
+}
+
+impl<T> Default for ObservedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, O> ops::Deref for ObservedArena<T, O> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        &self.arena
+    }
+}
+
+impl<T, O> ops::Index<Index> for ObservedArena<T, O> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        &self.arena[index]
+    }
+}
+
+impl<T, O> ops::IndexMut<Index> for ObservedArena<T, O> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        &mut self.arena[index]
+    }
+}
+
+/// See [`ObservedArena::drain`].
+#[derive(Debug)]
+pub struct Drain<'a, T, O: Observer<T>> {
+    inner: iter::Drain<'a, T>,
+    observer: &'a mut O,
+}
+
+impl<T, O: Observer<T>> Iterator for Drain<'_, T, O> {
+    type Item = (Index, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, value) = self.inner.next()?;
+        self.observer.removed(index, &value);
+        Some((index, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, O: Observer<T>> FusedIterator for Drain<'_, T, O> {}
+impl<T, O: Observer<T>> ExactSizeIterator for Drain<'_, T, O> {}
+
+impl<T, O: Observer<T>> Drop for Drain<'_, T, O> {
+    // Tell the observer about any values that weren't iterated.
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{ObservedArena, Observer};
+    use crate::{Arena, Index};
+
+    #[derive(Debug, Default)]
+    struct Log(Vec<String>);
+
+    impl Observer<u32> for Log {
+        fn inserted(&mut self, index: Index, value: &u32) {
+            self.0.push(format!(
+                "+{}@{} {}",
+                index.slot(),
+                index.generation(),
+                value
+            ));
+        }
+
+        fn removed(&mut self, index: Index, value: &u32) {
+            self.0.push(format!(
+                "-{}@{} {}",
+                index.slot(),
+                index.generation(),
+                value
+            ));
+        }
+
+        fn invalidated(&mut self, old: Index, new: Index, value: &u32) {
+            self.0.push(format!(
+                "~{}@{}->{} {}",
+                old.slot(),
+                old.generation(),
+                new.generation(),
+                value
+            ));
+        }
+    }
+
+    #[test]
+    fn every_change_is_observed() {
+        let mut arena = ObservedArena::with_observer(Log::default());
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        arena.insert_at(a, 10);
+        arena.insert_at_slot(3, 3);
+        let b = arena.invalidate(b).unwrap();
+        arena.remove(a);
+        arena.remove_by_slot(3);
+        arena.insert(4);
+        arena[b] = 20;
+
+        assert_eq!(
+            arena.observer().0,
+            [
+                "+0@1 1",
+                "+1@1 2",
+                "-0@1 1",
+                "+0@1 10",
+                "+3@1 3",
+                "~1@1->2 2",
+                "-0@1 10",
+                "-3@1 3",
+                "+3@2 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn bulk_removals_are_observed() {
+        let mut arena = ObservedArena::from_arena(Arena::new(), Log::default());
+        for i in 0..6 {
+            arena.insert(i);
+        }
+        arena.observer_mut().0.clear();
+
+        arena.retain(|_, value| *value % 2 == 0);
+        assert_eq!(arena.observer().0, ["-1@1 1", "-3@1 3", "-5@1 5"]);
+        arena.observer_mut().0.clear();
+
+        // Dropping a drain early still reports every removal.
+        let mut drain = arena.drain();
+        drain.next();
+        drop(drain);
+        assert_eq!(arena.observer().0, ["-0@1 0", "-2@1 2", "-4@1 4"]);
+        assert!(arena.is_empty());
+
+        arena.insert(7);
+        arena.clear();
+        let (_, log) = arena.into_parts();
+        assert_eq!(log.0.last().unwrap(), "-4@2 7");
+    }
+
+    #[test]
+    fn combined_observers() {
+        let mut first = Log::default();
+        let observers: (Box<dyn Observer<u32>>, Vec<&mut Log>) = (Box::new(()), vec![&mut first]);
+
+        let mut arena = ObservedArena::with_observer(observers);
+        arena.insert(1);
+        drop(arena);
+
+        assert_eq!(first.0, ["+0@1 1"]);
+    }
+}
@@ -0,0 +1,472 @@
+//! Arenas whose slots are split into ranges owned by different allocators.
+
+use core::fmt;
+use core::ops::{self, Range};
+
+// Vec is part of the prelude when std is enabled.
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::arena::{Arena, Index};
+use crate::iter::{Iter, IterMut};
+
+/// An [`Arena`] whose slots are split into disjoint ranges, called partitions.
+///
+/// Each partition hands out slots only from its own range and keeps its own
+/// free list. This lets several peers, like a server and its clients, create
+/// values independently without their indices ever colliding, and then merge
+/// them into one arena later with [`PartitionedArena::insert_at`] or
+/// [`Arena::insert_at`].
+///
+/// Indices produced by a `PartitionedArena` are ordinary [`Index`] values.
+///
+/// Note that the arena's storage grows up to the highest slot used in any
+/// partition, so partitions far away from slot zero cost memory for every
+/// slot below them.
+#[derive(Debug, Clone)]
+pub struct PartitionedArena<T> {
+    arena: Arena<T>,
+    partitions: Vec<Partition>,
+}
+
+#[derive(Debug, Clone)]
+struct Partition {
+    range: Range<u32>,
+
+    /// Every slot in `next_fresh..range.end` has never been handed out by this
+    /// partition, and doesn't need to be tracked in `free`.
+    next_fresh: u32,
+
+    /// Slots below `next_fresh` that have been freed, reused last in first out.
+    free: Vec<u32>,
+}
+
+/// The error type for operations on a [`PartitionedArena`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionError {
+    /// A partition was given an empty range of slots.
+    EmptyRange {
+        /// The position of the partition in the list of partitions.
+        partition: usize,
+    },
+
+    /// Two partitions were given overlapping ranges of slots.
+    Overlapping {
+        /// The position of the first overlapping partition.
+        first: usize,
+
+        /// The position of the second overlapping partition.
+        second: usize,
+    },
+
+    /// There is no partition with the given position.
+    UnknownPartition {
+        /// The position that was asked for.
+        partition: usize,
+    },
+
+    /// Every slot in the partition is occupied.
+    Exhausted {
+        /// The position of the full partition.
+        partition: usize,
+    },
+
+    /// The slot does not belong to any partition.
+    Unpartitioned {
+        /// The slot that was asked for.
+        slot: u32,
+    },
+}
+
+impl fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionError::EmptyRange { partition } => {
+                write!(f, "partition {} has an empty slot range", partition)
+            }
+            PartitionError::Overlapping { first, second } => {
+                write!(f, "partitions {} and {} overlap", first, second)
+            }
+            PartitionError::UnknownPartition { partition } => {
+                write!(f, "partition {} does not exist", partition)
+            }
+            PartitionError::Exhausted { partition } => {
+                write!(f, "partition {} has no free slots left", partition)
+            }
+            PartitionError::Unpartitioned { slot } => {
+                write!(f, "slot {} does not belong to any partition", slot)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PartitionError {}
+
+impl<T> PartitionedArena<T> {
+    /// Construct an empty arena with one partition per given slot range.
+    /// Partitions are identified by their position in `ranges`.
+    ///
+    /// Returns an error if any range is empty or if any two ranges overlap.
+    pub fn new<I>(ranges: I) -> Result<Self, PartitionError>
+    where
+        I: IntoIterator<Item = Range<u32>>,
+    {
+        let partitions: Vec<Partition> = ranges
+            .into_iter()
+            .map(|range| Partition {
+                next_fresh: range.start,
+                range,
+                free: Vec::new(),
+            })
+            .collect();
+
+        for (i, partition) in partitions.iter().enumerate() {
+            if partition.range.is_empty() {
+                return Err(PartitionError::EmptyRange { partition: i });
+            }
+
+            for (j, other) in partitions.iter().enumerate().skip(i.saturating_add(1)) {
+                if partition.range.start < other.range.end
+                    && other.range.start < partition.range.end
+                {
+                    return Err(PartitionError::Overlapping {
+                        first: i,
+                        second: j,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            arena: Arena::new(),
+            partitions,
+        })
+    }
+
+    /// Return the number of partitions.
+    pub fn partitions(&self) -> usize {
+        self.partitions.len()
+    }
+
+    /// Return the range of slots owned by the given partition.
+    pub fn partition_range(&self, partition: usize) -> Option<Range<u32>> {
+        self.partitions.get(partition).map(|p| p.range.clone())
+    }
+
+    /// Return the partition that owns the given slot, if any.
+    pub fn partition_of(&self, slot: u32) -> Option<usize> {
+        self.partitions
+            .iter()
+            .position(|partition| partition.range.contains(&slot))
+    }
+
+    /// Return the number of elements contained in the arena, across all
+    /// partitions.
+    pub const fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns whether the arena is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    /// Compute the `Index` that the next call to
+    /// [`PartitionedArena::insert_in_partition`] with the given partition
+    /// would produce, without mutating the arena.
+    pub fn next_index_in_partition(&self, partition: usize) -> Result<Index, PartitionError> {
+        let slot = self.next_slot(partition)?;
+        Ok(self.arena.predict_unlinked(slot))
+    }
+
+    /// Insert a new value into the given partition, returning an index that
+    /// can be used to later retrieve the value.
+    ///
+    /// Returns an error, dropping the value, if the partition does not exist
+    /// or has no free slots left.
+    pub fn insert_in_partition(
+        &mut self,
+        partition: usize,
+        value: T,
+    ) -> Result<Index, PartitionError> {
+        let slot = self.next_slot(partition)?;
+
+        let part = &mut self.partitions[partition];
+        if part.free.last() == Some(&slot) {
+            part.free.pop();
+        } else {
+            part.next_fresh = slot
+                .checked_add(1)
+                .unwrap_or_else(|| unreachable!("partition ranges end at or before u32::MAX"));
+        }
+
+        Ok(self.arena.insert_unlinked(slot, None, value))
+    }
+
+    /// Insert a value at a given index, returning the old value if present.
+    /// This is the building block for merging values created by another
+    /// peer's partition.
+    ///
+    /// Returns an error, dropping the value, if the index's slot does not
+    /// belong to any partition.
+    ///
+    /// Like [`Arena::insert_at`], this method is capable of "resurrecting" an
+    /// old `Index`.
+    pub fn insert_at(&mut self, index: Index, value: T) -> Result<Option<T>, PartitionError> {
+        let partition = self
+            .partition_of(index.slot)
+            .ok_or(PartitionError::Unpartitioned { slot: index.slot })?;
+
+        let old_value = match self.arena.contains_slot(index.slot) {
+            Some(old_index) => self.arena.remove_unlinked(old_index),
+            None => {
+                let part = &mut self.partitions[partition];
+                if let Some(position) = part.free.iter().position(|&slot| slot == index.slot) {
+                    part.free.remove(position);
+                }
+
+                None
+            }
+        };
+
+        self.arena
+            .insert_unlinked(index.slot, Some(index.generation), value);
+
+        Ok(old_value)
+    }
+
+    /// Remove the value contained at the given index from the arena, returning
+    /// it if it was present. The slot is returned to its partition's free list.
+    pub fn remove(&mut self, index: Index) -> Option<T> {
+        let value = self.arena.remove_unlinked(index)?;
+
+        if let Some(partition) = self.partition_of(index.slot) {
+            let part = &mut self.partitions[partition];
+
+            // Slots that the partition hasn't reached yet were filled with
+            // `insert_at`. They'll be picked up when the partition gets there.
+            if index.slot < part.next_fresh {
+                part.free.push(index.slot);
+            }
+        }
+
+        Some(value)
+    }
+
+    /// Returns true if the given index is valid for the arena.
+    pub fn contains(&self, index: Index) -> bool {
+        self.arena.contains(index)
+    }
+
+    /// Get an immutable reference to a value inside the arena by [`Index`],
+    /// returning `None` if the index is not contained in the arena.
+    pub fn get(&self, index: Index) -> Option<&T> {
+        self.arena.get(index)
+    }
+
+    /// Get a mutable reference to a value inside the arena by [`Index`],
+    /// returning `None` if the index is not contained in the arena.
+    pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        self.arena.get_mut(index)
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.arena.iter()
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena, with
+    /// mutable access to each value.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.arena.iter_mut()
+    }
+
+    /// Borrow the underlying [`Arena`], which contains the values of every
+    /// partition.
+    ///
+    /// The partitions keep their own free lists, so the arena's free list is
+    /// rebuilt out of every empty slot first, like with
+    /// [`PartitionedArena::into_arena`]. This takes time proportional to the
+    /// number of slots.
+    pub fn as_arena(&mut self) -> &Arena<T> {
+        self.arena.relink_free_list();
+        &self.arena
+    }
+
+    /// Convert this into a regular [`Arena`] holding the same values at the
+    /// same indices. Every empty slot ends up in the arena's free list.
+    pub fn into_arena(self) -> Arena<T> {
+        let mut arena = self.arena;
+        arena.relink_free_list();
+        arena
+    }
+
+    /// Find the slot the next insertion into a partition should use.
+    fn next_slot(&self, partition: usize) -> Result<u32, PartitionError> {
+        let part = self
+            .partitions
+            .get(partition)
+            .ok_or(PartitionError::UnknownPartition { partition })?;
+
+        if let Some(&slot) = part.free.last() {
+            return Ok(slot);
+        }
+
+        // Fresh slots may have been filled by `insert_at` ahead of time.
+        (part.next_fresh..part.range.end)
+            .find(|&slot| self.arena.contains_slot(slot).is_none())
+            .ok_or(PartitionError::Exhausted { partition })
+    }
+}
+
+impl<T> ops::Index<Index> for PartitionedArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        &self.arena[index]
+    }
+}
+
+impl<T> ops::IndexMut<Index> for PartitionedArena<T> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        &mut self.arena[index]
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{PartitionError, PartitionedArena};
+
+    #[test]
+    fn invalid_ranges() {
+        let empty = PartitionedArena::<()>::new(vec![0..4, 4..4]);
+        assert_eq!(
+            empty.unwrap_err(),
+            PartitionError::EmptyRange { partition: 1 }
+        );
+
+        let overlapping = PartitionedArena::<()>::new(vec![0..4, 8..16, 3..6]);
+        assert_eq!(
+            overlapping.unwrap_err(),
+            PartitionError::Overlapping {
+                first: 0,
+                second: 2
+            }
+        );
+    }
+
+    #[test]
+    fn insert_in_partition() {
+        let mut arena = PartitionedArena::new(vec![0..4, 16..20]).unwrap();
+
+        let a = arena.insert_in_partition(0, "a").unwrap();
+        let b = arena.insert_in_partition(1, "b").unwrap();
+        let c = arena.insert_in_partition(1, "c").unwrap();
+
+        assert_eq!(a.slot(), 0);
+        assert_eq!(b.slot(), 16);
+        assert_eq!(c.slot(), 17);
+        assert_eq!(arena.partition_of(c.slot()), Some(1));
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena[b], "b");
+
+        assert_eq!(
+            arena.insert_in_partition(2, "d"),
+            Err(PartitionError::UnknownPartition { partition: 2 })
+        );
+    }
+
+    #[test]
+    fn free_list_per_partition() {
+        let mut arena = PartitionedArena::new(vec![0..4, 4..8]).unwrap();
+        let a = arena.insert_in_partition(0, 1).unwrap();
+        let b = arena.insert_in_partition(1, 2).unwrap();
+
+        arena.remove(a);
+        arena.remove(b);
+
+        let predicted = arena.next_index_in_partition(1).unwrap();
+        let b2 = arena.insert_in_partition(1, 3).unwrap();
+        assert_eq!(predicted, b2);
+        assert_eq!(b2.slot(), b.slot());
+        assert_ne!(b2, b);
+
+        let a2 = arena.insert_in_partition(0, 4).unwrap();
+        assert_eq!(a2.slot(), a.slot());
+        assert!(!arena.contains(a));
+    }
+
+    #[test]
+    fn exhausted() {
+        let mut arena = PartitionedArena::new(vec![0..2, 2..3]).unwrap();
+        arena.insert_in_partition(1, 0).unwrap();
+        assert_eq!(
+            arena.insert_in_partition(1, 1),
+            Err(PartitionError::Exhausted { partition: 1 })
+        );
+
+        // Other partitions are unaffected.
+        arena.insert_in_partition(0, 2).unwrap();
+        arena.insert_in_partition(0, 3).unwrap();
+        assert!(arena.insert_in_partition(0, 4).is_err());
+    }
+
+    #[test]
+    fn merge_peers() {
+        let ranges = vec![0..1024, 1024..2048];
+        let mut server = PartitionedArena::new(ranges.clone()).unwrap();
+        let mut client = PartitionedArena::new(ranges).unwrap();
+
+        let from_server = server.insert_in_partition(0, "server").unwrap();
+        let from_client = client.insert_in_partition(1, "client").unwrap();
+        let also_client = client.insert_in_partition(1, "client 2").unwrap();
+        client.remove(from_client);
+
+        for (index, &value) in client.iter() {
+            server.insert_at(index, value).unwrap();
+        }
+
+        assert_eq!(server[from_server], "server");
+        assert_eq!(server[also_client], "client 2");
+
+        // The server keeps allocating for the client's partition around the
+        // merged value.
+        let next = server.insert_in_partition(1, "server for client").unwrap();
+        assert_eq!(next.slot(), 1024);
+        let next = server.insert_in_partition(1, "server for client").unwrap();
+        assert_eq!(next.slot(), 1026);
+
+        // Merging into a plain `Arena` works too.
+        let merged = server.into_arena();
+        assert_eq!(merged[also_client], "client 2");
+        assert_eq!(merged.len(), 4);
+    }
+
+    #[test]
+    fn as_arena_links_free_slots() {
+        let mut arena = PartitionedArena::new(vec![0..4, 4..8]).unwrap();
+        let a = arena.insert_in_partition(0, 1).unwrap();
+        arena.insert_in_partition(1, 2).unwrap();
+        arena.remove(a);
+
+        let view = arena.as_arena();
+        assert_eq!(view.free_slots().len(), 4);
+        assert_eq!(view.free_slots().count(), 4);
+
+        let mut clone = view.clone();
+        let next = clone.next_index();
+        assert_eq!(clone.insert(3), next);
+        assert_eq!(clone.len(), 2);
+        assert!(next.slot() < 4);
+
+        // The partitions keep working afterwards.
+        let b = arena.insert_in_partition(0, 4).unwrap();
+        assert_eq!(b.slot(), a.slot());
+        assert_eq!(arena.as_arena().free_slots().len(), 3);
+    }
+}
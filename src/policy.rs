@@ -0,0 +1,31 @@
+/// Decides which empty slot an [`Arena`](crate::Arena) fills first when a new
+/// value is inserted.
+///
+/// The policy is fixed when the arena is created with
+/// [`Arena::with_reuse_policy`](crate::Arena::with_reuse_policy). Every
+/// method that picks a slot on its own, like [`Arena::insert`],
+/// [`Arena::next_index`], and [`Arena::vacant_entry`], honors it.
+///
+/// [`Arena::insert`]: crate::Arena::insert
+/// [`Arena::next_index`]: crate::Arena::next_index
+/// [`Arena::vacant_entry`]: crate::Arena::vacant_entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ReusePolicy {
+    /// Reuse the most recently freed slot first. This is the default, and is
+    /// the cheapest policy to maintain.
+    #[default]
+    Lifo,
+
+    /// Reuse the least recently freed slot first. Freed slots sit in the free
+    /// list for as long as possible before being reused, which makes it less
+    /// likely that a stale `Index` is mistaken for a live one.
+    Fifo,
+
+    /// Reuse the free slot with the lowest slot number first. This keeps
+    /// values packed towards the start of the arena's storage, which helps
+    /// iteration and cache locality.
+    ///
+    /// Removing a value costs time proportional to the number of free slots
+    /// with a lower slot number than it.
+    LowestSlot,
+}
@@ -0,0 +1,100 @@
+use core::convert::TryInto;
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::VecDeque;
+
+/// Holds slots that have been freed but must not be reused until enough
+/// epochs have passed.
+///
+/// Slots are kept sorted by release epoch, so they're released in that order.
+/// Slots with the same release epoch are released in the order they were
+/// quarantined.
+#[derive(Debug, Clone)]
+pub(crate) struct Quarantine {
+    /// The number of epochs a freed slot waits before being reused. Zero
+    /// disables quarantine entirely.
+    pub(crate) epochs: u32,
+
+    /// The current epoch, advanced by `Arena::advance_epoch`.
+    pub(crate) epoch: u64,
+
+    /// Quarantined slots, each paired with the epoch it will be released at,
+    /// sorted by release epoch.
+    pub(crate) pending: VecDeque<(u64, u32)>,
+}
+
+impl Quarantine {
+    pub(crate) const fn new() -> Self {
+        Self {
+            epochs: 0,
+            epoch: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> u32 {
+        self.pending
+            .len()
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("Quarantined more slots than can fit in a u32"))
+    }
+
+    /// Quarantine a freed slot. Returns `false` if quarantine is disabled, in
+    /// which case the caller should free the slot immediately.
+    pub(crate) fn push(&mut self, slot: u32) -> bool {
+        if self.epochs == 0 {
+            return false;
+        }
+
+        let release = self
+            .epoch
+            .checked_add(self.epochs.into())
+            .unwrap_or_else(|| panic!("Overflowed u64 computing a quarantine release epoch"));
+
+        // Lowering `epochs` lets a slot be released before ones that were
+        // quarantined earlier, so it can't always go at the back.
+        let position = self
+            .pending
+            .partition_point(|&(pending, _)| pending <= release);
+        self.pending.insert(position, (release, slot));
+        true
+    }
+
+    /// Take a slot out of quarantine early, returning whether it was
+    /// quarantined at all.
+    pub(crate) fn take(&mut self, slot: u32) -> bool {
+        match self
+            .pending
+            .iter()
+            .position(|&(_, pending)| pending == slot)
+        {
+            Some(position) => {
+                self.pending.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move to the next epoch.
+    pub(crate) fn advance(&mut self) {
+        self.epoch = self
+            .epoch
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Overflowed u64 advancing quarantine epoch"));
+    }
+
+    /// Pop the next slot whose quarantine has expired, if any.
+    pub(crate) fn pop_expired(&mut self) -> Option<u32> {
+        match self.pending.front() {
+            Some(&(release, slot)) if release <= self.epoch => {
+                self.pending.pop_front();
+                Some(slot)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,693 @@
+use core::convert::TryInto;
+use core::fmt;
+
+// Vec is part of the prelude when std is enabled.
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::arena::{Arena, EmptySlot, Index, OccupiedSlot, Slot};
+use crate::free_pointer::FreePointer;
+use crate::generation::Generation;
+use crate::policy::ReusePolicy;
+use crate::quarantine::Quarantine;
+use crate::storage::SlotStorage;
+
+/// The most slots [`Arena::from_entries`] creates per entry it is given, which
+/// keeps the memory used by the arena proportional to the input.
+const MAX_SLOTS_PER_ENTRY: usize = 64;
+
+/// The error returned by [`Arena::from_entries`] when the entries don't
+/// describe a valid arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FromEntriesError {
+    /// More than one entry was given for the same slot.
+    DuplicateSlot {
+        /// The slot that more than one entry tried to occupy.
+        slot: u32,
+    },
+
+    /// An entry's slot is too far past the number of entries, so the arena
+    /// would be mostly empty slots.
+    TooSparse {
+        /// The offending slot.
+        slot: u32,
+    },
+}
+
+impl fmt::Display for FromEntriesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromEntriesError::DuplicateSlot { slot } => {
+                write!(f, "more than one entry was given for slot {}", slot)
+            }
+            FromEntriesError::TooSparse { slot } => {
+                write!(f, "slot {} is too far past the number of entries", slot)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FromEntriesError {}
+
+/// The complete state of an [`Arena`], including the generations of empty
+/// slots, the order of the free list, and the quarantine.
+///
+/// Created with [`Arena::into_raw_parts`] and turned back into an arena with
+/// [`Arena::from_raw_parts`]. This is meant for persistence layers that need
+/// to save and restore an arena without resurrecting old indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawParts<T> {
+    /// Every slot of the arena, in slot order.
+    pub slots: Vec<RawSlot<T>>,
+
+    /// The first slot of the free list, which is the slot the next insertion
+    /// will use. `None` if the free list is empty.
+    pub first_free: Option<u32>,
+
+    /// The arena's reuse policy.
+    pub policy: ReusePolicy,
+
+    /// The number of epochs freed slots are quarantined for. See
+    /// [`Arena::set_quarantine`].
+    pub quarantine: u32,
+
+    /// The arena's current epoch, advanced by [`Arena::advance_epoch`].
+    pub epoch: u64,
+
+    /// The empty slots in quarantine, each paired with the epoch it is
+    /// released at, sorted by release epoch. Quarantined slots are not part of
+    /// the free list.
+    pub quarantined: Vec<(u64, u32)>,
+}
+
+/// A single slot of an arena, as exposed by [`RawParts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawSlot<T> {
+    /// The generation of the slot. For an occupied slot, this is the generation
+    /// of its index. For an empty slot, it is the generation of the last value
+    /// stored there, which is bumped when the slot is reused. Must be nonzero.
+    pub generation: u32,
+
+    /// The value stored in the slot, or `None` if the slot is empty.
+    pub value: Option<T>,
+
+    /// For an empty slot, the next slot in the free list, or `None` if this is
+    /// the last free slot. Must be `None` for occupied slots.
+    pub next_free: Option<u32>,
+}
+
+/// The error returned by [`Arena::from_raw_parts`] when the parts don't
+/// describe a valid arena.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawPartsError {
+    /// There are more slots than can be addressed by a `u32`.
+    TooManySlots,
+
+    /// A slot has a generation of zero.
+    ZeroGeneration {
+        /// The offending slot.
+        slot: u32,
+    },
+
+    /// An occupied slot has a `next_free` link.
+    OccupiedLink {
+        /// The offending slot.
+        slot: u32,
+    },
+
+    /// The free list points past the end of the slots.
+    OutOfBounds {
+        /// The slot that was pointed to.
+        slot: u32,
+    },
+
+    /// The free list points to an occupied slot.
+    Occupied {
+        /// The slot that was pointed to.
+        slot: u32,
+    },
+
+    /// The free list visits the same slot twice.
+    Cycle {
+        /// The slot that was visited twice.
+        slot: u32,
+    },
+
+    /// An empty slot is not part of the free list.
+    Unlinked {
+        /// The offending slot.
+        slot: u32,
+    },
+
+    /// The arena uses [`ReusePolicy::LowestSlot`], but the free list is not
+    /// sorted by slot.
+    Unsorted {
+        /// The first slot that is out of order.
+        slot: u32,
+    },
+
+    /// A quarantined slot is out of bounds, occupied, part of the free list,
+    /// or quarantined more than once.
+    InvalidQuarantine {
+        /// The offending slot.
+        slot: u32,
+    },
+
+    /// The quarantined slots are not sorted by release epoch.
+    UnsortedQuarantine {
+        /// The first slot that is out of order.
+        slot: u32,
+    },
+}
+
+impl fmt::Display for RawPartsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RawPartsError::TooManySlots => write!(f, "too many slots for an arena"),
+            RawPartsError::ZeroGeneration { slot } => {
+                write!(f, "slot {} has a generation of zero", slot)
+            }
+            RawPartsError::OccupiedLink { slot } => {
+                write!(f, "occupied slot {} has a free list link", slot)
+            }
+            RawPartsError::OutOfBounds { slot } => {
+                write!(
+                    f,
+                    "free list points to slot {}, which is out of bounds",
+                    slot
+                )
+            }
+            RawPartsError::Occupied { slot } => {
+                write!(f, "free list points to slot {}, which is occupied", slot)
+            }
+            RawPartsError::Cycle { slot } => {
+                write!(f, "free list visits slot {} more than once", slot)
+            }
+            RawPartsError::Unlinked { slot } => {
+                write!(f, "empty slot {} is not in the free list", slot)
+            }
+            RawPartsError::Unsorted { slot } => {
+                write!(f, "free list is not sorted at slot {}", slot)
+            }
+            RawPartsError::InvalidQuarantine { slot } => {
+                write!(f, "slot {} can't be quarantined", slot)
+            }
+            RawPartsError::UnsortedQuarantine { slot } => {
+                write!(f, "quarantine is not sorted at slot {}", slot)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RawPartsError {}
+
+impl<T> Arena<T> {
+    /// Build an arena out of `(Index, T)` pairs, placing each value at exactly
+    /// its index. This is the inverse of [`Arena::to_entries`].
+    ///
+    /// Entries may be given in any order. Slots that aren't given a value are
+    /// left empty with their first generation, and the free list is built so
+    /// that the lowest empty slot is reused first. Building an arena this way
+    /// takes a single pass over the entries, unlike calling
+    /// [`Arena::insert_at`] for each of them.
+    ///
+    /// Returns an error if two entries have the same slot. So that a few bad
+    /// entries can't make the arena allocate billions of slots, the arena is
+    /// also limited to 64 slots per entry, and entries past that return an
+    /// error. Use [`Arena::into_raw_parts`] to save arenas that are sparser
+    /// than that.
+    pub fn from_entries<I>(entries: I) -> Result<Self, FromEntriesError>
+    where
+        I: IntoIterator<Item = (Index, T)>,
+    {
+        Self::from_entries_with_policy(entries, ReusePolicy::Lifo)
+    }
+
+    /// Like [`Arena::from_entries`], but builds an arena that uses the given
+    /// [`ReusePolicy`].
+    pub fn from_entries_with_policy<I>(
+        entries: I,
+        policy: ReusePolicy,
+    ) -> Result<Self, FromEntriesError>
+    where
+        I: IntoIterator<Item = (Index, T)>,
+    {
+        let entries: Vec<(Index, T)> = entries.into_iter().collect();
+        let max_slots = entries.len().max(1).saturating_mul(MAX_SLOTS_PER_ENTRY);
+
+        let mut storage: Vec<Slot<T>> = Vec::with_capacity(entries.len());
+        let mut len: u32 = 0;
+
+        for (index, value) in entries {
+            let slot = index.slot as usize;
+
+            if slot >= max_slots {
+                return Err(FromEntriesError::TooSparse { slot: index.slot });
+            }
+
+            while storage.len() <= slot {
+                storage.push(Slot::Empty(EmptySlot {
+                    generation: Generation::first(),
+                    next_free: None,
+                }));
+            }
+
+            if let Slot::Occupied(_) = storage[slot] {
+                return Err(FromEntriesError::DuplicateSlot { slot: index.slot });
+            }
+
+            storage[slot] = Slot::Occupied(OccupiedSlot {
+                generation: index.generation,
+                value,
+            });
+
+            len = len
+                .checked_add(1)
+                .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+        }
+
+        let mut arena = Arena {
+            storage,
+            len,
+            ..Arena::with_reuse_policy(policy)
+        };
+        arena.relink_free_list();
+
+        Ok(arena)
+    }
+
+    /// Decompose the arena into its complete state, including the generations
+    /// of empty slots, the order of the free list, and the quarantine.
+    pub fn into_raw_parts(self) -> RawParts<T> {
+        let free_list: Vec<u32> = self.free_list().collect();
+
+        let mut slots: Vec<RawSlot<T>> = self
+            .storage
+            .into_iter()
+            .map(|entry| match entry {
+                Slot::Occupied(occupied) => RawSlot {
+                    generation: occupied.generation.to_u32(),
+                    value: Some(occupied.value),
+                    next_free: None,
+                },
+                Slot::Empty(empty) => RawSlot {
+                    generation: empty.generation.to_u32(),
+                    value: None,
+                    next_free: None,
+                },
+            })
+            .collect();
+
+        for pair in free_list.windows(2) {
+            slots[pair[0] as usize].next_free = Some(pair[1]);
+        }
+
+        RawParts {
+            slots,
+            first_free: free_list.first().copied(),
+            policy: self.policy,
+            quarantine: self.quarantine.epochs,
+            epoch: self.quarantine.epoch,
+            quarantined: self.quarantine.pending.into_iter().collect(),
+        }
+    }
+
+    /// Rebuild an arena from parts created by [`Arena::into_raw_parts`].
+    ///
+    /// The parts are validated: every generation must be nonzero, and every
+    /// empty slot must be visited exactly once by either the free list or the
+    /// quarantine, which can't touch any occupied slot. Quarantined slots must
+    /// be sorted by release epoch, and arenas using [`ReusePolicy::LowestSlot`]
+    /// also need their free list to be sorted by slot.
+    pub fn from_raw_parts(parts: RawParts<T>) -> Result<Self, RawPartsError> {
+        let slot_count: u32 = parts
+            .slots
+            .len()
+            .try_into()
+            .map_err(|_| RawPartsError::TooManySlots)?;
+
+        let mut storage = Vec::with_capacity(parts.slots.len());
+        let mut len: u32 = 0;
+        let mut empty_count: u32 = 0;
+
+        for (slot, raw) in (0..slot_count).zip(parts.slots) {
+            let generation = Generation::from_u32(raw.generation)
+                .ok_or(RawPartsError::ZeroGeneration { slot })?;
+
+            match raw.value {
+                Some(value) => {
+                    if raw.next_free.is_some() {
+                        return Err(RawPartsError::OccupiedLink { slot });
+                    }
+
+                    storage.push(Slot::Occupied(OccupiedSlot { generation, value }));
+                    len = len.checked_add(1).ok_or(RawPartsError::TooManySlots)?;
+                }
+                None => {
+                    let next_free = match raw.next_free {
+                        Some(next) if next >= slot_count => {
+                            return Err(RawPartsError::OutOfBounds { slot: next });
+                        }
+                        next => next.map(FreePointer::from_slot),
+                    };
+
+                    storage.push(Slot::Empty(EmptySlot {
+                        generation,
+                        next_free,
+                    }));
+                    empty_count = empty_count
+                        .checked_add(1)
+                        .ok_or(RawPartsError::TooManySlots)?;
+                }
+            }
+        }
+
+        // Walk the free list, making sure it visits every empty slot once.
+        let mut visited = Vec::new();
+        visited.resize(storage.len(), false);
+
+        let mut previous: Option<u32> = None;
+        let mut current = parts.first_free;
+        let mut visited_count: u32 = 0;
+
+        while let Some(slot) = current {
+            let empty = match storage.get(slot as usize) {
+                Some(Slot::Empty(empty)) => empty,
+                Some(Slot::Occupied(_)) => return Err(RawPartsError::Occupied { slot }),
+                None => return Err(RawPartsError::OutOfBounds { slot }),
+            };
+
+            if visited[slot as usize] {
+                return Err(RawPartsError::Cycle { slot });
+            }
+
+            if parts.policy == ReusePolicy::LowestSlot && previous.is_some_and(|p| p > slot) {
+                return Err(RawPartsError::Unsorted { slot });
+            }
+
+            visited[slot as usize] = true;
+            visited_count = visited_count
+                .checked_add(1)
+                .unwrap_or_else(|| unreachable!("visited more slots than exist"));
+
+            previous = Some(slot);
+            current = empty.next_free.map(FreePointer::slot);
+        }
+
+        let mut previous_release: Option<u64> = None;
+
+        for &(release, slot) in &parts.quarantined {
+            let quarantinable = matches!(storage.get(slot as usize), Some(Slot::Empty(_)))
+                && !visited[slot as usize];
+            if !quarantinable {
+                return Err(RawPartsError::InvalidQuarantine { slot });
+            }
+
+            if previous_release.is_some_and(|previous| previous > release) {
+                return Err(RawPartsError::UnsortedQuarantine { slot });
+            }
+
+            visited[slot as usize] = true;
+            visited_count = visited_count
+                .checked_add(1)
+                .unwrap_or_else(|| unreachable!("visited more slots than exist"));
+
+            previous_release = Some(release);
+        }
+
+        if visited_count != empty_count {
+            let slot = storage
+                .iter()
+                .zip(&visited)
+                .position(|(entry, &visited)| matches!(entry, Slot::Empty(_)) && !visited)
+                .unwrap_or_else(|| unreachable!("fewer slots visited than are empty"));
+
+            return Err(RawPartsError::Unlinked { slot: slot as u32 });
+        }
+
+        Ok(Arena {
+            storage,
+            len,
+            first_free: parts.first_free.map(FreePointer::from_slot),
+            last_free: previous.map(FreePointer::from_slot),
+            quarantine: Quarantine {
+                epochs: parts.quarantine,
+                epoch: parts.epoch,
+                pending: parts.quarantined.into_iter().collect(),
+            },
+            ..Arena::with_reuse_policy(parts.policy)
+        })
+    }
+
+    /// Copy every value in the arena out into a list of `(Index, T)` pairs,
+    /// ordered by slot. [`Arena::from_entries`] turns the list back into an
+    /// arena with the same values at the same indices.
+    pub fn to_entries(&self) -> Vec<(Index, T)>
+    where
+        T: Clone,
+    {
+        let mut entries = Vec::with_capacity(self.len());
+        entries.extend(self.iter().map(|(index, value)| (index, value.clone())));
+        entries
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{FromEntriesError, RawParts, RawPartsError, RawSlot};
+    use crate::arena::{Arena, Index};
+    use crate::policy::ReusePolicy;
+
+    #[test]
+    fn entries_roundtrip() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[1]);
+        arena.remove(indices[4]);
+        arena.invalidate(indices[2]);
+
+        let entries = arena.to_entries();
+        assert_eq!(entries.len(), 4);
+        assert!(entries.windows(2).all(|w| w[0].0.slot() < w[1].0.slot()));
+
+        let rebuilt = Arena::from_entries(entries.iter().rev().copied()).unwrap();
+        assert_eq!(rebuilt.to_entries(), entries);
+    }
+
+    #[test]
+    fn from_entries_fills_gaps() {
+        let a = Index::from_bits(0x0000_0003_0000_0005).unwrap();
+        let b = Index::from_bits(0x0000_0007_0000_0002).unwrap();
+
+        let mut arena = Arena::from_entries([(a, "a"), (b, "b")]).unwrap();
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena[a], "a");
+        assert_eq!(arena[b], "b");
+
+        // Gaps are reused lowest slot first.
+        let slots: Vec<_> = (0..4).map(|_| arena.insert("gap").slot()).collect();
+        assert_eq!(slots, [0, 1, 3, 4]);
+        assert_eq!(arena.insert("new").slot(), 6);
+    }
+
+    #[test]
+    fn from_entries_duplicate_slot() {
+        let a = Index::from_bits(0x0000_0001_0000_0002).unwrap();
+        let b = Index::from_bits(0x0000_0002_0000_0002).unwrap();
+
+        let result = Arena::from_entries([(a, 1), (b, 2)]);
+        assert_eq!(
+            result.unwrap_err(),
+            FromEntriesError::DuplicateSlot { slot: 2 }
+        );
+    }
+
+    #[test]
+    fn from_entries_too_sparse() {
+        let far = Index::from_bits(0x0000_0001_ffff_ffff).unwrap();
+        let result = Arena::from_entries([(far, 1)]);
+        assert_eq!(
+            result.unwrap_err(),
+            FromEntriesError::TooSparse { slot: u32::MAX }
+        );
+
+        // Sparse arenas still work up to the limit.
+        let a = Index::from_bits(0x0000_0001_0000_0000).unwrap();
+        let b = Index::from_bits(0x0000_0001_0000_007f).unwrap();
+        let arena = Arena::from_entries([(a, 1), (b, 2)]).unwrap();
+        assert_eq!(arena[b], 2);
+    }
+
+    #[test]
+    fn from_entries_with_policy() {
+        let a = Index::from_bits(0x0000_0001_0000_0003).unwrap();
+        let mut arena = Arena::from_entries_with_policy([(a, "a")], ReusePolicy::Fifo).unwrap();
+        assert_eq!(arena.reuse_policy(), ReusePolicy::Fifo);
+
+        // Gaps are still reused lowest slot first, and freed slots after them.
+        let b = arena.insert("b");
+        arena.remove(b);
+        let slots: Vec<_> = (0..4).map(|_| arena.insert("gap").slot()).collect();
+        assert_eq!(slots, [1, 2, 0, 4]);
+    }
+
+    fn raw_roundtrip(arena: Arena<i32>) -> Arena<i32> {
+        let parts = arena.into_raw_parts();
+        Arena::from_raw_parts(parts.clone()).unwrap()
+    }
+
+    #[test]
+    fn raw_parts_roundtrip() {
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[4]);
+        arena.remove(indices[1]);
+        arena.remove(indices[3]);
+
+        let mut copy = raw_roundtrip(arena.clone());
+        assert_eq!(copy.reuse_policy(), ReusePolicy::Fifo);
+        assert_eq!(copy.len(), 3);
+        assert_eq!(copy[indices[5]], 5);
+
+        // The free list order and empty generations survive, so both arenas
+        // keep producing the same indices and don't resurrect old ones.
+        for i in 0..5 {
+            let index = arena.insert(i);
+            assert_eq!(copy.insert(i), index);
+            assert!(!indices.contains(&index));
+        }
+
+        assert_eq!(arena.into_raw_parts(), copy.into_raw_parts());
+    }
+
+    #[test]
+    fn raw_parts_keep_quarantine() {
+        let mut arena = Arena::new();
+        arena.set_quarantine(4);
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        arena.remove(a);
+        arena.advance_epoch();
+        arena.remove(b);
+
+        let parts = arena.clone().into_raw_parts();
+        assert_eq!((parts.quarantine, parts.epoch), (4, 1));
+        assert_eq!(parts.quarantined, [(4, 0), (5, 1)]);
+        assert_eq!(parts.first_free, None);
+
+        let mut copy = raw_roundtrip(arena.clone());
+        assert!(copy.same_state(&arena));
+        assert_eq!(copy.insert(3).slot(), 2);
+
+        for _ in 0..3 {
+            copy.advance_epoch();
+        }
+        assert_eq!(copy.quarantined_len(), 1);
+        assert_eq!(copy.insert(4).slot(), a.slot());
+    }
+
+    #[test]
+    fn raw_parts_quarantine_validation() {
+        let parts = |quarantined: Vec<(u64, u32)>| RawParts {
+            slots: vec![
+                slot(1, None, None),
+                slot(1, None, None),
+                slot(1, Some(1), None),
+            ],
+            first_free: Some(0),
+            policy: ReusePolicy::Lifo,
+            quarantine: 1,
+            epoch: 0,
+            quarantined,
+        };
+
+        for &bad in &[0, 2, 3] {
+            assert_eq!(
+                Arena::from_raw_parts(parts(vec![(1, 1), (1, bad)])).unwrap_err(),
+                RawPartsError::InvalidQuarantine { slot: bad }
+            );
+        }
+
+        let mut unsorted = parts(vec![(2, 1)]);
+        unsorted.first_free = None;
+        unsorted.quarantined.insert(0, (1, 0));
+        assert!(Arena::from_raw_parts(unsorted.clone()).is_ok());
+        unsorted.quarantined.swap(0, 1);
+        assert_eq!(
+            Arena::from_raw_parts(unsorted).unwrap_err(),
+            RawPartsError::UnsortedQuarantine { slot: 0 }
+        );
+
+        assert_eq!(
+            Arena::from_raw_parts(parts(Vec::new())).unwrap_err(),
+            RawPartsError::Unlinked { slot: 1 }
+        );
+        assert!(Arena::from_raw_parts(parts(vec![(1, 1)])).is_ok());
+    }
+
+    fn slot(generation: u32, value: Option<i32>, next_free: Option<u32>) -> RawSlot<i32> {
+        RawSlot {
+            generation,
+            value,
+            next_free,
+        }
+    }
+
+    fn from_raw(
+        slots: Vec<RawSlot<i32>>,
+        first_free: Option<u32>,
+    ) -> Result<Arena<i32>, RawPartsError> {
+        Arena::from_raw_parts(RawParts {
+            slots,
+            first_free,
+            policy: ReusePolicy::LowestSlot,
+            quarantine: 0,
+            epoch: 0,
+            quarantined: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn raw_parts_validation() {
+        assert_eq!(
+            from_raw(vec![slot(0, Some(1), None)], None).unwrap_err(),
+            RawPartsError::ZeroGeneration { slot: 0 }
+        );
+        assert_eq!(
+            from_raw(vec![slot(1, Some(1), Some(0))], None).unwrap_err(),
+            RawPartsError::OccupiedLink { slot: 0 }
+        );
+        assert_eq!(
+            from_raw(vec![slot(1, None, Some(3))], Some(0)).unwrap_err(),
+            RawPartsError::OutOfBounds { slot: 3 }
+        );
+        assert_eq!(
+            from_raw(vec![slot(1, Some(1), None)], Some(0)).unwrap_err(),
+            RawPartsError::Occupied { slot: 0 }
+        );
+        assert_eq!(
+            from_raw(
+                vec![slot(1, None, Some(1)), slot(1, None, Some(0))],
+                Some(0)
+            )
+            .unwrap_err(),
+            RawPartsError::Cycle { slot: 0 }
+        );
+        assert_eq!(
+            from_raw(vec![slot(1, None, None), slot(1, None, None)], Some(0)).unwrap_err(),
+            RawPartsError::Unlinked { slot: 1 }
+        );
+        assert_eq!(
+            from_raw(vec![slot(1, None, None), slot(1, None, Some(0))], Some(1)).unwrap_err(),
+            RawPartsError::Unsorted { slot: 0 }
+        );
+
+        let arena = from_raw(vec![slot(2, None, Some(1)), slot(5, None, None)], Some(0)).unwrap();
+        assert_eq!(
+            arena.next_index(),
+            Index::from_bits(0x0000_0003_0000_0000).unwrap()
+        );
+    }
+}
@@ -0,0 +1,371 @@
+//! Translating indices when values move from one arena to another.
+
+// Vec is part of the prelude when std is enabled.
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::arena::{Arena, Index};
+
+/// A mapping from old indices to new ones, produced when values are moved
+/// between arenas by methods like [`Arena::append`] and [`Arena::split_off`].
+///
+/// Only exact indices are translated: an old index whose generation doesn't
+/// match the value that was moved has no mapping.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexRemap {
+    // Sorted by old slot. Slots are unique because only live values move.
+    entries: Vec<(Index, Index)>,
+}
+
+impl IndexRemap {
+    /// Construct an empty remap.
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Return the number of indices that are translated by this remap.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether this remap translates no indices at all.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Translate an old index into its new index, returning `None` if the index
+    /// wasn't moved.
+    pub fn get(&self, old: Index) -> Option<Index> {
+        let position = self
+            .entries
+            .binary_search_by_key(&old.slot, |(from, _)| from.slot)
+            .ok()?;
+
+        match self.entries[position] {
+            (from, to) if from == old => Some(to),
+            _ => None,
+        }
+    }
+
+    /// Translate every index contained in the given value, leaving indices
+    /// that weren't moved untouched.
+    pub fn apply<R: RemapIndices + ?Sized>(&self, target: &mut R) {
+        target.remap_indices(self);
+    }
+
+    /// Record that the value at `old` moved to `new`, replacing any previous
+    /// mapping for the same slot.
+    pub(crate) fn insert(&mut self, old: Index, new: Index) {
+        match self
+            .entries
+            .binary_search_by_key(&old.slot, |(from, _)| from.slot)
+        {
+            Ok(position) => self.entries[position] = (old, new),
+            Err(position) => self.entries.insert(position, (old, new)),
+        }
+    }
+
+    /// The new indices in this remap, in the order of their old slots.
+    pub(crate) fn targets(&self) -> impl Iterator<Item = Index> + '_ {
+        self.entries.iter().map(|&(_, to)| to)
+    }
+}
+
+/// Implemented by types that contain [`Index`] values that need to be fixed up
+/// when values move between arenas.
+///
+/// Implementations should hand every `Index` they contain to the remap, for
+/// example by calling `remap_indices` on each field that holds indices.
+///
+/// ```rust
+/// # use thunderdome::{Arena, Index, IndexRemap, RemapIndices};
+/// struct Node {
+///     parent: Option<Index>,
+///     children: Vec<Index>,
+/// }
+///
+/// impl RemapIndices for Node {
+///     fn remap_indices(&mut self, remap: &IndexRemap) {
+///         self.parent.remap_indices(remap);
+///         self.children.remap_indices(remap);
+///     }
+/// }
+///
+/// let mut prefab = Arena::new();
+/// let root = prefab.insert(Node { parent: None, children: Vec::new() });
+/// let child = prefab.insert(Node { parent: Some(root), children: Vec::new() });
+/// prefab[root].children.push(child);
+///
+/// let mut world = Arena::new();
+/// world.insert(Node { parent: None, children: Vec::new() });
+///
+/// let remap = world.append_remapped(prefab);
+/// let new_root = remap.get(root).unwrap();
+/// let new_child = remap.get(child).unwrap();
+/// assert_eq!(world[new_root].children, [new_child]);
+/// assert_eq!(world[new_child].parent, Some(new_root));
+/// ```
+pub trait RemapIndices {
+    /// Translate every index contained in `self` using the given remap.
+    fn remap_indices(&mut self, remap: &IndexRemap);
+}
+
+impl RemapIndices for Index {
+    fn remap_indices(&mut self, remap: &IndexRemap) {
+        if let Some(new) = remap.get(*self) {
+            *self = new;
+        }
+    }
+}
+
+impl<R: RemapIndices> RemapIndices for Option<R> {
+    fn remap_indices(&mut self, remap: &IndexRemap) {
+        if let Some(inner) = self {
+            inner.remap_indices(remap);
+        }
+    }
+}
+
+impl<R: RemapIndices> RemapIndices for [R] {
+    fn remap_indices(&mut self, remap: &IndexRemap) {
+        for inner in self {
+            inner.remap_indices(remap);
+        }
+    }
+}
+
+impl<R: RemapIndices, const N: usize> RemapIndices for [R; N] {
+    fn remap_indices(&mut self, remap: &IndexRemap) {
+        self.as_mut_slice().remap_indices(remap);
+    }
+}
+
+impl<R: RemapIndices> RemapIndices for Vec<R> {
+    fn remap_indices(&mut self, remap: &IndexRemap) {
+        self.as_mut_slice().remap_indices(remap);
+    }
+}
+
+impl<T> Arena<T> {
+    /// Move every value out of `other` and insert it into this arena,
+    /// returning a remap from each value's index in `other` to its new index.
+    ///
+    /// Values are inserted in slot order, following this arena's reuse
+    /// policy. Indices stored inside the values are not touched; see
+    /// [`Arena::append_remapped`].
+    pub fn append(&mut self, other: Arena<T>) -> IndexRemap {
+        let mut remap = IndexRemap::new();
+
+        for (old, value) in other {
+            let new = self.insert(value);
+            remap.insert(old, new);
+        }
+
+        remap
+    }
+
+    /// Like [`Arena::append`], but also translates the indices stored inside
+    /// each appended value, so that values from `other` that refer to each
+    /// other keep doing so in this arena.
+    ///
+    /// Only the appended values are visited.
+    pub fn append_remapped(&mut self, other: Arena<T>) -> IndexRemap
+    where
+        T: RemapIndices,
+    {
+        let remap = self.append(other);
+
+        for new in remap.targets() {
+            if let Some(value) = self.get_mut(new) {
+                value.remap_indices(&remap);
+            }
+        }
+
+        remap
+    }
+
+    /// Move every value for which `predicate` returns `true` out of this arena
+    /// and into a new arena, returning the new arena and a remap from each
+    /// moved value's old index to its index in the new arena. The new arena
+    /// uses the same [`ReusePolicy`](crate::ReusePolicy) as this one.
+    ///
+    /// The slots of moved values are freed in this arena. Indices stored inside
+    /// the values are not touched; see [`Arena::split_off_remapped`].
+    pub fn split_off<F>(&mut self, mut predicate: F) -> (Arena<T>, IndexRemap)
+    where
+        F: FnMut(Index, &T) -> bool,
+    {
+        let mut split = Arena::with_reuse_policy(self.policy);
+        let mut remap = IndexRemap::new();
+
+        let slots: u32 = self
+            .iter()
+            .next_back()
+            .map_or(0, |(index, _)| index.slot().saturating_add(1));
+
+        for slot in 0..slots {
+            let moves = match self.get_by_slot(slot) {
+                Some((index, value)) => predicate(index, value),
+                None => false,
+            };
+
+            if moves {
+                if let Some((old, value)) = self.remove_by_slot(slot) {
+                    let new = split.insert(value);
+                    remap.insert(old, new);
+                }
+            }
+        }
+
+        (split, remap)
+    }
+
+    /// Like [`Arena::split_off`], but also translates the indices stored inside
+    /// the values of both arenas, so that values that refer to moved values
+    /// keep doing so, whether they moved too or stayed behind.
+    ///
+    /// Every value in both arenas is visited.
+    pub fn split_off_remapped<F>(&mut self, predicate: F) -> (Arena<T>, IndexRemap)
+    where
+        F: FnMut(Index, &T) -> bool,
+        T: RemapIndices,
+    {
+        let (mut split, remap) = self.split_off(predicate);
+
+        for (_, value) in self.iter_mut().chain(split.iter_mut()) {
+            value.remap_indices(&remap);
+        }
+
+        (split, remap)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{IndexRemap, RemapIndices};
+    use crate::{Arena, Index, ReusePolicy};
+
+    #[test]
+    fn append() {
+        let mut world = Arena::new();
+        let a = world.insert("a");
+        let b = world.insert("b");
+        world.remove(a);
+
+        let mut prefab = Arena::new();
+        let x = prefab.insert("x");
+        let y = prefab.insert("y");
+        let z = prefab.insert("z");
+        prefab.remove(y);
+
+        let remap = world.append(prefab);
+        assert_eq!(remap.len(), 2);
+        assert_eq!(world.len(), 3);
+
+        let new_x = remap.get(x).unwrap();
+        let new_z = remap.get(z).unwrap();
+        assert_eq!(world[new_x], "x");
+        assert_eq!(world[new_z], "z");
+        assert_eq!(world[b], "b");
+
+        // `x` reused the slot freed by `a`, but `a` must still be invalid.
+        assert_eq!(new_x.slot(), a.slot());
+        assert_eq!(world.get(a), None);
+
+        assert_eq!(remap.get(y), None);
+    }
+
+    #[test]
+    fn remap_ignores_other_generations() {
+        let mut world = Arena::new();
+        world.insert(0);
+
+        let mut other = Arena::new();
+        let old = other.insert(1);
+        let current = other.invalidate(old).unwrap();
+
+        let remap = world.append(other);
+        assert_eq!(remap.get(old), None);
+        assert!(remap.get(current).is_some());
+
+        let mut unrelated = Index::DANGLING;
+        remap.apply(&mut unrelated);
+        assert_eq!(unrelated, Index::DANGLING);
+    }
+
+    #[test]
+    fn split_off() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+
+        let (evens, remap) = arena.split_off(|_, &value| value % 2 == 0);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(evens.len(), 3);
+        assert_eq!(remap.len(), 3);
+
+        for (i, &index) in indices.iter().enumerate() {
+            match remap.get(index) {
+                Some(new) => {
+                    assert_eq!(evens[new], i);
+                    assert!(!arena.contains(index));
+                }
+                None => assert_eq!(arena[index], i),
+            }
+        }
+    }
+
+    #[test]
+    fn split_off_keeps_policy() {
+        let mut arena = Arena::with_reuse_policy(ReusePolicy::Fifo);
+        arena.insert(1);
+
+        let (split, _) = arena.split_off(|_, _| true);
+        assert_eq!(split.reuse_policy(), ReusePolicy::Fifo);
+    }
+
+    #[test]
+    fn split_off_remapped() {
+        // A parent that stays behind, with one child that moves and one that
+        // doesn't. Each value holds the indices it refers to.
+        let mut arena: Arena<Vec<Index>> = Arena::new();
+        let parent = arena.insert(Vec::new());
+        let moved = arena.insert(vec![parent]);
+        let kept = arena.insert(vec![parent]);
+        arena[parent] = vec![moved, kept];
+
+        let (split, remap) = arena.split_off_remapped(|index, _| index == moved);
+        let new_moved = remap.get(moved).unwrap();
+
+        assert_eq!(arena[parent], [new_moved, kept]);
+        assert_eq!(arena[kept], [parent]);
+        assert_eq!(split[new_moved], [parent]);
+    }
+
+    #[test]
+    fn apply_to_nested_values() {
+        let mut other = Arena::new();
+        let a = other.insert(Vec::new());
+        let b = other.insert(vec![a]);
+        other[a].push(b);
+
+        let mut world: Arena<Vec<Index>> = Arena::new();
+        world.insert(Vec::new());
+        let remap = world.append(other);
+
+        let mut refs = [Some(a), None, Some(b)];
+        remap.apply(&mut refs);
+        assert_eq!(refs, [remap.get(a), None, remap.get(b)]);
+    }
+
+    #[test]
+    fn empty_remap() {
+        let remap = IndexRemap::new();
+        assert!(remap.is_empty());
+
+        let mut index = Index::DANGLING;
+        index.remap_indices(&remap);
+        assert_eq!(index, Index::DANGLING);
+    }
+}
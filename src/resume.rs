@@ -0,0 +1,240 @@
+use core::convert::TryInto;
+
+use crate::arena::{Arena, Index, Slot};
+use crate::iter::Iter;
+
+/// Where a sweep over an [`Arena`] that is spread over many calls left off,
+/// for [`Arena::iter_from`] and [`Arena::retain_budgeted`].
+///
+/// A token holds the slot to continue from and a stamp of the arena's state,
+/// used to tell whether the arena was changed between calls. Values can be
+/// inserted and removed between calls: the sweep continues from the same slot
+/// either way, though values inserted into slots it already passed won't be
+/// visited until the next sweep.
+///
+/// Once a call reaches the end of the arena, the token is finished, and
+/// passing it again starts a new sweep from the first slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IterToken {
+    slot: u32,
+    stamp: Option<u64>,
+    changed: bool,
+    finished: bool,
+}
+
+impl IterToken {
+    /// A token for starting a sweep from the first slot.
+    pub const fn new() -> Self {
+        IterToken {
+            slot: 0,
+            stamp: None,
+            changed: false,
+            finished: false,
+        }
+    }
+
+    /// The slot the next call continues from.
+    pub const fn slot(&self) -> u32 {
+        self.slot
+    }
+
+    /// Returns true if the last call reached the end of the arena, which
+    /// completes the sweep.
+    pub const fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Returns true if values were inserted, removed, or invalidated by
+    /// something else between the calls of this sweep, so the sweep may not
+    /// have seen a consistent view of the arena.
+    pub const fn arena_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Work out the range of slots the next call visits, and the token it
+    /// returns without the arena's stamp.
+    fn advance<T>(self, arena: &Arena<T>, budget: usize) -> (usize, usize, IterToken) {
+        let (start, changed) = if self.finished {
+            (0, false)
+        } else {
+            let changed = self.changed || self.stamp.is_some_and(|stamp| stamp != arena.stamp);
+            (self.slot as usize, changed)
+        };
+
+        let storage_len = arena.storage.len();
+        let start = start.min(storage_len);
+        let end = start.saturating_add(budget).min(storage_len);
+
+        let token = IterToken {
+            slot: end.try_into().unwrap_or_else(|_| {
+                unreachable!("Arena storage exceeded what can be represented by a u32")
+            }),
+            stamp: None,
+            changed,
+            finished: end == storage_len,
+        };
+
+        (start, end, token)
+    }
+}
+
+impl<T> Arena<T> {
+    /// Iterate over the values in the next `budget` slots of a sweep that is
+    /// spread over many calls, returning the iterator and the token to pass to
+    /// the next call. Both empty and occupied slots count towards the budget.
+    ///
+    /// ```rust
+    /// # use thunderdome::{Arena, IterToken};
+    /// let mut arena = Arena::new();
+    /// for i in 0..10 {
+    ///     arena.insert(i);
+    /// }
+    ///
+    /// let mut token = IterToken::new();
+    /// let mut sum = 0;
+    /// while !token.is_finished() {
+    ///     let (iter, next) = arena.iter_from(token, 4);
+    ///     sum += iter.map(|(_, value)| value).sum::<i32>();
+    ///     token = next;
+    ///
+    ///     // The arena can change between calls.
+    ///     arena.insert(100);
+    /// }
+    ///
+    /// // The sweep reaches the first two values inserted past its end, but
+    /// // not the last one.
+    /// assert_eq!(sum, 45 + 200);
+    /// assert_eq!(arena.len(), 13);
+    /// assert!(token.arena_changed());
+    /// ```
+    pub fn iter_from(&self, token: IterToken, budget: usize) -> (Iter<'_, T>, IterToken) {
+        let (start, end, mut token) = token.advance(self, budget);
+        token.stamp = Some(self.stamp);
+
+        let iter = self.iter_range(start, end);
+        (iter, token)
+    }
+
+    /// Run the next `budget` slots of a sweep that is spread over many calls,
+    /// removing the values in them for which the given function returns false.
+    /// Returns the token to pass to the next call. Both empty and occupied
+    /// slots count towards the budget.
+    ///
+    /// Removals made by the sweep itself don't count as changes for
+    /// [`IterToken::arena_changed`].
+    pub fn retain_budgeted<F>(&mut self, token: IterToken, budget: usize, mut f: F) -> IterToken
+    where
+        F: FnMut(Index, &mut T) -> bool,
+    {
+        let (start, end, mut token) = token.advance(self, budget);
+
+        for i in start..end {
+            let slot = i as u32;
+
+            let keep = match &mut self.storage[i] {
+                Slot::Occupied(occupied) => {
+                    let index = Index {
+                        slot,
+                        generation: occupied.generation,
+                    };
+
+                    f(index, &mut occupied.value)
+                }
+                Slot::Empty(_) => true,
+            };
+
+            if !keep {
+                self.remove_by_slot(slot);
+            }
+        }
+
+        token.stamp = Some(self.stamp);
+        token
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::IterToken;
+    use crate::Arena;
+
+    #[test]
+    fn iter_in_slices() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[4]);
+        arena.remove(indices[5]);
+
+        let (iter, token) = arena.iter_from(IterToken::new(), 4);
+        assert_eq!(iter.len(), 4);
+        assert_eq!(
+            iter.map(|(_, value)| *value).collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+        assert_eq!(token.slot(), 4);
+
+        let (iter, token) = arena.iter_from(token, 3);
+        assert_eq!(
+            iter.map(|(index, _)| index).collect::<Vec<_>>(),
+            [indices[6]]
+        );
+
+        let (iter, token) = arena.iter_from(token, 100);
+        assert_eq!(
+            iter.rev().map(|(_, value)| *value).collect::<Vec<_>>(),
+            [9, 8, 7]
+        );
+        assert!(token.is_finished());
+        assert!(!token.arena_changed());
+
+        // A finished token starts over.
+        let (mut iter, token) = arena.iter_from(token, 1);
+        assert_eq!(iter.next(), Some((indices[0], &0)));
+        assert_eq!(token.slot(), 1);
+        assert!(!token.is_finished());
+    }
+
+    #[test]
+    fn tolerates_changes_between_calls() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+
+        let token = arena.retain_budgeted(IterToken::new(), 3, |_, value| *value != 1);
+        assert!(!token.arena_changed());
+        assert_eq!(arena.len(), 5);
+
+        // Slot 1 is reused behind the sweep, and slot 4 is emptied ahead of it.
+        arena.insert(100);
+        arena.remove(indices[4]);
+
+        let mut seen = Vec::new();
+        let token = arena.retain_budgeted(token, 10, |_, value| {
+            seen.push(*value);
+            true
+        });
+        assert_eq!(seen, [3, 5]);
+        assert!(token.is_finished());
+        assert!(token.arena_changed());
+
+        // A new sweep doesn't remember the changes.
+        let token = arena.retain_budgeted(token, 10, |_, _| true);
+        assert!(token.is_finished());
+        assert!(!token.arena_changed());
+    }
+
+    #[test]
+    fn token_past_the_end() {
+        let mut arena = Arena::new();
+        arena.insert_at_slot(9, ());
+
+        let (_, token) = arena.iter_from(IterToken::new(), 8);
+        assert_eq!(token.slot(), 8);
+
+        // Use the token with a smaller arena.
+        let mut small = Arena::new();
+        small.insert(());
+        let (iter, token) = small.iter_from(token, 8);
+        assert_eq!(iter.count(), 0);
+        assert!(token.is_finished());
+    }
+}
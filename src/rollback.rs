@@ -0,0 +1,325 @@
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops;
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::VecDeque;
+
+use crate::cow::CowArena;
+use crate::hash::StateHasher;
+
+/// A [`CowArena`] that keeps the state of the last few frames, for
+/// deterministic lockstep games with rollback.
+///
+/// [`RollbackArena::save_frame`] records the state of the arena at the end of
+/// a frame, and [`RollbackArena::rollback_to`] restores it exactly, including
+/// empty slots, generations and the order of the free list. Replaying the same
+/// operations after a rollback produces the same `Index` values as the first
+/// time. Saving a frame takes constant time, and frames share storage with
+/// each other through [`CowArena`] snapshots.
+///
+/// [`RollbackArena::checksum`] hashes the full state of the arena in a way
+/// that doesn't depend on the platform, so peers can compare checksums of a
+/// frame to detect desyncs.
+///
+/// The arena dereferences to [`CowArena`], which is used to read and change
+/// its values.
+///
+/// ```rust
+/// # use thunderdome::RollbackArena;
+/// let mut arena = RollbackArena::new(8);
+/// arena.insert("player");
+/// arena.save_frame(1);
+/// let checksum = arena.checksum();
+///
+/// let enemy = arena.insert("enemy");
+/// arena.save_frame(2);
+///
+/// // A late input means that frame 2 has to be simulated again.
+/// arena.rollback_to(1).unwrap();
+/// assert_eq!(arena.checksum(), checksum);
+/// assert_eq!(arena.insert("enemy"), enemy);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RollbackArena<T> {
+    arena: CowArena<T>,
+    frames: VecDeque<(u64, CowArena<T>)>,
+    max_frames: usize,
+}
+
+/// The error returned by [`RollbackArena::rollback_to`] when the frame was
+/// never saved or is no longer kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownFrame {
+    frame: u64,
+}
+
+impl UnknownFrame {
+    /// The frame that was asked for.
+    pub const fn frame(&self) -> u64 {
+        self.frame
+    }
+}
+
+impl fmt::Display for UnknownFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {} is not saved", self.frame)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownFrame {}
+
+impl<T> RollbackArena<T> {
+    /// Construct an empty arena that keeps up to `max_frames` saved frames.
+    pub fn new(max_frames: usize) -> Self {
+        Self::from_arena(CowArena::new(), max_frames)
+    }
+
+    /// Wrap an existing arena, keeping up to `max_frames` saved frames.
+    pub fn from_arena(arena: CowArena<T>, max_frames: usize) -> Self {
+        RollbackArena {
+            arena,
+            frames: VecDeque::new(),
+            max_frames,
+        }
+    }
+
+    /// Unwrap the arena, dropping every saved frame.
+    pub fn into_arena(self) -> CowArena<T> {
+        self.arena
+    }
+
+    /// The most frames that are kept at once.
+    pub fn max_frames(&self) -> usize {
+        self.max_frames
+    }
+
+    /// Change the most frames that are kept at once, dropping the oldest
+    /// frames if there are too many.
+    pub fn set_max_frames(&mut self, max_frames: usize) {
+        self.max_frames = max_frames;
+        self.trim();
+    }
+
+    /// Save the current state of the arena as the given frame.
+    ///
+    /// Frames are expected to be saved in increasing order. Saved frames that
+    /// are not older than `frame` are dropped first, since they belong to a
+    /// timeline that has been replaced. If more than
+    /// [`RollbackArena::max_frames`] frames are saved, the oldest is dropped.
+    pub fn save_frame(&mut self, frame: u64) {
+        self.drop_frames_from(frame);
+        self.frames.push_back((frame, self.arena.snapshot()));
+        self.trim();
+    }
+
+    /// Restore the state of the arena saved for the given frame. The frame is
+    /// kept, and every frame saved after it is dropped.
+    pub fn rollback_to(&mut self, frame: u64) -> Result<(), UnknownFrame> {
+        let position = self.position(frame).ok_or(UnknownFrame { frame })?;
+
+        self.frames.truncate(position.saturating_add(1));
+        self.arena = self.frames[position].1.snapshot();
+
+        Ok(())
+    }
+
+    /// Returns true if the given frame is saved.
+    pub fn has_frame(&self, frame: u64) -> bool {
+        self.position(frame).is_some()
+    }
+
+    /// The oldest saved frame, if any.
+    pub fn oldest_frame(&self) -> Option<u64> {
+        self.frames.front().map(|&(frame, _)| frame)
+    }
+
+    /// The most recently saved frame, if any.
+    pub fn latest_frame(&self) -> Option<u64> {
+        self.frames.back().map(|&(frame, _)| frame)
+    }
+
+    /// The state of the arena saved for the given frame.
+    pub fn frame(&self, frame: u64) -> Option<&CowArena<T>> {
+        self.position(frame)
+            .map(|position| &self.frames[position].1)
+    }
+
+    /// Drop every saved frame older than the given frame, such as frames that
+    /// every peer has confirmed.
+    pub fn forget_before(&mut self, frame: u64) {
+        while matches!(self.frames.front(), Some(&(oldest, _)) if oldest < frame) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Hash the full state of the arena: every slot's generation and value,
+    /// and the order of the free list.
+    ///
+    /// This is [`CowArena::state_hash`] fed to a [`StateHasher`], so it's the
+    /// same on every platform as long as `T`'s `Hash` implementation is.
+    pub fn checksum(&self) -> u64
+    where
+        T: Hash,
+    {
+        checksum(&self.arena)
+    }
+
+    /// The checksum of the state saved for the given frame. See
+    /// [`RollbackArena::checksum`].
+    pub fn frame_checksum(&self, frame: u64) -> Option<u64>
+    where
+        T: Hash,
+    {
+        self.frame(frame).map(checksum)
+    }
+
+    fn position(&self, frame: u64) -> Option<usize> {
+        self.frames.iter().rposition(|&(saved, _)| saved == frame)
+    }
+
+    fn drop_frames_from(&mut self, frame: u64) {
+        while matches!(self.frames.back(), Some(&(latest, _)) if latest >= frame) {
+            self.frames.pop_back();
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.frames.len() > self.max_frames {
+            self.frames.pop_front();
+        }
+    }
+}
+
+fn checksum<T: Hash>(arena: &CowArena<T>) -> u64 {
+    let mut hasher = StateHasher::new();
+    arena.state_hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<T> ops::Deref for RollbackArena<T> {
+    type Target = CowArena<T>;
+
+    fn deref(&self) -> &CowArena<T> {
+        &self.arena
+    }
+}
+
+impl<T> ops::DerefMut for RollbackArena<T> {
+    fn deref_mut(&mut self) -> &mut CowArena<T> {
+        &mut self.arena
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{RollbackArena, UnknownFrame};
+    use crate::cow::CowArena;
+    use crate::test_util::POLICIES;
+    use crate::Index;
+
+    /// Run one frame of a made-up simulation, returning the indices it
+    /// created.
+    fn simulate(arena: &mut RollbackArena<u64>, frame: u64) -> Vec<Index> {
+        let mut created = Vec::new();
+
+        let doomed: Vec<_> = arena
+            .iter()
+            .filter(|&(_, value)| value % 3 == frame % 3)
+            .map(|(index, _)| index)
+            .collect();
+        for index in doomed {
+            arena.remove(index);
+        }
+
+        for i in 0..4 {
+            created.push(arena.insert(frame.wrapping_mul(10).wrapping_add(i)));
+        }
+
+        if let Some(index) = created.first() {
+            arena.invalidate(*index);
+        }
+
+        created
+    }
+
+    #[test]
+    fn replay_after_rollback_is_deterministic() {
+        for &policy in &POLICIES {
+            let mut arena = RollbackArena::from_arena(CowArena::with_reuse_policy(policy), 16);
+            let mut first_run = Vec::new();
+            let mut checksums = Vec::new();
+
+            for frame in 0..10 {
+                first_run.push(simulate(&mut arena, frame));
+                arena.save_frame(frame);
+                checksums.push(arena.checksum());
+            }
+
+            arena.rollback_to(4).unwrap();
+            assert_eq!(arena.checksum(), checksums[4]);
+            assert_eq!(arena.latest_frame(), Some(4));
+
+            for frame in 5..10 {
+                assert_eq!(simulate(&mut arena, frame), first_run[frame as usize]);
+                arena.save_frame(frame);
+                assert_eq!(arena.checksum(), checksums[frame as usize]);
+            }
+        }
+    }
+
+    #[test]
+    fn checksum_covers_full_state() {
+        let mut a = CowArena::new();
+        let x = a.insert(1u32);
+        let y = a.insert(2);
+        a.remove(x);
+        a.remove(y);
+
+        // Same values and generations, but a different free list order.
+        let mut b = CowArena::new();
+        let x = b.insert(1u32);
+        let y = b.insert(2);
+        b.remove(y);
+        b.remove(x);
+
+        let a = RollbackArena::from_arena(a, 1);
+        let mut b = RollbackArena::from_arena(b, 1);
+        assert_ne!(a.checksum(), b.checksum());
+
+        b.insert(5);
+        let before = b.checksum();
+        let index = b.insert(6);
+        b.remove(index);
+        assert_ne!(b.checksum(), before);
+    }
+
+    #[test]
+    fn frames_are_limited() {
+        let mut arena = RollbackArena::new(3);
+
+        for frame in 0..5 {
+            arena.insert(frame);
+            arena.save_frame(frame);
+        }
+
+        assert_eq!(arena.oldest_frame(), Some(2));
+        assert_eq!(arena.rollback_to(1), Err(UnknownFrame { frame: 1 }));
+        assert_eq!(arena.frame(3).map(CowArena::len), Some(4));
+
+        arena.forget_before(4);
+        assert_eq!(arena.oldest_frame(), Some(4));
+
+        // Saving a frame again replaces it and everything after it.
+        arena.save_frame(4);
+        arena.insert(100);
+        arena.save_frame(2);
+        assert_eq!(arena.oldest_frame(), Some(2));
+        assert!(!arena.has_frame(4));
+        assert_eq!(arena.frame_checksum(2), Some(arena.checksum()));
+    }
+}
@@ -0,0 +1,416 @@
+//! Slot storage shared by [`Arena`](crate::Arena) and
+//! [`CowArena`](crate::CowArena), and the free list and quarantine bookkeeping
+//! written once on top of it.
+
+use core::iter::successors;
+use core::mem::replace;
+use core::ops::{Bound, RangeBounds};
+
+use crate::arena::{EmptySlot, Index, OccupiedSlot, Slot};
+use crate::free_pointer::FreePointer;
+use crate::generation::Generation;
+use crate::policy::ReusePolicy;
+use crate::quarantine::Quarantine;
+
+/// Read access to the slots and free list of an arena.
+pub(crate) trait SlotStorage {
+    type Value;
+
+    /// The number of slots, occupied or empty.
+    fn slot_count(&self) -> u32;
+
+    fn slot(&self, slot: u32) -> Option<&Slot<Self::Value>>;
+
+    fn policy(&self) -> ReusePolicy;
+
+    fn first_free(&self) -> Option<FreePointer>;
+
+    fn last_free(&self) -> Option<FreePointer>;
+
+    fn quarantine_state(&self) -> &Quarantine;
+
+    fn empty_slot(&self, slot: u32) -> &EmptySlot {
+        self.slot(slot)
+            .unwrap_or_else(|| {
+                unreachable!("free list pointed past the end of the arena's storage")
+            })
+            .as_empty()
+            .unwrap_or_else(|| unreachable!("free list pointed to an occupied entry"))
+    }
+
+    /// The slots in the free list, in the order they will be reused.
+    fn free_list(&self) -> impl Iterator<Item = u32> + '_ {
+        successors(self.first_free(), move |pointer| {
+            self.empty_slot(pointer.slot()).next_free
+        })
+        .map(FreePointer::slot)
+    }
+
+    /// The slots in quarantine, each with the number of epochs until it is
+    /// released.
+    fn quarantined(&self) -> impl Iterator<Item = (u64, u32)> + '_ {
+        let quarantine = self.quarantine_state();
+        let epoch = quarantine.epoch;
+
+        quarantine
+            .pending
+            .iter()
+            .map(move |&(release, slot)| (release.saturating_sub(epoch), slot))
+    }
+
+    /// Find the pointers a slot passed to `push_free` will sit between. A
+    /// `None` predecessor means that the slot becomes the head of the list.
+    fn free_list_position(&self, slot: u32) -> (Option<FreePointer>, Option<FreePointer>) {
+        match (self.policy(), self.first_free()) {
+            (_, None) => (None, None),
+            (ReusePolicy::Lifo, head) => (None, head),
+            (ReusePolicy::Fifo, _) => (self.last_free(), None),
+            (ReusePolicy::LowestSlot, Some(head)) => {
+                let tail = self
+                    .last_free()
+                    .unwrap_or_else(|| unreachable!("first_free is set but last_free is None"));
+
+                // The common cases of freeing a slot below or above every
+                // other free slot don't need to walk the list.
+                if slot < head.slot() {
+                    (None, Some(head))
+                } else if slot > tail.slot() {
+                    (Some(tail), None)
+                } else {
+                    let mut prev = head;
+                    loop {
+                        match self.empty_slot(prev.slot()).next_free {
+                            Some(next) if next.slot() < slot => prev = next,
+                            next => break (Some(prev), next),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Find the slot whose free list link points to the given free slot, or
+    /// `None` if the slot is the head of the free list.
+    fn free_list_predecessor(&self, slot: u32) -> Option<u32> {
+        let mut prev = None;
+        let mut current = self.first_free();
+
+        while let Some(pointer) = current {
+            if pointer.slot() == slot {
+                return prev;
+            }
+
+            prev = Some(pointer.slot());
+            current = self.empty_slot(pointer.slot()).next_free;
+        }
+
+        unreachable!("slot {} is not in the free list", slot)
+    }
+
+    /// Turn a range of slots into start and end positions in the arena's
+    /// storage, clamped to its length.
+    fn slot_bounds<R: RangeBounds<u32>>(&self, slots: R) -> (usize, usize) {
+        let storage_len = self.slot_count() as usize;
+
+        let start = match slots.start_bound() {
+            Bound::Included(&start) => start as usize,
+            Bound::Excluded(&start) => (start as usize).saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match slots.end_bound() {
+            Bound::Included(&end) => (end as usize).saturating_add(1),
+            Bound::Excluded(&end) => end as usize,
+            Bound::Unbounded => storage_len,
+        };
+
+        let end = end.min(storage_len);
+        (start.min(end), end)
+    }
+
+    /// Compute the `Index` that the next insertion would produce.
+    fn next_insert_index(&self) -> Index {
+        match self.first_free() {
+            Some(free_pointer) => Index {
+                slot: free_pointer.slot(),
+                generation: self.empty_slot(free_pointer.slot()).generation.next(),
+            },
+            None => Index {
+                slot: self.slot_count(),
+                generation: Generation::first(),
+            },
+        }
+    }
+
+    /// Compute the `Index` that inserting into the given slot without a
+    /// generation would produce.
+    fn next_index_at(&self, slot: u32) -> Index {
+        let generation = match self.slot(slot) {
+            Some(Slot::Occupied(occupied)) => occupied.generation.next(),
+            Some(Slot::Empty(empty)) => empty.generation.next(),
+            None => Generation::first(),
+        };
+
+        Index { slot, generation }
+    }
+}
+
+/// Write access to the slots and free list of an arena, and the operations
+/// that keep the free list and quarantine consistent as slots are filled and
+/// emptied.
+pub(crate) trait SlotStorageMut: SlotStorage {
+    /// A mutable reference to a slot, copying it first if it is shared.
+    fn slot_mut(&mut self, slot: u32) -> Option<&mut Slot<Self::Value>>;
+
+    /// Add a slot to the end of the storage.
+    fn push_slot(&mut self, entry: Slot<Self::Value>);
+
+    fn len_mut(&mut self) -> &mut u32;
+
+    fn first_free_mut(&mut self) -> &mut Option<FreePointer>;
+
+    fn last_free_mut(&mut self) -> &mut Option<FreePointer>;
+
+    fn quarantine_state_mut(&mut self) -> &mut Quarantine;
+
+    /// Record that values were added, removed, or given new indices.
+    fn touch(&mut self) {}
+
+    fn empty_slot_mut(&mut self, slot: u32) -> &mut EmptySlot {
+        self.slot_mut(slot)
+            .unwrap_or_else(|| {
+                unreachable!("free list pointed past the end of the arena's storage")
+            })
+            .as_empty_mut()
+            .unwrap_or_else(|| unreachable!("free list pointed to an occupied entry"))
+    }
+
+    /// Link a known-empty slot into the free list, at the position given by the
+    /// arena's reuse policy.
+    fn push_free(&mut self, slot: u32) {
+        let (prev, next) = self.free_list_position(slot);
+
+        let pointer = FreePointer::from_slot(slot);
+        self.empty_slot_mut(slot).next_free = next;
+
+        match prev {
+            Some(prev) => self.empty_slot_mut(prev.slot()).next_free = Some(pointer),
+            None => *self.first_free_mut() = Some(pointer),
+        }
+
+        if next.is_none() {
+            *self.last_free_mut() = Some(pointer);
+        }
+    }
+
+    /// Remove a known-empty slot from the free list, linking whatever pointed
+    /// to it to the slot after it.
+    fn unlink_free(&mut self, slot: u32) {
+        let next = self.empty_slot(slot).next_free;
+        let prev = self.free_list_predecessor(slot);
+
+        match prev {
+            Some(prev) => self.empty_slot_mut(prev).next_free = next,
+            None => *self.first_free_mut() = next,
+        }
+
+        // If this slot was the tail of the free list, whatever pointed to it is
+        // the new tail.
+        if next.is_none() {
+            *self.last_free_mut() = prev.map(FreePointer::from_slot);
+        }
+    }
+
+    /// Insert a value into the head of the free list, or a new slot if the
+    /// free list is empty.
+    fn insert_next(&mut self, value: Self::Value) -> Index {
+        self.touch();
+        increment_len(self.len_mut());
+
+        match self.first_free() {
+            Some(free_pointer) => {
+                let slot = free_pointer.slot();
+                let empty = *self.empty_slot(slot);
+
+                // If there is another empty entry after this one, we'll update
+                // the arena to point to it to use it on the next insertion.
+                *self.first_free_mut() = empty.next_free;
+                if empty.next_free.is_none() {
+                    *self.last_free_mut() = None;
+                }
+
+                let generation = empty.generation.next();
+                *self.slot_mut(slot).unwrap_or_else(|| unreachable!()) =
+                    Slot::Occupied(OccupiedSlot { generation, value });
+
+                Index { slot, generation }
+            }
+            None => {
+                // There were no more empty entries left in our free list, so
+                // we'll create a new first-generation entry.
+                let slot = self.slot_count();
+                let generation = Generation::first();
+                self.push_slot(Slot::Occupied(OccupiedSlot { generation, value }));
+
+                Index { slot, generation }
+            }
+        }
+    }
+
+    /// Replace the occupied entry at the given slot with an empty one and add
+    /// it to the free list or quarantine, returning the entry's index and
+    /// value.
+    fn vacate(&mut self, slot: u32) -> Option<(Index, Self::Value)> {
+        let generation = match self.slot(slot)? {
+            Slot::Occupied(occupied) => occupied.generation,
+            Slot::Empty(_) => return None,
+        };
+
+        // We can replace an occupied entry with an empty entry with the same
+        // generation. On next insertion, this generation will increment.
+        let new_entry = Slot::Empty(EmptySlot {
+            generation,
+            next_free: None,
+        });
+
+        let entry = self.slot_mut(slot).unwrap_or_else(|| unreachable!());
+        let value = replace(entry, new_entry)
+            .into_value()
+            .unwrap_or_else(|| unreachable!());
+
+        self.touch();
+        if !self.quarantine_state_mut().push(slot) {
+            self.push_free(slot);
+        }
+
+        let len = self.len_mut();
+        *len = len.checked_sub(1).unwrap_or_else(|| unreachable!());
+
+        Some((Index { slot, generation }, value))
+    }
+
+    /// Insert a value at the given slot, with the given generation or the
+    /// slot's next one, returning the old value if the slot was occupied.
+    fn insert_at_inner(
+        &mut self,
+        slot: u32,
+        generation: Option<Generation>,
+        value: Self::Value,
+    ) -> (Index, Option<Self::Value>) {
+        // Three cases to consider:
+        //
+        // 1.) The slot is free; we need to remove it from the free list, and
+        //     then insert the value.
+        // 2.) The slot is occupied; we can just replace the value and return
+        //     the old one.
+        // 3.) The slot is beyond the current length of the arena. In this
+        //     case, we must extend the arena with new empty slots filling the
+        //     free list accordingly, and then insert the value.
+
+        self.touch();
+
+        let generation = generation.unwrap_or_else(|| self.next_index_at(slot).generation);
+        let index = Index { slot, generation };
+
+        let old_value = match self.slot_mut(slot) {
+            Some(Slot::Empty(_)) => {
+                // Quarantined slots aren't part of the free list, so they only
+                // need to leave quarantine.
+                if !self.quarantine_state_mut().take(slot) {
+                    self.unlink_free(slot);
+                }
+
+                *self.slot_mut(slot).unwrap_or_else(|| unreachable!()) =
+                    Slot::Occupied(OccupiedSlot { generation, value });
+
+                None
+            }
+            Some(Slot::Occupied(occupied)) => {
+                occupied.generation = generation;
+                Some(replace(&mut occupied.value, value))
+            }
+            None => {
+                while self.slot_count() < slot {
+                    let new_slot = self.slot_count();
+                    self.push_slot(Slot::Empty(EmptySlot {
+                        generation: Generation::first(),
+                        next_free: None,
+                    }));
+
+                    self.push_free(new_slot);
+                }
+
+                self.push_slot(Slot::Occupied(OccupiedSlot { generation, value }));
+
+                None
+            }
+        };
+
+        // If this insertion didn't replace an old value, then the arena now
+        // contains one more element.
+        if old_value.is_none() {
+            increment_len(self.len_mut());
+        }
+
+        (index, old_value)
+    }
+
+    /// Give the value at the given index the next generation of its slot.
+    fn invalidate_slot(&mut self, index: Index) -> Option<Index> {
+        match self.slot(index.slot) {
+            Some(Slot::Occupied(occupied)) if occupied.generation == index.generation => {}
+            _ => return None,
+        }
+
+        let occupied = match self.slot_mut(index.slot) {
+            Some(Slot::Occupied(occupied)) => occupied,
+            _ => unreachable!(),
+        };
+
+        occupied.generation = occupied.generation.next();
+        let generation = occupied.generation;
+        self.touch();
+
+        Some(Index {
+            generation,
+            ..index
+        })
+    }
+
+    /// Move to the next epoch, releasing every quarantined slot whose
+    /// quarantine has expired to the free list.
+    fn release_expired(&mut self) {
+        self.quarantine_state_mut().advance();
+
+        while let Some(slot) = self.quarantine_state_mut().pop_expired() {
+            self.push_free(slot);
+        }
+    }
+}
+
+fn increment_len(len: &mut u32) {
+    *len = len
+        .checked_add(1)
+        .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+}
+
+pub(crate) mod sealed {
+    use crate::arena::Index;
+
+    /// The operations of [`Arena`](crate::Arena) and
+    /// [`CowArena`](crate::CowArena) that the entry and drain types are built
+    /// on, so that both arenas can share them.
+    ///
+    /// This trait lives in a private module, so it can't be named or
+    /// implemented outside of this crate.
+    pub trait ArenaLike<T> {
+        fn len(&self) -> usize;
+        fn contains(&self, index: Index) -> bool;
+        fn get(&self, index: Index) -> Option<&T>;
+        fn get_mut(&mut self, index: Index) -> Option<&mut T>;
+        fn insert_at(&mut self, index: Index, value: T) -> Option<T>;
+        fn insert_at_slot(&mut self, slot: u32, value: T) -> (Index, Option<T>);
+        fn remove(&mut self, index: Index) -> Option<T>;
+        fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)>;
+        fn invalidate(&mut self, index: Index) -> Option<Index>;
+    }
+}
@@ -0,0 +1,24 @@
+//! Helpers shared by the tests of several modules.
+
+#[cfg(feature = "std")]
+use crate::Arena;
+use crate::ReusePolicy;
+
+/// Every reuse policy, for tests that should pass under each of them.
+pub(crate) const POLICIES: [ReusePolicy; 3] = [
+    ReusePolicy::Lifo,
+    ReusePolicy::Fifo,
+    ReusePolicy::LowestSlot,
+];
+
+/// Assert that two arenas are in the same complete state, as defined by
+/// [`Arena::same_state`].
+#[cfg(feature = "std")]
+pub(crate) fn assert_same_state(a: &Arena<u32>, b: &Arena<u32>) {
+    assert!(
+        a.same_state(b),
+        "{:?} is not in the same state as {:?}",
+        a,
+        b
+    );
+}
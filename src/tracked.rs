@@ -0,0 +1,728 @@
+//! An arena that records when each value was last added or changed.
+
+use core::iter::FusedIterator;
+use core::mem::replace;
+use core::ops;
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::VecDeque, vec::Vec};
+
+use crate::arena::{Arena, Index};
+use crate::iter::{Iter, IterMut};
+
+/// An [`Arena`] that records the tick each value was added and last changed
+/// at, and a log of removed indices, so that consumers like renderers only
+/// need to look at what changed.
+///
+/// The arena has a current tick, which starts at zero and is moved forward
+/// with [`TrackedArena::advance_tick`]. Inserting a value marks it as added
+/// and changed at the current tick. Any mutable access marks it as changed,
+/// whether or not the value is actually modified: [`TrackedArena::get_mut`],
+/// [`TrackedArena::iter_mut`], the entry API, and `IndexMut` all count.
+/// [`TrackedArena::iter_mut`] and [`TrackedArena::values_mut`] mark every
+/// value as changed when they're called.
+///
+/// [`TrackedArena::changed_since`], [`TrackedArena::added_since`] and
+/// [`TrackedArena::removed_since`] report what happened at a given tick or
+/// later. Invalidating an index counts as removing the old index and adding
+/// the new one.
+///
+/// The arena can be read through the `TrackedArena`, which dereferences to
+/// [`Arena`].
+///
+/// ```rust
+/// # use thunderdome::TrackedArena;
+/// let mut arena = TrackedArena::new();
+/// let a = arena.insert(1);
+/// let b = arena.insert(2);
+///
+/// let frame = arena.advance_tick();
+/// arena[a] += 10;
+/// arena.remove(b);
+/// let c = arena.insert(3);
+///
+/// let changed: Vec<_> = arena.changed_since(frame).map(|(index, _)| index).collect();
+/// assert_eq!(changed, vec![a, c]);
+///
+/// let added: Vec<_> = arena.added_since(frame).map(|(index, _)| index).collect();
+/// assert_eq!(added, vec![c]);
+///
+/// let removed: Vec<_> = arena.removed_since(frame).collect();
+/// assert_eq!(removed, vec![b]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrackedArena<T> {
+    arena: Arena<T>,
+
+    /// The ticks of each slot, indexed by slot. Only meaningful for occupied
+    /// slots.
+    ticks: Vec<Ticks>,
+
+    /// Removed indices, in the order they were removed.
+    removed: VecDeque<(u64, Index)>,
+
+    tick: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Ticks {
+    added: u64,
+    changed: u64,
+}
+
+impl<T> TrackedArena<T> {
+    /// Construct an empty arena.
+    pub fn new() -> Self {
+        Self::from_arena(Arena::new())
+    }
+
+    /// Start tracking changes to an existing arena. Values already in the
+    /// arena are treated as added at tick zero.
+    pub fn from_arena(arena: Arena<T>) -> Self {
+        let mut ticks = Vec::new();
+        ticks.resize(arena.storage.len(), Ticks::default());
+
+        TrackedArena {
+            arena,
+            ticks,
+            removed: VecDeque::new(),
+            tick: 0,
+        }
+    }
+
+    /// Stop tracking changes, returning the arena.
+    pub fn into_arena(self) -> Arena<T> {
+        self.arena
+    }
+
+    /// The current tick, which changes are recorded at.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Move to the next tick, returning it.
+    pub fn advance_tick(&mut self) -> u64 {
+        self.tick = self
+            .tick
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Overflowed u64 advancing change tick"));
+
+        self.tick
+    }
+
+    /// The tick the value at the given index was added at, or `None` if the
+    /// index is not contained in the arena.
+    pub fn added_tick(&self, index: Index) -> Option<u64> {
+        self.ticks(index).map(|ticks| ticks.added)
+    }
+
+    /// The tick the value at the given index was last changed at, or `None` if
+    /// the index is not contained in the arena.
+    pub fn changed_tick(&self, index: Index) -> Option<u64> {
+        self.ticks(index).map(|ticks| ticks.changed)
+    }
+
+    /// Iterate over the values that were added or changed at the given tick or
+    /// later.
+    pub fn changed_since(&self, tick: u64) -> ChangedSince<'_, T> {
+        ChangedSince {
+            inner: self.arena.iter(),
+            ticks: &self.ticks,
+            tick,
+        }
+    }
+
+    /// Iterate over the values that were added at the given tick or later.
+    pub fn added_since(&self, tick: u64) -> AddedSince<'_, T> {
+        AddedSince {
+            inner: self.arena.iter(),
+            ticks: &self.ticks,
+            tick,
+        }
+    }
+
+    /// Iterate over the indices that were removed at the given tick or later,
+    /// in the order they were removed.
+    ///
+    /// The log of removed indices grows until it's trimmed with
+    /// [`TrackedArena::forget_removed_before`].
+    pub fn removed_since(&self, tick: u64) -> RemovedSince<'_> {
+        let start = self.removed.partition_point(|&(removed, _)| removed < tick);
+
+        RemovedSince {
+            inner: self.removed.range(start..),
+        }
+    }
+
+    /// Drop removed indices that were logged before the given tick.
+    pub fn forget_removed_before(&mut self, tick: u64) {
+        let end = self.removed.partition_point(|&(removed, _)| removed < tick);
+        self.removed.drain(..end);
+    }
+
+    /// Insert a new value. See [`Arena::insert`].
+    pub fn insert(&mut self, value: T) -> Index {
+        let index = self.arena.insert(value);
+        self.mark_added(index.slot);
+        index
+    }
+
+    /// Insert a value at the given index. See [`Arena::insert_at`].
+    ///
+    /// Replacing a value with the same generation counts as changing it;
+    /// replacing a value with a different generation counts as removing it.
+    pub fn insert_at(&mut self, index: Index, value: T) -> Option<T> {
+        let old_index = self.arena.contains_slot(index.slot);
+        let old_value = self.arena.insert_at(index, value);
+        self.mark_replaced(old_index, index);
+        old_value
+    }
+
+    /// Insert a value at the given slot. See [`Arena::insert_at_slot`].
+    pub fn insert_at_slot(&mut self, slot: u32, value: T) -> (Index, Option<T>) {
+        let old_index = self.arena.contains_slot(slot);
+        let (index, old_value) = self.arena.insert_at_slot(slot, value);
+        self.mark_replaced(old_index, index);
+        (index, old_value)
+    }
+
+    /// Get a mutable reference to a value, marking it as changed. See
+    /// [`Arena::get_mut`].
+    pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        let value = self.arena.get_mut(index)?;
+        mark_changed(&mut self.ticks, self.tick, index.slot);
+        Some(value)
+    }
+
+    /// Get mutable references to two values, marking them as changed. See
+    /// [`Arena::get2_mut`].
+    pub fn get2_mut(&mut self, index1: Index, index2: Index) -> (Option<&mut T>, Option<&mut T>) {
+        let (value1, value2) = self.arena.get2_mut(index1, index2);
+
+        if value1.is_some() {
+            mark_changed(&mut self.ticks, self.tick, index1.slot);
+        }
+        if value2.is_some() {
+            mark_changed(&mut self.ticks, self.tick, index2.slot);
+        }
+
+        (value1, value2)
+    }
+
+    /// Get a mutable reference to the value in a slot, marking it as changed.
+    /// See [`Arena::get_by_slot_mut`].
+    pub fn get_by_slot_mut(&mut self, slot: u32) -> Option<(Index, &mut T)> {
+        let (index, value) = self.arena.get_by_slot_mut(slot)?;
+        mark_changed(&mut self.ticks, self.tick, slot);
+        Some((index, value))
+    }
+
+    /// Remove a value, logging its index. See [`Arena::remove`].
+    pub fn remove(&mut self, index: Index) -> Option<T> {
+        let value = self.arena.remove(index)?;
+        self.removed.push_back((self.tick, index));
+        Some(value)
+    }
+
+    /// Remove the value in a slot, logging its index. See
+    /// [`Arena::remove_by_slot`].
+    pub fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
+        let (index, value) = self.arena.remove_by_slot(slot)?;
+        self.removed.push_back((self.tick, index));
+        Some((index, value))
+    }
+
+    /// Give a value a new generation. This counts as removing the old index and
+    /// adding the new one. See [`Arena::invalidate`].
+    pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        let new_index = self.arena.invalidate(index)?;
+        self.removed.push_back((self.tick, index));
+        self.mark_added(index.slot);
+        Some(new_index)
+    }
+
+    /// Remove every value, logging their indices. See [`Arena::clear`].
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Remove the values for which the given function returns false, logging
+    /// their indices. Values that are kept are marked as changed, since the
+    /// function could have changed them. See [`Arena::retain`].
+    pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
+        let (ticks, removed, tick) = (&mut self.ticks, &mut self.removed, self.tick);
+
+        self.arena.retain(|index, value| {
+            let keep = f(index, value);
+
+            if keep {
+                mark_changed(ticks, tick, index.slot);
+            } else {
+                removed.push_back((tick, index));
+            }
+
+            keep
+        });
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena, with
+    /// mutable access to each value. Every value is marked as changed. See
+    /// [`Arena::iter_mut`].
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        for (index, _) in self.arena.iter() {
+            mark_changed(&mut self.ticks, self.tick, index.slot);
+        }
+
+        self.arena.iter_mut()
+    }
+
+    /// Iterate over all of the values contained in the arena, with mutable
+    /// access to each value. Every value is marked as changed. See
+    /// [`Arena::values_mut`].
+    pub fn values_mut(&mut self) -> crate::iter::ValuesMut<'_, T> {
+        for (index, _) in self.arena.iter() {
+            mark_changed(&mut self.ticks, self.tick, index.slot);
+        }
+
+        self.arena.values_mut()
+    }
+
+    /// Gets the given key's corresponding entry in the arena for in-place
+    /// manipulation. See [`Arena::entry`].
+    pub fn entry(&mut self, index: Index) -> Entry<'_, T> {
+        if self.arena.contains(index) {
+            Entry::Occupied(OccupiedEntry { arena: self, index })
+        } else {
+            Entry::Vacant(VacantEntry { arena: self, index })
+        }
+    }
+
+    /// Gets a vacant entry for the index the next call to
+    /// [`TrackedArena::insert`] would produce. See [`Arena::vacant_entry`].
+    pub fn vacant_entry(&mut self) -> VacantEntry<'_, T> {
+        let index = self.arena.next_index();
+        VacantEntry { arena: self, index }
+    }
+
+    fn ticks(&self, index: Index) -> Option<&Ticks> {
+        if !self.arena.contains(index) {
+            return None;
+        }
+
+        self.ticks.get(index.slot as usize)
+    }
+
+    fn mark_added(&mut self, slot: u32) {
+        let ticks = ticks_mut(&mut self.ticks, slot);
+        ticks.added = self.tick;
+        ticks.changed = self.tick;
+    }
+
+    /// Record an insertion into a slot that held `old_index` before, if it was
+    /// occupied.
+    fn mark_replaced(&mut self, old_index: Option<Index>, index: Index) {
+        match old_index {
+            Some(old_index) if old_index == index => {
+                mark_changed(&mut self.ticks, self.tick, index.slot)
+            }
+            Some(old_index) => {
+                self.removed.push_back((self.tick, old_index));
+                self.mark_added(index.slot);
+            }
+            None => self.mark_added(index.slot),
+        }
+    }
+}
+
+fn ticks_mut(ticks: &mut Vec<Ticks>, slot: u32) -> &mut Ticks {
+    let slot = slot as usize;
+    if ticks.len() <= slot {
+        ticks.resize(slot.saturating_add(1), Ticks::default());
+    }
+
+    &mut ticks[slot]
+}
+
+fn mark_changed(ticks: &mut Vec<Ticks>, tick: u64, slot: u32) {
+    ticks_mut(ticks, slot).changed = tick;
+}
+
+impl<T> Default for TrackedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Arena<T>> for TrackedArena<T> {
+    fn from(arena: Arena<T>) -> Self {
+        Self::from_arena(arena)
+    }
+}
+
+impl<T> ops::Deref for TrackedArena<T> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        &self.arena
+    }
+}
+
+impl<T> ops::Index<Index> for TrackedArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        &self.arena[index]
+    }
+}
+
+impl<T> ops::IndexMut<Index> for TrackedArena<T> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+/// See [`TrackedArena::changed_since`].
+#[derive(Debug, Clone)]
+pub struct ChangedSince<'a, T> {
+    inner: Iter<'a, T>,
+    ticks: &'a [Ticks],
+    tick: u64,
+}
+
+impl<'a, T> Iterator for ChangedSince<'a, T> {
+    type Item = (Index, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (ticks, tick) = (self.ticks, self.tick);
+        self.inner
+            .find(|(index, _)| ticks[index.slot as usize].changed >= tick)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<T> DoubleEndedIterator for ChangedSince<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (ticks, tick) = (self.ticks, self.tick);
+        self.inner
+            .rfind(|(index, _)| ticks[index.slot as usize].changed >= tick)
+    }
+}
+
+impl<T> FusedIterator for ChangedSince<'_, T> {}
+
+/// See [`TrackedArena::added_since`].
+#[derive(Debug, Clone)]
+pub struct AddedSince<'a, T> {
+    inner: Iter<'a, T>,
+    ticks: &'a [Ticks],
+    tick: u64,
+}
+
+impl<'a, T> Iterator for AddedSince<'a, T> {
+    type Item = (Index, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (ticks, tick) = (self.ticks, self.tick);
+        self.inner
+            .find(|(index, _)| ticks[index.slot as usize].added >= tick)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+impl<T> DoubleEndedIterator for AddedSince<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (ticks, tick) = (self.ticks, self.tick);
+        self.inner
+            .rfind(|(index, _)| ticks[index.slot as usize].added >= tick)
+    }
+}
+
+impl<T> FusedIterator for AddedSince<'_, T> {}
+
+/// See [`TrackedArena::removed_since`].
+#[derive(Debug, Clone)]
+pub struct RemovedSince<'a> {
+    inner: RemovedLog<'a>,
+}
+
+type RemovedLog<'a> = <&'a VecDeque<(u64, Index)> as IntoIterator>::IntoIter;
+
+impl Iterator for RemovedSince<'_> {
+    type Item = Index;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|&(_, index)| index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for RemovedSince<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|&(_, index)| index)
+    }
+}
+
+impl ExactSizeIterator for RemovedSince<'_> {}
+impl FusedIterator for RemovedSince<'_> {}
+
+/// A view into a single entry in a [`TrackedArena`], which may either be
+/// vacant or occupied. See [`crate::Entry`].
+#[derive(Debug)]
+pub enum Entry<'a, T> {
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, T>),
+
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, T>),
+}
+
+/// A view into a vacant entry in a [`TrackedArena`].
+/// It is part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct VacantEntry<'a, T> {
+    arena: &'a mut TrackedArena<T>,
+    index: Index,
+}
+
+/// A view into an occupied entry in a [`TrackedArena`].
+/// It is part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, T> {
+    arena: &'a mut TrackedArena<T>,
+    index: Index,
+}
+
+impl<'a, T> Entry<'a, T> {
+    /// Ensures a value is in the entry by inserting the default if empty, and
+    /// returns a mutable reference to the value in the entry.
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function if empty, and returns a mutable reference to the value in the
+    /// entry.
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of
+    /// the default function, which is given the entry's key.
+    pub fn or_insert_with_key<F: FnOnce(Index) -> T>(self, default: F) -> &'a mut T {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Returns this entry's key.
+    pub fn key(&self) -> Index {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the arena.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut T),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, T: Default> Entry<'a, T> {
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut T {
+        self.or_insert_with(Default::default)
+    }
+}
+
+impl<'a, T> VacantEntry<'a, T> {
+    /// Gets the key that would be used when inserting a value through the
+    /// `VacantEntry`.
+    pub fn key(&self) -> Index {
+        self.index
+    }
+
+    /// Sets the value of the entry with the `VacantEntry`'s key,
+    /// and returns a mutable reference to it.
+    pub fn insert(self, value: T) -> &'a mut T {
+        self.arena.insert_at(self.index, value);
+        self.arena
+            .arena
+            .get_mut(self.index)
+            .unwrap_or_else(|| unreachable!("insert_at must create an occupied entry"))
+    }
+}
+
+impl<'a, T> OccupiedEntry<'a, T> {
+    /// Gets the key in the entry.
+    pub fn key(&self) -> Index {
+        self.index
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &T {
+        self.arena
+            .arena
+            .get(self.index)
+            .unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"))
+    }
+
+    /// Gets a mutable reference to the value in the entry, marking it as
+    /// changed.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.arena
+            .get_mut(self.index)
+            .unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"))
+    }
+
+    /// Converts the entry into a mutable reference to its value, marking it as
+    /// changed.
+    pub fn into_mut(self) -> &'a mut T {
+        self.arena
+            .get_mut(self.index)
+            .unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"))
+    }
+
+    /// Sets the value of the entry with the `OccupiedEntry`'s key,
+    /// and returns the entry's old value.
+    pub fn insert(&mut self, value: T) -> T {
+        replace(self.get_mut(), value)
+    }
+
+    /// Takes the value of the entry out of the arena, and returns it.
+    pub fn remove(self) -> T {
+        self.arena
+            .remove(self.index)
+            .unwrap_or_else(|| unreachable!("OccupiedEntry points to a vacant slot"))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::{Entry, TrackedArena};
+    use crate::{Arena, Index};
+
+    fn changed(arena: &TrackedArena<u32>, tick: u64) -> Vec<Index> {
+        arena.changed_since(tick).map(|(index, _)| index).collect()
+    }
+
+    fn added(arena: &TrackedArena<u32>, tick: u64) -> Vec<Index> {
+        arena.added_since(tick).map(|(index, _)| index).collect()
+    }
+
+    #[test]
+    fn mutable_access_marks_changes() {
+        let mut arena = TrackedArena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+
+        let tick = arena.advance_tick();
+        assert!(changed(&arena, tick).is_empty());
+
+        *arena.get_mut(a).unwrap() = 10;
+        assert_eq!(changed(&arena, tick), vec![a]);
+
+        let tick = arena.advance_tick();
+        arena[b] = 20;
+        let _ = arena.get(c);
+        assert_eq!(changed(&arena, tick), vec![b]);
+
+        let tick = arena.advance_tick();
+        if let Entry::Occupied(mut entry) = arena.entry(c) {
+            *entry.get_mut() = 30;
+        }
+        assert_eq!(changed(&arena, tick), vec![c]);
+        assert_eq!(arena.changed_tick(c), Some(tick));
+        assert_eq!(arena.added_tick(c), Some(0));
+
+        let tick = arena.advance_tick();
+        arena.values_mut().for_each(|_| {});
+        assert_eq!(changed(&arena, tick), vec![a, b, c]);
+        assert!(added(&arena, tick).is_empty());
+    }
+
+    #[test]
+    fn additions_and_removals() {
+        let mut arena = TrackedArena::from(Arena::new());
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+
+        let tick = arena.advance_tick();
+        arena.remove(a);
+        let c = arena.insert(3);
+        let new_b = arena.invalidate(b).unwrap();
+        let d = arena.entry(Index::from_bits(0x0000_0001_0000_0005).unwrap());
+        let d = match d {
+            Entry::Vacant(entry) => {
+                let key = entry.key();
+                entry.insert(4);
+                key
+            }
+            Entry::Occupied(_) => unreachable!(),
+        };
+
+        assert_eq!(added(&arena, tick), vec![c, new_b, d]);
+        assert_eq!(arena.removed_since(tick).collect::<Vec<_>>(), vec![a, b]);
+        assert_eq!(arena.removed_since(0).len(), 2);
+
+        let next = arena.advance_tick();
+        arena.retain(|_, value| *value != 3);
+        assert_eq!(arena.removed_since(next).collect::<Vec<_>>(), vec![c]);
+        assert_eq!(changed(&arena, next), vec![new_b, d]);
+
+        arena.forget_removed_before(next);
+        assert_eq!(arena.removed_since(0).collect::<Vec<_>>(), vec![c]);
+    }
+
+    #[test]
+    fn insert_at_replacing() {
+        let mut arena = TrackedArena::new();
+        let a = arena.insert(1);
+
+        let tick = arena.advance_tick();
+        arena.insert_at(a, 2);
+        assert_eq!(changed(&arena, tick), vec![a]);
+        assert!(added(&arena, tick).is_empty());
+
+        let tick = arena.advance_tick();
+        let (b, old) = arena.insert_at_slot(a.slot(), 3);
+        assert_eq!(old, Some(2));
+        assert_eq!(added(&arena, tick), vec![b]);
+        assert_eq!(arena.removed_since(tick).collect::<Vec<_>>(), vec![a]);
+    }
+}
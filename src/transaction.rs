@@ -0,0 +1,462 @@
+use core::mem::replace;
+use core::ops;
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+
+use crate::arena::{Arena, EmptySlot, Index, OccupiedSlot, Slot};
+use crate::free_pointer::FreePointer;
+use crate::generation::Generation;
+use crate::quarantine::Quarantine;
+use crate::storage::{SlotStorage, SlotStorageMut};
+
+/// A set of changes to an [`Arena`] that are either all kept or all undone.
+///
+/// Created with [`Arena::begin`] or [`Arena::transaction`]. Changes are made
+/// to the arena right away, and [`Transaction::rollback`] undoes them,
+/// restoring values, generations, and the exact order of the free list. A
+/// transaction that is dropped without calling [`Transaction::commit`] is
+/// rolled back.
+///
+/// The arena can be read through the transaction, which dereferences to
+/// [`Arena`].
+///
+/// Values that existed before the transaction began are kept around until the
+/// transaction ends so they can be restored. Because of that, removing or
+/// replacing such a value through a transaction returns `Some(None)` instead
+/// of the old value. Values inserted during the transaction are returned as
+/// usual.
+#[derive(Debug)]
+pub struct Transaction<'a, T> {
+    arena: &'a mut Arena<T>,
+
+    /// The original state of slots that were empty when the transaction began
+    /// and have been changed.
+    empty: BTreeMap<u32, EmptySlot>,
+
+    /// The original state of occupied slots that have been changed.
+    saved: BTreeMap<u32, Saved<T>>,
+
+    storage_len: usize,
+    len: u32,
+    first_free: Option<FreePointer>,
+    last_free: Option<FreePointer>,
+    quarantine: Quarantine,
+    committed: bool,
+}
+
+#[derive(Debug)]
+enum Saved<T> {
+    /// Only the generation of the slot has changed; the value in the arena is
+    /// still the original one.
+    Generation(Generation),
+
+    /// The original value, which has been moved out of the arena.
+    Value(OccupiedSlot<T>),
+}
+
+impl<T> Arena<T> {
+    /// Begin a transaction, which undoes every change made through it unless
+    /// it is committed.
+    pub fn begin(&mut self) -> Transaction<'_, T> {
+        Transaction {
+            empty: BTreeMap::new(),
+            saved: BTreeMap::new(),
+            storage_len: self.storage.len(),
+            len: self.len,
+            first_free: self.first_free,
+            last_free: self.last_free,
+            quarantine: self.quarantine.clone(),
+            committed: false,
+            arena: self,
+        }
+    }
+
+    /// Run a closure inside a transaction. The transaction is committed if the
+    /// closure returns `Ok`, and rolled back if it returns `Err` or panics.
+    ///
+    /// ```rust
+    /// # use thunderdome::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert("a");
+    /// let next = arena.next_index();
+    ///
+    /// let result: Result<(), &str> = arena.transaction(|tx| {
+    ///     tx.remove(a);
+    ///     tx.insert("b");
+    ///     Err("something went wrong")
+    /// });
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(arena[a], "a");
+    /// assert_eq!(arena.next_index(), next);
+    /// ```
+    pub fn transaction<R, E, F>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Transaction<'_, T>) -> Result<R, E>,
+    {
+        let mut transaction = self.begin();
+        let result = f(&mut transaction);
+
+        if result.is_ok() {
+            transaction.commit();
+        }
+
+        result
+    }
+}
+
+impl<T> Transaction<'_, T> {
+    /// Keep every change made in the transaction.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    /// Undo every change made in the transaction.
+    pub fn rollback(self) {}
+
+    /// Insert a new value. See [`Arena::insert`].
+    pub fn insert(&mut self, value: T) -> Index {
+        if let Some(head) = self.arena.first_free {
+            self.save_empty(head.slot());
+        }
+
+        self.arena.insert(value)
+    }
+
+    /// Insert a value at the given index. See [`Arena::insert_at`].
+    ///
+    /// Returns `None` if no value was replaced, and `Some(None)` if the
+    /// replaced value existed before the transaction began.
+    pub fn insert_at(&mut self, index: Index, value: T) -> Option<Option<T>> {
+        match self.arena.storage.get(index.slot as usize) {
+            Some(Slot::Occupied(occupied)) => {
+                let generation = occupied.generation;
+                let old = self
+                    .arena
+                    .insert_at(index, value)
+                    .unwrap_or_else(|| unreachable!());
+
+                return Some(self.keep_original(index.slot, generation, old));
+            }
+            Some(Slot::Empty(_)) => {
+                self.save_empty(index.slot);
+
+                // Quarantined slots aren't part of the free list.
+                let quarantined = self.arena.quarantined().any(|(_, slot)| slot == index.slot);
+                if !quarantined {
+                    if let Some(prev) = self.arena.free_list_predecessor(index.slot) {
+                        self.save_empty(prev);
+                    }
+                }
+            }
+            None => {
+                // New slots are linked after the old tail of the free list,
+                // unless the arena reuses slots last in first out.
+                if let Some(tail) = self.arena.last_free {
+                    self.save_empty(tail.slot());
+                }
+            }
+        }
+
+        self.arena.insert_at(index, value);
+        None
+    }
+
+    /// Remove the value at the given index. See [`Arena::remove`].
+    ///
+    /// Returns `None` if there was no value to remove, and `Some(None)` if the
+    /// removed value existed before the transaction began.
+    pub fn remove(&mut self, index: Index) -> Option<Option<T>> {
+        if !self.arena.contains(index) {
+            return None;
+        }
+
+        if let (Some(prev), _) = self.arena.free_list_position(index.slot) {
+            self.save_empty(prev.slot());
+        }
+
+        let value = self.arena.remove(index).unwrap_or_else(|| unreachable!());
+        Some(self.keep_original(index.slot, index.generation, value))
+    }
+
+    /// Give the value at the given index a new generation. See
+    /// [`Arena::invalidate`].
+    pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        if self.arena.contains(index) {
+            self.preserve(index.slot, index.generation, None);
+        }
+
+        self.arena.invalidate(index)
+    }
+
+    /// Replace the value at the given index, keeping its generation.
+    ///
+    /// Returns `None` if there was no value to replace, and `Some(None)` if the
+    /// replaced value existed before the transaction began.
+    pub fn replace(&mut self, index: Index, value: T) -> Option<Option<T>> {
+        let old = replace(self.arena.get_mut(index)?, value);
+        Some(self.keep_original(index.slot, index.generation, old))
+    }
+
+    /// Get a mutable reference to the value at the given index. Values that
+    /// existed before the transaction began are cloned the first time they are
+    /// borrowed this way, so that they can be restored.
+    pub fn get_mut(&mut self, index: Index) -> Option<&mut T>
+    where
+        T: Clone,
+    {
+        let value = self.arena.get(index)?;
+
+        if self.needs_value(index.slot) {
+            let value = value.clone();
+            self.preserve(index.slot, index.generation, Some(value));
+        }
+
+        self.arena.get_mut(index)
+    }
+
+    /// Returns true if the slot was occupied when the transaction began. Only
+    /// meaningful for slots that are occupied or have been changed, because
+    /// empty slots are only saved once they are changed.
+    fn was_occupied(&self, slot: u32) -> bool {
+        (slot as usize) < self.storage_len && !self.empty.contains_key(&slot)
+    }
+
+    /// Remember the original state of a slot that was empty when the
+    /// transaction began, if it is about to change for the first time.
+    fn save_empty(&mut self, slot: u32) {
+        // Slots past the original end are truncated, and slots that were
+        // occupied are restored from `saved`.
+        if (slot as usize) >= self.storage_len || self.saved.contains_key(&slot) {
+            return;
+        }
+
+        if let Slot::Empty(empty) = &self.arena.storage[slot as usize] {
+            self.empty.entry(slot).or_insert(*empty);
+        }
+    }
+
+    /// Hand back a value that was just moved out of the arena, unless it's the
+    /// original value of the slot and has to be kept to be restored.
+    fn keep_original(&mut self, slot: u32, generation: Generation, value: T) -> Option<T> {
+        if self.needs_value(slot) {
+            self.preserve(slot, generation, Some(value));
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Returns true if the original value of the slot would be lost if the
+    /// value in the arena was changed.
+    fn needs_value(&self, slot: u32) -> bool {
+        self.was_occupied(slot) && !matches!(self.saved.get(&slot), Some(Saved::Value(_)))
+    }
+
+    /// Remember the original state of a slot that is about to change, or has
+    /// just had its value moved out. `generation` is the generation of the
+    /// slot before the change, and `value` is the value that was moved out, if
+    /// any.
+    fn preserve(&mut self, slot: u32, generation: Generation, value: Option<T>) {
+        if !self.was_occupied(slot) {
+            return;
+        }
+
+        match (self.saved.get(&slot), value) {
+            (None, None) => {
+                self.saved.insert(slot, Saved::Generation(generation));
+            }
+            (None, Some(value)) => {
+                self.saved
+                    .insert(slot, Saved::Value(OccupiedSlot { generation, value }));
+            }
+            // The value in the arena was still the original, so keep it along
+            // with the original generation.
+            (Some(&Saved::Generation(generation)), Some(value)) => {
+                self.saved
+                    .insert(slot, Saved::Value(OccupiedSlot { generation, value }));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<T> Drop for Transaction<'_, T> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+
+        let arena = &mut *self.arena;
+        arena.touch();
+        arena.storage.truncate(self.storage_len);
+
+        for (&slot, &empty) in &self.empty {
+            arena.storage[slot as usize] = Slot::Empty(empty);
+        }
+
+        for (slot, saved) in core::mem::take(&mut self.saved) {
+            match (saved, &mut arena.storage[slot as usize]) {
+                (Saved::Generation(generation), Slot::Occupied(occupied)) => {
+                    occupied.generation = generation;
+                }
+                (Saved::Generation(_), Slot::Empty(_)) => {
+                    unreachable!("a slot whose value was moved out only saved its generation")
+                }
+                (Saved::Value(occupied), entry) => *entry = Slot::Occupied(occupied),
+            }
+        }
+
+        arena.len = self.len;
+        arena.first_free = self.first_free;
+        arena.last_free = self.last_free;
+        arena.quarantine = replace(&mut self.quarantine, Quarantine::new());
+    }
+}
+
+impl<T> ops::Deref for Transaction<'_, T> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        self.arena
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use crate::test_util::{assert_same_state, POLICIES};
+    use crate::{Arena, Index, ReusePolicy};
+
+    fn sample(policy: ReusePolicy) -> (Arena<u32>, Vec<Index>) {
+        let mut arena = Arena::with_reuse_policy(policy);
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[3]);
+        arena.remove(indices[7]);
+        arena.remove(indices[5]);
+        (arena, indices)
+    }
+
+    #[test]
+    fn rollback_restores_everything() {
+        for (&policy, epochs) in POLICIES.iter().flat_map(|p| [(p, 0), (p, 2)]) {
+            let (mut arena, indices) = sample(policy);
+            arena.set_quarantine(epochs);
+            let before = arena.clone();
+            let next = arena.next_index();
+
+            let mut tx = arena.begin();
+            let new = tx.insert(100);
+            tx.remove(indices[0]);
+            tx.remove(new);
+            let invalidated = tx.invalidate(indices[1]).unwrap();
+            *tx.get_mut(invalidated).unwrap() = 11;
+            tx.remove(invalidated);
+            tx.replace(indices[2], 22);
+            *tx.get_mut(indices[2]).unwrap() = 23;
+            tx.invalidate(indices[4]);
+            tx.insert_at(indices[6], 66);
+            tx.insert_at(Index::from_bits(0x0000_0001_0000_0028).unwrap(), 40);
+            tx.insert(200);
+            tx.insert(300);
+            tx.rollback();
+
+            assert_same_state(&arena, &before);
+            assert_eq!(arena.next_index(), next);
+        }
+    }
+
+    #[test]
+    fn returns_new_values() {
+        let (mut arena, indices) = sample(ReusePolicy::Lifo);
+        let before = arena.clone();
+
+        let mut tx = arena.begin();
+        let new = tx.insert(100);
+        assert_eq!(tx.replace(new, 101), Some(Some(100)));
+        assert_eq!(tx.remove(new), Some(Some(101)));
+        assert_eq!(tx.remove(new), None);
+
+        assert_eq!(tx.replace(indices[0], 10), Some(None));
+        assert_eq!(tx.replace(indices[0], 20), Some(Some(10)));
+        assert_eq!(tx.insert_at(indices[0], 30), Some(Some(20)));
+        assert_eq!(tx.remove(indices[0]), Some(Some(30)));
+
+        assert_eq!(tx.remove(indices[1]), Some(None));
+        assert_eq!(tx.insert_at(indices[2], 22), Some(None));
+        assert_eq!(tx.insert_at(indices[3], 33), None);
+        tx.rollback();
+
+        assert_same_state(&arena, &before);
+    }
+
+    #[test]
+    fn commit_keeps_changes() {
+        let (mut arena, indices) = sample(ReusePolicy::Lifo);
+        let mut expected = arena.clone();
+
+        let mut tx = arena.begin();
+        let a = tx.insert(100);
+        tx.remove(indices[0]);
+        tx.replace(indices[1], 11);
+        tx.commit();
+
+        assert_eq!(expected.insert(100), a);
+        expected.remove(indices[0]);
+        expected[indices[1]] = 11;
+        assert_same_state(&arena, &expected);
+    }
+
+    #[test]
+    fn drop_rolls_back() {
+        let (mut arena, indices) = sample(ReusePolicy::Fifo);
+        let before = arena.clone();
+
+        {
+            let mut tx = arena.begin();
+            tx.remove(indices[0]);
+            tx.insert(5);
+            assert_eq!(tx.len(), 7);
+        }
+
+        assert_same_state(&arena, &before);
+    }
+
+    #[test]
+    fn transaction_closure() {
+        let (mut arena, indices) = sample(ReusePolicy::LowestSlot);
+        arena.set_quarantine(1);
+        arena.remove(indices[8]);
+        let before = arena.clone();
+
+        let result: Result<(), ()> = arena.transaction(|tx| {
+            tx.remove(indices[9]);
+            tx.insert(1);
+            Err(())
+        });
+        assert!(result.is_err());
+        assert_same_state(&arena, &before);
+
+        let index = arena.transaction(|tx| Ok::<_, ()>(tx.insert(1))).unwrap();
+        assert_eq!(arena[index], 1);
+    }
+
+    #[test]
+    fn panic_rolls_back() {
+        let (mut arena, indices) = sample(ReusePolicy::Lifo);
+        let before = arena.clone();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = arena.transaction(|tx| -> Result<(), ()> {
+                tx.remove(indices[0]);
+                panic!("oh no");
+            });
+        }));
+
+        assert!(result.is_err());
+        assert_same_state(&arena, &before);
+    }
+}
@@ -0,0 +1,692 @@
+use core::mem::{replace, swap};
+use core::ops;
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::VecDeque, string::String, vec, vec::Vec};
+
+use crate::arena::{Arena, Index, OccupiedSlot, Slot};
+use crate::free_pointer::FreePointer;
+use crate::generation::Generation;
+use crate::storage::{SlotStorage, SlotStorageMut};
+
+/// An [`Arena`] that records its changes so they can be undone and redone.
+///
+/// Changes are grouped into named steps with [`UndoArena::begin_step`] and
+/// [`UndoArena::end_step`]. A change made outside of a step becomes a step of
+/// its own, named after the operation. [`UndoArena::undo`] and
+/// [`UndoArena::redo`] restore the arena exactly, including generations and the
+/// free list, so redoing an insertion recreates the same [`Index`] and indices
+/// stay valid across undo and redo.
+///
+/// Making a change after undoing discards the steps that could have been
+/// redone. The number of steps kept can be limited with
+/// [`UndoArena::with_history_limit`].
+///
+/// The arena can be read through the `UndoArena`, which dereferences to
+/// [`Arena`]. Quarantine works like it does for [`Arena`], and
+/// [`UndoArena::advance_epoch`] is recorded like any other change, so that
+/// undoing it puts released slots back into quarantine.
+///
+/// ```rust
+/// # use thunderdome::UndoArena;
+/// let mut arena = UndoArena::new();
+///
+/// arena.begin_step("create");
+/// let a = arena.insert("a");
+/// let b = arena.insert("b");
+/// arena.end_step();
+///
+/// arena.remove(a);
+/// assert_eq!(arena.undo_name(), Some("remove"));
+///
+/// arena.undo();
+/// arena.undo();
+/// assert!(arena.is_empty());
+///
+/// arena.redo();
+/// assert_eq!(arena[a], "a");
+/// assert_eq!(arena[b], "b");
+/// ```
+#[derive(Debug, Clone)]
+pub struct UndoArena<T> {
+    arena: Arena<T>,
+
+    /// Steps that can be undone, followed by steps that can be redone.
+    history: VecDeque<Step<T>>,
+
+    /// The number of steps in `history` that can be undone.
+    position: usize,
+
+    /// The step being recorded, if one was started with `begin_step`.
+    current: Option<Step<T>>,
+
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Step<T> {
+    name: String,
+    patches: Vec<Patch<T>>,
+}
+
+/// The parts of an arena that one operation changed. Applying a patch swaps
+/// its contents with the arena's, so the same patch first undoes its
+/// operation and then redoes it.
+#[derive(Debug, Clone)]
+struct Patch<T> {
+    slots: Vec<(u32, SlotImage<T>)>,
+
+    /// Slots past `storage_len` in the other state of the arena.
+    tail: Vec<Slot<T>>,
+
+    storage_len: usize,
+    len: u32,
+    first_free: Option<FreePointer>,
+    last_free: Option<FreePointer>,
+    epoch: u64,
+
+    /// The quarantined slots, saved only if the operation can change them.
+    pending: Option<VecDeque<(u64, u32)>>,
+}
+
+#[derive(Debug, Clone)]
+enum SlotImage<T> {
+    /// The whole slot.
+    Slot(Slot<T>),
+
+    /// Only the generation of an occupied slot, whose value didn't change.
+    Generation(Generation),
+}
+
+impl<T> Patch<T> {
+    /// Start a patch for an operation that is about to be made to `arena`.
+    fn before(arena: &Arena<T>) -> Self {
+        // Without quarantine, nothing can be quarantined by the operation.
+        let quarantine = &arena.quarantine;
+        let pending = (quarantine.epochs > 0 || !quarantine.pending.is_empty())
+            .then(|| quarantine.pending.clone());
+
+        Patch {
+            slots: Vec::new(),
+            tail: Vec::new(),
+            storage_len: arena.storage.len(),
+            len: arena.len,
+            first_free: arena.first_free,
+            last_free: arena.last_free,
+            epoch: quarantine.epoch,
+            pending,
+        }
+    }
+
+    /// Remember an empty slot before the operation changes it.
+    fn save_empty(&mut self, arena: &Arena<T>, slot: u32) {
+        let empty = *arena.empty_slot(slot);
+        self.slots.push((slot, SlotImage::Slot(Slot::Empty(empty))));
+    }
+
+    /// Like `save_empty`, but does nothing if the slot was already saved.
+    /// Saving a slot twice would restore the second image instead of the first.
+    fn save_empty_once(&mut self, arena: &Arena<T>, slot: u32) {
+        if !self.slots.iter().any(|&(saved, _)| saved == slot) {
+            self.save_empty(arena, slot);
+        }
+    }
+
+    fn swap(&mut self, arena: &mut Arena<T>) {
+        arena.touch();
+
+        let storage_len = arena.storage.len();
+        if self.storage_len < storage_len {
+            self.tail = arena.storage.split_off(self.storage_len);
+        } else {
+            arena.storage.append(&mut self.tail);
+        }
+        self.storage_len = storage_len;
+
+        for (slot, image) in &mut self.slots {
+            let entry = &mut arena.storage[*slot as usize];
+
+            match (image, entry) {
+                (SlotImage::Slot(image), entry) => swap(image, entry),
+                (SlotImage::Generation(generation), Slot::Occupied(occupied)) => {
+                    swap(generation, &mut occupied.generation)
+                }
+                (SlotImage::Generation(_), Slot::Empty(_)) => {
+                    unreachable!("generation patch applied to an empty slot")
+                }
+            }
+        }
+
+        swap(&mut self.len, &mut arena.len);
+        swap(&mut self.first_free, &mut arena.first_free);
+        swap(&mut self.last_free, &mut arena.last_free);
+        swap(&mut self.epoch, &mut arena.quarantine.epoch);
+
+        if let Some(pending) = &mut self.pending {
+            swap(pending, &mut arena.quarantine.pending);
+        }
+    }
+}
+
+impl<T> UndoArena<T> {
+    /// Construct an empty `UndoArena` with no history limit.
+    pub fn new() -> Self {
+        Self::from_arena(Arena::new())
+    }
+
+    /// Construct an empty `UndoArena` that keeps at most `limit` steps of
+    /// history.
+    pub fn with_history_limit(limit: usize) -> Self {
+        let mut arena = Self::new();
+        arena.set_history_limit(Some(limit));
+        arena
+    }
+
+    /// Start recording changes to an existing arena.
+    pub fn from_arena(arena: Arena<T>) -> Self {
+        UndoArena {
+            arena,
+            history: VecDeque::new(),
+            position: 0,
+            current: None,
+            limit: None,
+        }
+    }
+
+    /// Stop recording changes, returning the arena.
+    pub fn into_arena(self) -> Arena<T> {
+        self.arena
+    }
+
+    /// Limit the number of steps kept, dropping the oldest steps when there
+    /// are too many. `None` removes the limit.
+    pub fn set_history_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.enforce_limit();
+    }
+
+    /// Return the maximum number of steps kept. See
+    /// [`UndoArena::set_history_limit`].
+    pub fn history_limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Forget every step, keeping the arena as it is.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.position = 0;
+        self.current = None;
+    }
+
+    /// Group the changes made from now until [`UndoArena::end_step`] into one
+    /// step with the given name. If a step is already being recorded, it is
+    /// ended first.
+    pub fn begin_step(&mut self, name: impl Into<String>) {
+        self.end_step();
+        self.current = Some(Step {
+            name: name.into(),
+            patches: Vec::new(),
+        });
+    }
+
+    /// Finish the step started with [`UndoArena::begin_step`]. Steps without
+    /// any changes are discarded.
+    pub fn end_step(&mut self) {
+        if let Some(step) = self.current.take() {
+            if !step.patches.is_empty() {
+                self.push_step(step);
+            }
+        }
+    }
+
+    /// Return the number of steps that can be undone.
+    pub fn undo_len(&self) -> usize {
+        self.position
+    }
+
+    /// Return the number of steps that can be redone.
+    pub fn redo_len(&self) -> usize {
+        self.history.len().saturating_sub(self.position)
+    }
+
+    /// Return the name of the step [`UndoArena::undo`] would undo.
+    pub fn undo_name(&self) -> Option<&str> {
+        let position = self.position.checked_sub(1)?;
+        Some(self.history[position].name.as_str())
+    }
+
+    /// Return the name of the step [`UndoArena::redo`] would redo.
+    pub fn redo_name(&self) -> Option<&str> {
+        self.history
+            .get(self.position)
+            .map(|step| step.name.as_str())
+    }
+
+    /// Undo the last step, ending the step being recorded first. Returns false
+    /// if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.end_step();
+
+        let position = match self.position.checked_sub(1) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        for patch in self.history[position].patches.iter_mut().rev() {
+            patch.swap(&mut self.arena);
+        }
+
+        self.position = position;
+        true
+    }
+
+    /// Redo the last step that was undone. Returns false if there was nothing
+    /// to redo.
+    pub fn redo(&mut self) -> bool {
+        self.end_step();
+
+        let step = match self.history.get_mut(self.position) {
+            Some(step) => step,
+            None => return false,
+        };
+
+        for patch in &mut step.patches {
+            patch.swap(&mut self.arena);
+        }
+
+        self.position = self
+            .position
+            .checked_add(1)
+            .unwrap_or_else(|| unreachable!());
+        true
+    }
+
+    /// Quarantine slots freed from now on for the given number of epochs. See
+    /// [`Arena::set_quarantine`].
+    ///
+    /// This is a setting rather than a change to the arena, so it is not
+    /// recorded and can't be undone.
+    pub fn set_quarantine(&mut self, epochs: u32) {
+        self.arena.set_quarantine(epochs);
+    }
+
+    /// Advance the arena to its next epoch, releasing every quarantined slot
+    /// whose quarantine has expired. See [`Arena::advance_epoch`].
+    pub fn advance_epoch(&mut self) {
+        let mut patch = Patch::before(&self.arena);
+
+        self.arena.quarantine.advance();
+        while let Some(slot) = self.arena.quarantine.pop_expired() {
+            patch.save_empty_once(&self.arena, slot);
+            if let (Some(prev), _) = self.arena.free_list_position(slot) {
+                patch.save_empty_once(&self.arena, prev.slot());
+            }
+
+            self.arena.push_free(slot);
+        }
+
+        self.record("advance_epoch", patch);
+    }
+
+    /// Insert a new value. See [`Arena::insert`].
+    pub fn insert(&mut self, value: T) -> Index {
+        let mut patch = Patch::before(&self.arena);
+        if let Some(head) = self.arena.first_free {
+            patch.save_empty(&self.arena, head.slot());
+        }
+
+        let index = self.arena.insert(value);
+        self.record("insert", patch);
+        index
+    }
+
+    /// Insert a value at the given index. See [`Arena::insert_at`].
+    ///
+    /// Returns true if a value was replaced. The replaced value is kept in the
+    /// history so that it can be restored.
+    pub fn insert_at(&mut self, index: Index, value: T) -> bool {
+        let mut patch = Patch::before(&self.arena);
+
+        let replaced = match self.arena.storage.get(index.slot as usize) {
+            Some(Slot::Occupied(occupied)) => {
+                let generation = occupied.generation;
+                let old = self
+                    .arena
+                    .insert_at(index, value)
+                    .unwrap_or_else(|| unreachable!());
+
+                patch.slots.push((
+                    index.slot,
+                    SlotImage::Slot(Slot::Occupied(OccupiedSlot {
+                        generation,
+                        value: old,
+                    })),
+                ));
+
+                true
+            }
+            Some(Slot::Empty(_)) => {
+                patch.save_empty(&self.arena, index.slot);
+
+                // Quarantined slots aren't part of the free list.
+                let quarantined = self.arena.quarantined().any(|(_, slot)| slot == index.slot);
+                if !quarantined {
+                    if let Some(prev) = self.arena.free_list_predecessor(index.slot) {
+                        patch.save_empty(&self.arena, prev);
+                    }
+                }
+
+                self.arena.insert_at(index, value);
+                false
+            }
+            None => {
+                // New slots are linked after the old tail of the free list,
+                // unless the arena reuses slots last in first out.
+                if let Some(tail) = self.arena.last_free {
+                    patch.save_empty(&self.arena, tail.slot());
+                }
+
+                self.arena.insert_at(index, value);
+                false
+            }
+        };
+
+        self.record("insert_at", patch);
+        replaced
+    }
+
+    /// Remove the value at the given index. See [`Arena::remove`].
+    ///
+    /// Returns true if there was a value to remove. The removed value is kept
+    /// in the history so that it can be restored.
+    pub fn remove(&mut self, index: Index) -> bool {
+        if !self.arena.contains(index) {
+            return false;
+        }
+
+        let mut patch = Patch::before(&self.arena);
+        if let (Some(prev), _) = self.arena.free_list_position(index.slot) {
+            patch.save_empty(&self.arena, prev.slot());
+        }
+
+        let value = self.arena.remove(index).unwrap_or_else(|| unreachable!());
+
+        patch.slots.push((
+            index.slot,
+            SlotImage::Slot(Slot::Occupied(OccupiedSlot {
+                generation: index.generation,
+                value,
+            })),
+        ));
+
+        self.record("remove", patch);
+        true
+    }
+
+    /// Give the value at the given index a new generation. See
+    /// [`Arena::invalidate`].
+    pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        let mut patch = Patch::before(&self.arena);
+        let new_index = self.arena.invalidate(index)?;
+
+        patch
+            .slots
+            .push((index.slot, SlotImage::Generation(index.generation)));
+
+        self.record("invalidate", patch);
+        Some(new_index)
+    }
+
+    /// Replace the value at the given index, keeping its generation.
+    ///
+    /// Returns true if there was a value to replace. The replaced value is kept
+    /// in the history so that it can be restored.
+    pub fn replace(&mut self, index: Index, value: T) -> bool {
+        let mut patch = Patch::before(&self.arena);
+
+        let old = match self.arena.get_mut(index) {
+            Some(slot) => replace(slot, value),
+            None => return false,
+        };
+
+        patch.slots.push((
+            index.slot,
+            SlotImage::Slot(Slot::Occupied(OccupiedSlot {
+                generation: index.generation,
+                value: old,
+            })),
+        ));
+
+        self.record("replace", patch);
+        true
+    }
+
+    /// Change the value at the given index with a closure. The value is cloned
+    /// beforehand so that the change can be undone.
+    pub fn update<F, R>(&mut self, index: Index, f: F) -> Option<R>
+    where
+        T: Clone,
+        F: FnOnce(&mut T) -> R,
+    {
+        let mut value = self.arena.get(index)?.clone();
+        let result = f(&mut value);
+        self.replace(index, value);
+
+        Some(result)
+    }
+
+    fn record(&mut self, name: &str, patch: Patch<T>) {
+        // A change made after undoing means the undone steps can't be redone.
+        self.history.truncate(self.position);
+
+        match &mut self.current {
+            Some(step) => step.patches.push(patch),
+            None => self.push_step(Step {
+                name: name.into(),
+                patches: vec![patch],
+            }),
+        }
+    }
+
+    fn push_step(&mut self, step: Step<T>) {
+        self.history.truncate(self.position);
+        self.history.push_back(step);
+        self.position = self.history.len();
+        self.enforce_limit();
+    }
+
+    fn enforce_limit(&mut self) {
+        if let Some(limit) = self.limit {
+            // Drop the oldest undo steps first, then redo steps from the end,
+            // so the steps that are left are still contiguous.
+            while self.history.len() > limit && self.position > 0 {
+                self.history.pop_front();
+                self.position = self.position.saturating_sub(1);
+            }
+
+            self.history.truncate(limit);
+        }
+    }
+}
+
+impl<T> Default for UndoArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ops::Deref for UndoArena<T> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        &self.arena
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::UndoArena;
+    use crate::test_util::{assert_same_state, POLICIES};
+    use crate::{Arena, Index};
+
+    /// Make a mix of changes, one step per change, returning the arena's state
+    /// before the first change and after each one.
+    fn run(arena: &mut UndoArena<u32>) -> Vec<Arena<u32>> {
+        let mut states = vec![arena.arena.clone()];
+        let mut live: Vec<Index> = Vec::new();
+
+        for step in 0..30u32 {
+            match step % 8 {
+                0..=2 => live.push(arena.insert(step)),
+                3 => {
+                    let index = live.remove(1);
+                    arena.remove(index);
+                }
+                4 => {
+                    if let Some(index) = arena.invalidate(live[0]) {
+                        live[0] = index;
+                    }
+                }
+                5 => {
+                    arena.update(live[0], |value| *value = value.wrapping_mul(3));
+                }
+                6 => arena.advance_epoch(),
+                _ => {
+                    // Lands on occupied, empty, and brand new slots.
+                    let bits = 0x0000_0001_0000_0000 | u64::from(step);
+                    arena.insert_at(Index::from_bits(bits).unwrap(), step);
+                }
+            }
+
+            states.push(arena.arena.clone());
+        }
+
+        states
+    }
+
+    #[test]
+    fn undo_and_redo_every_step() {
+        for (&policy, quarantine) in POLICIES.iter().flat_map(|p| [(p, 0), (p, 2)]) {
+            let mut arena = Arena::with_reuse_policy(policy);
+            arena.set_quarantine(quarantine);
+            let mut arena = UndoArena::from_arena(arena);
+            let states = run(&mut arena);
+            assert_eq!(arena.undo_len(), states.len() - 1);
+
+            for expected in states.iter().rev().skip(1) {
+                assert!(arena.undo());
+                assert_same_state(&arena, expected);
+            }
+            assert!(!arena.undo());
+
+            for expected in states.iter().skip(1) {
+                assert!(arena.redo());
+                assert_same_state(&arena, expected);
+            }
+            assert!(!arena.redo());
+        }
+    }
+
+    #[test]
+    fn steps_group_changes() {
+        let mut arena = UndoArena::new();
+        let a = arena.insert(1);
+
+        arena.begin_step("edit");
+        arena.remove(a);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+        arena.end_step();
+
+        assert_eq!(arena.undo_name(), Some("edit"));
+        assert!(arena.undo());
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena[a], 1);
+        assert_eq!(arena.redo_name(), Some("edit"));
+
+        // Redoing recreates the same indices.
+        assert!(arena.redo());
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena[b], 2);
+        assert_eq!(arena[c], 3);
+    }
+
+    #[test]
+    fn new_changes_truncate_redo() {
+        let mut arena = UndoArena::new();
+        let a = arena.insert(1);
+        arena.insert(2);
+        arena.undo();
+        assert_eq!(arena.redo_len(), 1);
+
+        arena.replace(a, 10);
+        assert_eq!(arena.redo_len(), 0);
+        assert!(!arena.redo());
+
+        arena.undo();
+        assert_eq!(arena[a], 1);
+    }
+
+    #[test]
+    fn history_limit() {
+        let mut arena = UndoArena::with_history_limit(2);
+        let a = arena.insert(1);
+        arena.insert(2);
+        arena.insert(3);
+        assert_eq!(arena.undo_len(), 2);
+
+        assert!(arena.undo());
+        assert!(arena.undo());
+        assert!(!arena.undo());
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena[a], 1);
+
+        arena.set_history_limit(Some(0));
+        assert_eq!(arena.redo_len(), 0);
+    }
+
+    #[test]
+    fn history_limit_drops_redo_steps() {
+        let mut arena = UndoArena::new();
+        arena.insert(1);
+        arena.insert(2);
+        arena.insert(3);
+        assert!(arena.undo());
+        assert!(arena.undo());
+        assert!(arena.undo());
+
+        arena.set_history_limit(Some(2));
+        assert_eq!(arena.redo_len(), 2);
+        assert!(arena.redo());
+        assert!(arena.redo());
+        assert!(!arena.redo());
+
+        arena.insert(4);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.iter().count(), 3);
+    }
+
+    #[test]
+    fn quarantine_is_kept() {
+        let mut inner = Arena::new();
+        inner.set_quarantine(1);
+        let a = inner.insert(1);
+        inner.remove(a);
+
+        let mut arena = UndoArena::from_arena(inner);
+        assert_eq!(arena.quarantined_len(), 1);
+        assert_eq!(arena.quarantine(), 1);
+        assert_ne!(arena.next_index().slot(), a.slot());
+
+        arena.advance_epoch();
+        assert_eq!(arena.quarantined_len(), 0);
+        assert_eq!(arena.next_index().slot(), a.slot());
+
+        assert!(arena.undo());
+        assert_eq!(arena.quarantined_len(), 1);
+        assert_ne!(arena.next_index().slot(), a.slot());
+    }
+}